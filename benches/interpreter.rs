@@ -0,0 +1,279 @@
+use alloy_primitives::{keccak256, Address, U256};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use evm_disasm::asm::Assembler;
+use evm_disasm::context::BlockContext;
+use evm_disasm::state::{InMemoryStateDB, StateDB};
+use evm_disasm::vm::Interpreter;
+
+const FIB: &str = r#"
+    PUSH1 0x00          // a
+    PUSH1 0x01          // b
+    PUSH2 0x03e8        // n = 1000
+loop:
+    JUMPDEST
+    DUP1
+    ISZERO
+    PUSH2 @done
+    JUMPI
+    PUSH1 0x01
+    SWAP1
+    SUB                 // a b n-1
+    SWAP2
+    DUP2
+    ADD                 // n-1 b a+b
+    SWAP1
+    SWAP2               // b a+b n-1
+    PUSH2 @loop
+    JUMP
+done:
+    JUMPDEST
+    STOP
+"#;
+
+const ARITHMETIC: &str = r#"
+    PUSH2 0x0400        // n = 1024
+    PUSH1 0x07          // x
+loop:
+    JUMPDEST
+    DUP2
+    ISZERO
+    PUSH2 @done
+    JUMPI
+    PUSH1 0x03
+    MUL
+    PUSH1 0x05
+    ADD
+    PUSH1 0x11
+    SWAP1
+    MOD
+    DUP1
+    PUSH1 0x02
+    EXP
+    XOR
+    PUSH1 0x01
+    DUP3
+    SUB
+    SWAP2
+    POP                 // n-1 x'
+    PUSH2 @loop
+    JUMP
+done:
+    JUMPDEST
+    STOP
+"#;
+
+const KECCAK_LOOP: &str = r#"
+    PUSH2 0x0400        // n = 1024
+loop:
+    JUMPDEST
+    DUP1
+    ISZERO
+    PUSH2 @done
+    JUMPI
+    PUSH1 0x20
+    PUSH1 0x00
+    KECCAK256
+    PUSH1 0x00
+    MSTORE              // mem[0] = keccak256(mem[0..32])
+    PUSH1 0x01
+    SWAP1
+    SUB
+    PUSH2 @loop
+    JUMP
+done:
+    JUMPDEST
+    STOP
+"#;
+
+/// ERC-20 `transfer(address,uint256)`，余额存放在 mapping(address => uint256) slot 0
+const ERC20_TRANSFER: &str = r#"
+    PUSH1 0x00
+    CALLDATALOAD
+    PUSH29 0x0100000000000000000000000000000000000000000000000000000000
+    SWAP1
+    DIV                 // selector
+    PUSH4 0xa9059cbb
+    EQ
+    PUSH2 @transfer
+    JUMPI
+    PUSH1 0x00
+    DUP1
+    REVERT
+transfer:
+    JUMPDEST
+    CALLER
+    PUSH1 0x00
+    MSTORE
+    PUSH1 0x00
+    PUSH1 0x20
+    MSTORE
+    PUSH1 0x40
+    PUSH1 0x00
+    KECCAK256           // slot_from
+    DUP1
+    SLOAD               // slot_from balance_from
+    PUSH1 0x24
+    CALLDATALOAD        // slot_from balance_from amount
+    DUP1
+    DUP3
+    LT
+    PUSH2 @fail
+    JUMPI
+    DUP1
+    DUP3
+    SUB
+    DUP4
+    SSTORE              // balances[caller] -= amount
+    PUSH1 0x04
+    CALLDATALOAD
+    PUSH1 0x00
+    MSTORE
+    PUSH1 0x40
+    PUSH1 0x00
+    KECCAK256           // slot_from balance_from amount slot_to
+    DUP1
+    SLOAD
+    DUP3
+    ADD
+    SWAP1
+    SSTORE              // balances[to] += amount
+    DUP1
+    PUSH1 0x00
+    MSTORE
+    PUSH1 0x04
+    CALLDATALOAD
+    CALLER
+    PUSH32 0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef
+    PUSH1 0x20
+    PUSH1 0x00
+    LOG3                // Transfer(caller, to, amount)
+    PUSH1 0x01
+    PUSH1 0x00
+    MSTORE
+    PUSH1 0x20
+    PUSH1 0x00
+    RETURN
+fail:
+    JUMPDEST
+    PUSH1 0x00
+    DUP1
+    REVERT
+"#;
+
+type Setup = fn() -> Program;
+
+/// 循环 CALL 一个 24KB 的合约 256 次，被调用的合约第一条指令就是 STOP
+const CALL_LARGE_CONTRACT: &str = r#"
+    PUSH2 0x0100        // n = 256
+loop:
+    JUMPDEST
+    DUP1
+    ISZERO
+    PUSH2 @done
+    JUMPI
+    PUSH1 0x00
+    PUSH1 0x00
+    PUSH1 0x00
+    PUSH1 0x00
+    PUSH1 0x00
+    PUSH20 0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb
+    PUSH2 0xffff
+    CALL
+    POP
+    PUSH1 0x01
+    SWAP1
+    SUB
+    PUSH2 @loop
+    JUMP
+done:
+    JUMPDEST
+    STOP
+"#;
+
+/// EIP-170 合约代码大小上限
+const MAX_CODE_SIZE: usize = 0x6000;
+
+struct Program {
+    state: InMemoryStateDB,
+    caller: Address,
+    contract: Address,
+    call_data: Vec<u8>,
+}
+
+fn setup(source: &str, call_data: Vec<u8>) -> Program {
+    let code = Assembler::new().asm(source).unwrap();
+    let mut state = InMemoryStateDB::new();
+    let caller = Address::repeat_byte(0x11);
+    state.create_object(caller);
+    let contract = state.create_contract(caller, code.into());
+    Program {
+        state,
+        caller,
+        contract,
+        call_data,
+    }
+}
+
+fn setup_erc20() -> Program {
+    let to = Address::repeat_byte(0x22);
+    let mut call_data = vec![0xa9, 0x05, 0x9c, 0xbb];
+    call_data.extend(to.into_word());
+    call_data.extend(U256::from(1000).to_be_bytes::<32>());
+
+    let mut program = setup(ERC20_TRANSFER, call_data);
+    let mut key = program.caller.into_word().to_vec();
+    key.extend([0u8; 32]);
+    program.state.set_state(
+        program.contract,
+        keccak256(&key).into(),
+        U256::from(1_000_000),
+    );
+    program
+}
+
+fn setup_call_large_contract() -> Program {
+    let mut program = setup(CALL_LARGE_CONTRACT, vec![]);
+    program
+        .state
+        .set_code(Address::repeat_byte(0xbb), vec![0u8; MAX_CODE_SIZE].into());
+    program.state.commit();
+    program
+}
+
+fn execute(program: Program, blk_ctx: &BlockContext) -> u64 {
+    let mut vm = Interpreter::new(Box::new(program.state), blk_ctx);
+    vm.run(
+        program.caller,
+        program.caller,
+        program.contract,
+        program.call_data.into(),
+        U256::ZERO,
+    )
+    .unwrap();
+    vm.instruction_count()
+}
+
+fn bench_interpreter(c: &mut Criterion) {
+    let blk_ctx = BlockContext::new();
+    let programs: [(&str, Setup); 5] = [
+        ("fib", || setup(FIB, vec![])),
+        ("arithmetic", || setup(ARITHMETIC, vec![])),
+        ("keccak_loop", || setup(KECCAK_LOOP, vec![])),
+        ("erc20_transfer", setup_erc20),
+        ("call_24kb_contract", setup_call_large_contract),
+    ];
+
+    let mut group = c.benchmark_group("interpreter");
+    for (name, setup) in programs {
+        // 吞吐量按执行的指令数统计，报告中的 elements/s 即每秒执行的指令数
+        let instructions = execute(setup(), &blk_ctx);
+        group.throughput(Throughput::Elements(instructions));
+        group.bench_function(name, |b| {
+            b.iter_batched(setup, |p| execute(p, &blk_ctx), BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_interpreter);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+//! 编译期汇编 EVM 代码
+//!
+//! ```ignore
+//! let code: &'static [u8] = evm_asm! {
+//!     PUSH1 0x01
+//!     PUSH1 0x02
+//!     ADD
+//!     STOP
+//! };
+//! ```
+
+use evm_disasm::asm::Assembler;
+use proc_macro::TokenStream;
+use proc_macro2::TokenTree;
+use quote::quote;
+
+#[proc_macro]
+pub fn evm_asm(input: TokenStream) -> TokenStream {
+    match assemble(input.into()) {
+        Ok(code) => quote! {
+            {
+                const CODE: &'static [u8] = &[#(#code),*];
+                CODE
+            }
+        }
+        .into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// 按 `助记符 [操作数]` 切分 token，每条指令交给 `Assembler` 汇编，
+/// 出错时把错误定位到对应指令的 span
+///
+/// `Span::join` 只在 nightly 上可用，stable 上总是返回 `None`。这里不合并 span，
+/// 而是用 `Error::new_spanned` 分别记下指令首尾 token 的 span，
+/// 这样 stable 上报错也能覆盖从助记符到操作数的整条指令
+fn assemble(input: proc_macro2::TokenStream) -> syn::Result<Vec<u8>> {
+    let assembler = Assembler::new();
+    let mut code = vec![];
+    let mut tokens = input.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        let mnemonic = match token {
+            TokenTree::Ident(ident) => ident,
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    format!("expected opcode, found `{}`", other),
+                ))
+            }
+        };
+
+        let mut line = mnemonic.to_string();
+        let mut instruction = vec![TokenTree::Ident(mnemonic)];
+        if let Some(TokenTree::Literal(operand)) = tokens.peek() {
+            line = format!("{} {}", line, operand);
+            instruction.push(TokenTree::Literal(operand.clone()));
+            tokens.next();
+        }
+
+        let bytes = assembler.asm(&line).map_err(|e| {
+            let instruction: proc_macro2::TokenStream = instruction.into_iter().collect();
+            syn::Error::new_spanned(instruction, e.to_string())
+        })?;
+        code.extend(bytes);
+    }
+
+    Ok(code)
+}
@@ -0,0 +1,47 @@
+use evm_disasm::asm::Assembler;
+use evm_disasm_macros::evm_asm;
+
+#[test]
+fn test_evm_asm_matches_assembler() {
+    const CODE: &[u8] = evm_asm! {
+        PUSH17 0x67600035600757FE5B60005260086018F3
+        PUSH1 0
+        MSTORE
+        // comments are ordinary rust comments
+        PUSH1 0x11
+        PUSH1 0xF
+        PUSH1 0
+        CREATE
+        PUSH2 0x01
+        STOP
+    };
+
+    let expected = Assembler::new()
+        .asm(
+            r#"
+        PUSH17 0x67600035600757FE5B60005260086018F3
+        PUSH1 0
+        MSTORE
+        PUSH1 0x11
+        PUSH1 0xF
+        PUSH1 0
+        CREATE
+        PUSH2 0x01
+        STOP
+    "#,
+        )
+        .unwrap();
+    assert_eq!(CODE, expected.as_slice());
+}
+
+#[test]
+fn test_evm_asm_empty() {
+    let code: &'static [u8] = evm_asm! {};
+    assert!(code.is_empty());
+}
+
+#[test]
+fn test_evm_asm_compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
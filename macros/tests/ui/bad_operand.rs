@@ -0,0 +1,10 @@
+use evm_disasm_macros::evm_asm;
+
+fn main() {
+    let _: &[u8] = evm_asm! {
+        PUSH1 0x01
+        PUSH1 0x0203
+        ADD
+        STOP
+    };
+}
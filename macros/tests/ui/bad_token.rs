@@ -0,0 +1,9 @@
+use evm_disasm_macros::evm_asm;
+
+fn main() {
+    let _: &[u8] = evm_asm! {
+        PUSH1 0x01
+        0x02
+        STOP
+    };
+}
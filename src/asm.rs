@@ -1,83 +1,492 @@
 // TODO 汇编，根据opcode 字符串生成Bytecode
 
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use alloy_primitives::U256;
+use alloy_primitives::{keccak256, Address, U256};
 use anyhow::Result;
 
 use crate::error::EVMError;
 use crate::opcode::*;
 use crate::opcode_table::OPCODE_TABLE;
 
+const INCLUDE_DIRECTIVE: &str = "#include";
+const COMMENT_PREFIX: &str = "//";
+const LABEL_SUFFIX: char = ':';
+const LABEL_REF_PREFIX: char = '@';
+
+/// Solidity 风格的库占位符: `__$` + 34 位 hex + `$__`，共 40 个字符，对应 20 字节地址
+const PLACEHOLDER_PREFIX: &str = "__$";
+const PLACEHOLDER_SUFFIX: &str = "$__";
+const PLACEHOLDER_LEN: usize = 40;
+
+/// 根据库的全限定名 (例如 `contracts/Math.sol:Math`) 计算占位符
+pub fn library_placeholder(fully_qualified_name: &str) -> String {
+    let hash = hex::encode(keccak256(fully_qualified_name));
+    format!(
+        "{}{}{}",
+        PLACEHOLDER_PREFIX,
+        &hash[..34],
+        PLACEHOLDER_SUFFIX
+    )
+}
+
+fn is_placeholder(token: &str) -> bool {
+    token.len() == PLACEHOLDER_LEN
+        && token.starts_with(PLACEHOLDER_PREFIX)
+        && token.ends_with(PLACEHOLDER_SUFFIX)
+        && token[3..37].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// 一行汇编源码中的语句
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement<'a> {
+    /// `#include "path"`
+    Include(&'a str),
+    /// `name:`，标记当前字节码偏移，本身不生成字节码
+    Label(&'a str),
+    /// `MNEMONIC [operand]`
+    Instruction {
+        mnemonic: &'a str,
+        operand: Option<&'a str>,
+    },
+}
+
+/// 解析后的一行汇编源码，引号外的 `//` 之后为注释
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceLine<'a> {
+    pub statement: Option<Statement<'a>>,
+    pub comment: Option<&'a str>,
+}
+
+/// 注释的起始位置，引号内的 `//` 不算注释，如 `#include "a//b.asm"`
+fn find_comment(line: &str) -> Option<usize> {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            _ if !in_quotes && line[i..].starts_with(COMMENT_PREFIX) => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn parse_line(line: &str) -> Result<SourceLine<'_>, EVMError> {
+    let (code, comment) = match find_comment(line) {
+        Some(i) => (&line[..i], Some(line[i + COMMENT_PREFIX.len()..].trim())),
+        None => (line, None),
+    };
+    let code = code.trim();
+
+    let statement = if code.is_empty() {
+        None
+    } else if let Some(include) = code.strip_prefix(INCLUDE_DIRECTIVE) {
+        let include_path = include
+            .trim()
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .ok_or(EVMError::InvalidAsmToken(line.to_string()))?;
+        Some(Statement::Include(include_path))
+    } else if let Some(label) = code.strip_suffix(LABEL_SUFFIX) {
+        if !is_label_name(label) {
+            return Err(EVMError::InvalidAsmToken(line.to_string()));
+        }
+        Some(Statement::Label(label))
+    } else {
+        let mut tokens = code.split_whitespace();
+        let mnemonic = tokens.next().unwrap_or_default();
+        let operand = tokens.next();
+        if tokens.next().is_some() {
+            return Err(EVMError::InvalidAsmToken(line.to_string()));
+        }
+        Some(Statement::Instruction { mnemonic, operand })
+    };
+
+    Ok(SourceLine { statement, comment })
+}
+
+/// PUSH 指令的操作数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand<'a> {
+    /// 十六进制立即数，`0x` 前缀可省略
+    Value(U256),
+    /// `@label`，汇编时替换为标签的偏移
+    Label(&'a str),
+    /// 库地址占位符，链接时替换
+    Placeholder(&'a str),
+}
+
+impl Operand<'_> {
+    /// 操作数至少需要的字节数
+    pub fn min_size(&self) -> usize {
+        match self {
+            Operand::Value(value) => value.byte_len().max(1),
+            Operand::Label(_) => 1,
+            Operand::Placeholder(_) => Address::len_bytes(),
+        }
+    }
+}
+
+pub fn parse_operand(operand: &str) -> Result<Operand<'_>, EVMError> {
+    if is_placeholder(operand) {
+        return Ok(Operand::Placeholder(operand));
+    }
+    if let Some(label) = operand.strip_prefix(LABEL_REF_PREFIX) {
+        if !is_label_name(label) {
+            return Err(EVMError::InvalidAsmToken(operand.to_string()));
+        }
+        return Ok(Operand::Label(label));
+    }
+    U256::from_str_radix(operand.strip_prefix("0x").unwrap_or(operand), 16)
+        .map(Operand::Value)
+        .map_err(|_| EVMError::InvalidAsmToken(operand.to_string()))
+}
+
+/// 字节码中需要链接的库地址位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkReference {
+    pub placeholder: String,
+    pub offset: usize,
+}
+
+/// 汇编结果，可能包含尚未链接的库占位符
+#[derive(Debug, Clone, Default)]
+pub struct Object {
+    pub code: Vec<u8>,
+    pub link_references: Vec<LinkReference>,
+    pub labels: HashMap<String, usize>,
+}
+
+impl Object {
+    pub fn is_linked(&self) -> bool {
+        self.link_references.is_empty()
+    }
+
+    /// 用 link map (占位符 -> 地址) 替换所有库占位符，生成最终的字节码
+    pub fn link(&self, link_map: &HashMap<String, Address>) -> Result<Vec<u8>, EVMError> {
+        let mut code = self.code.clone();
+        for reference in &self.link_references {
+            let address = link_map
+                .get(&reference.placeholder)
+                .ok_or(EVMError::UnlinkedLibrary(reference.placeholder.clone()))?;
+            code[reference.offset..reference.offset + Address::len_bytes()]
+                .copy_from_slice(address.as_slice());
+        }
+        Ok(code)
+    }
+}
+
+/// 引用了标签的 PUSH 操作数，标签可能在后面才定义，所以全部汇编完之后再回填
+struct LabelFixup {
+    label: String,
+    offset: usize,
+    size: usize,
+    line: String,
+}
+
+#[derive(Default)]
+struct AsmOutput {
+    object: Object,
+    fixups: Vec<LabelFixup>,
+}
+
+impl AsmOutput {
+    fn finish(mut self) -> Result<Object, EVMError> {
+        for fixup in &self.fixups {
+            let target = self
+                .object
+                .labels
+                .get(&fixup.label)
+                .ok_or(EVMError::UndefinedLabel(fixup.label.clone()))?;
+            let target_bytes = U256::from(*target).to_be_bytes_trimmed_vec();
+            if target_bytes.len() > fixup.size {
+                return Err(EVMError::InvalidAsmToken(fixup.line.clone()));
+            }
+            let end = fixup.offset + fixup.size;
+            self.object.code[end - target_bytes.len()..end].copy_from_slice(&target_bytes);
+        }
+        Ok(self.object)
+    }
+}
+
 pub struct Assembler {
     opcode_table: HashMap<String, u8>,
 }
 
+impl Default for Assembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Assembler {
     pub fn new() -> Self {
         Self {
             opcode_table: OPCODE_TABLE
                 .iter()
-                .map(|(k, (name, _, _))| (name.to_string(), k.to_owned()))
+                .map(|(k, info)| (info.name.to_string(), k.to_owned()))
                 .collect(),
         }
     }
 
+    /// 查找助记符对应的 opcode，不区分大小写
+    pub fn opcode(&self, mnemonic: &str) -> Option<u8> {
+        self.opcode_table
+            .get(&mnemonic.to_ascii_uppercase())
+            .copied()
+    }
+
+    /// 汇编源码并返回字节码，源码中不允许出现未链接的库占位符
     pub fn asm(&self, opcode: &str) -> Result<Vec<u8>, EVMError> {
-        let mut bytes = vec![];
-        for line in opcode.lines() {
-            if line.is_empty() || line.chars().all(|c| c.is_whitespace()) {
-                continue;
-            }
-            bytes.extend(self.asm_opcode(line)?);
+        self.asm_object(opcode)?.link(&HashMap::new())
+    }
+
+    /// 汇编源码，`#include` 相对于当前工作目录解析
+    pub fn asm_object(&self, source: &str) -> Result<Object, EVMError> {
+        let base_dir = std::env::current_dir()
+            .map_err(|e| EVMError::AsmInclude(format!("current dir: {}", e)))?;
+        let mut output = AsmOutput::default();
+        self.asm_source(source, &base_dir, &mut vec![], &mut output)?;
+        output.finish()
+    }
+
+    /// 汇编文件，`#include` 相对于包含它的文件解析
+    pub fn asm_file<P: AsRef<Path>>(&self, path: P) -> Result<Object, EVMError> {
+        let mut output = AsmOutput::default();
+        self.asm_include(path.as_ref(), &mut vec![], &mut output)?;
+        output.finish()
+    }
+
+    fn asm_include(
+        &self,
+        path: &Path,
+        include_stack: &mut Vec<PathBuf>,
+        output: &mut AsmOutput,
+    ) -> Result<(), EVMError> {
+        let path = path
+            .canonicalize()
+            .map_err(|e| EVMError::AsmInclude(format!("{}: {}", path.display(), e)))?;
+
+        if include_stack.contains(&path) {
+            let cycle = include_stack
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(EVMError::IncludeCycle(cycle));
         }
-        Ok(bytes)
+
+        let source = fs::read_to_string(&path)
+            .map_err(|e| EVMError::AsmInclude(format!("{}: {}", path.display(), e)))?;
+        let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        include_stack.push(path);
+        self.asm_source(&source, &base_dir, include_stack, output)?;
+        include_stack.pop();
+        Ok(())
     }
 
-    fn asm_opcode(&self, opcode: &str) -> Result<Vec<u8>, EVMError> {
-        let mut opcode_and_operand = opcode.split_whitespace().filter(|s| !s.is_empty());
+    fn asm_source(
+        &self,
+        source: &str,
+        base_dir: &Path,
+        include_stack: &mut Vec<PathBuf>,
+        output: &mut AsmOutput,
+    ) -> Result<(), EVMError> {
+        for line in source.lines() {
+            match parse_line(line)?.statement {
+                Some(Statement::Include(include_path)) => {
+                    self.asm_include(&base_dir.join(include_path), include_stack, output)?;
+                }
+                Some(Statement::Label(label)) => {
+                    let offset = output.object.code.len();
+                    if output
+                        .object
+                        .labels
+                        .insert(label.to_string(), offset)
+                        .is_some()
+                    {
+                        return Err(EVMError::DuplicateLabel(label.to_string()));
+                    }
+                }
+                Some(Statement::Instruction { mnemonic, operand }) => {
+                    self.asm_opcode(line.trim(), mnemonic, operand, output)?;
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
 
-        let opcode_token = opcode_and_operand
-            .next()
-            .ok_or(EVMError::InvalidAsmToken(opcode.to_string()))?;
+    fn asm_opcode(
+        &self,
+        line: &str,
+        mnemonic: &str,
+        operand: Option<&str>,
+        output: &mut AsmOutput,
+    ) -> Result<(), EVMError> {
         let opcode_byte = self
-            .opcode_table
-            .get(opcode_token)
-            .ok_or(EVMError::InvalidAsmToken(opcode.to_string()))?
-            .to_owned();
-
-        match opcode_byte {
-            PUSH1..=PUSH32 => {
-                let operand = opcode_and_operand
-                    .next()
-                    .ok_or(EVMError::InvalidAsmToken(opcode.to_string()))?;
-                let operand_u256 = match operand.strip_prefix("0x") {
-                    Some(operand_remove_prefix) => {
-                        let operand_u256 = U256::from_str_radix(operand_remove_prefix, 16)
-                            .map_err(|_| EVMError::InvalidAsmToken(opcode.to_string()))?;
-                        operand_u256
-                    }
-                    None => {
-                        let operand_u256 = U256::from_str_radix(operand, 16)
-                            .map_err(|_| EVMError::InvalidAsmToken(opcode.to_string()))?;
-                        operand_u256
+            .opcode(mnemonic)
+            .ok_or(EVMError::InvalidAsmToken(line.to_string()))?;
+
+        let object = &mut output.object;
+        object.code.push(opcode_byte);
+
+        if let PUSH1..=PUSH32 = opcode_byte {
+            let operand = operand.ok_or(EVMError::InvalidAsmToken(line.to_string()))?;
+            let operand_size = (opcode_byte - PUSH1 + 1) as usize;
+            let offset = object.code.len();
+
+            match parse_operand(operand).map_err(|_| EVMError::InvalidAsmToken(line.to_string()))? {
+                // 库地址占位符，先填充 0，链接时再替换
+                Operand::Placeholder(placeholder) => {
+                    if operand_size != Address::len_bytes() {
+                        return Err(EVMError::InvalidAsmToken(line.to_string()));
                     }
-                };
-                let mut operand_bytes = vec![0];
-                if !operand_u256.is_zero() {
-                    operand_bytes = operand_u256.to_be_bytes_trimmed_vec()
+                    object.link_references.push(LinkReference {
+                        placeholder: placeholder.to_string(),
+                        offset,
+                    });
+                    object.code.extend([0u8; 20]);
                 }
-
-                let operand_size = (opcode_byte - PUSH1 + 1) as usize;
-                if operand_bytes.len() > operand_size {
-                    return Err(EVMError::InvalidAsmToken(opcode.to_string()));
+                // 标签偏移，先填充 0，汇编结束时回填
+                Operand::Label(label) => {
+                    output.fixups.push(LabelFixup {
+                        label: label.to_string(),
+                        offset,
+                        size: operand_size,
+                        line: line.to_string(),
+                    });
+                    object.code.extend(std::iter::repeat_n(0, operand_size));
                 }
+                Operand::Value(value) => {
+                    let operand_bytes = value.to_be_bytes_trimmed_vec();
+                    if operand_bytes.len() > operand_size {
+                        return Err(EVMError::InvalidAsmToken(line.to_string()));
+                    }
 
-                let mut bytes = vec![opcode_byte];
-                bytes.extend(&operand_bytes[0..operand_size]);
-                Ok(bytes)
+                    // 左侧补 0 到 PUSH 指令的宽度
+                    object
+                        .code
+                        .extend(std::iter::repeat_n(0, operand_size - operand_bytes.len()));
+                    object.code.extend(operand_bytes);
+                }
             }
-            _ => Ok(vec![opcode_byte]),
+        } else if operand.is_some() {
+            return Err(EVMError::InvalidAsmToken(line.to_string()));
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("evm-asm-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_asm_push_padding() {
+        let code = Assembler::new().asm("PUSH2 0x01\nPUSH1 0").unwrap();
+        assert_eq!(code, vec![PUSH2, 0x00, 0x01, PUSH1, 0x00]);
+    }
+
+    #[test]
+    fn test_asm_include() {
+        let dir = temp_dir("include");
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(dir.join("lib/helper.asm"), "PUSH1 0x02\nADD\n").unwrap();
+        fs::write(
+            dir.join("main.asm"),
+            "PUSH1 0x01\n#include \"lib/helper.asm\"\nSTOP\n",
+        )
+        .unwrap();
+
+        let object = Assembler::new().asm_file(dir.join("main.asm")).unwrap();
+        assert_eq!(object.code, vec![PUSH1, 0x01, PUSH1, 0x02, ADD, STOP]);
+    }
+
+    #[test]
+    fn test_parse_line_comment_outside_quotes() {
+        let line = parse_line("#include \"lib//helper.asm\" // helpers").unwrap();
+        assert_eq!(line.statement, Some(Statement::Include("lib//helper.asm")));
+        assert_eq!(line.comment, Some("helpers"));
+
+        let line = parse_line("PUSH1 0x01 // \"quoted\" // text").unwrap();
+        assert_eq!(
+            line.statement,
+            Some(Statement::Instruction {
+                mnemonic: "PUSH1",
+                operand: Some("0x01"),
+            })
+        );
+        assert_eq!(line.comment, Some("\"quoted\" // text"));
+    }
+
+    #[test]
+    fn test_asm_include_cycle() {
+        let dir = temp_dir("cycle");
+        fs::write(dir.join("a.asm"), "#include \"b.asm\"\n").unwrap();
+        fs::write(dir.join("b.asm"), "#include \"a.asm\"\n").unwrap();
+
+        let result = Assembler::new().asm_file(dir.join("a.asm"));
+        assert!(matches!(result, Err(EVMError::IncludeCycle(_))));
+    }
+
+    #[test]
+    fn test_asm_link_library() {
+        let placeholder = library_placeholder("lib/Math.sol:Math");
+        let assembler = Assembler::new();
+        let source = format!("PUSH20 {}\nPOP", placeholder);
+
+        assert!(matches!(
+            assembler.asm(&source),
+            Err(EVMError::UnlinkedLibrary(_))
+        ));
+
+        let object = assembler.asm_object(&source).unwrap();
+        assert_eq!(object.link_references[0].offset, 1);
+
+        let address = Address::repeat_byte(0xaa);
+        let code = object
+            .link(&HashMap::from([(placeholder, address)]))
+            .unwrap();
+        assert_eq!(&code[1..21], address.as_slice());
+        assert_eq!(code[21], POP);
+    }
+
+    #[test]
+    fn test_asm_labels_and_comments() {
+        let code = Assembler::new()
+            .asm(
+                r#"
+            // jump forward over the INVALID
+                push2 @end   // label defined later
+                JUMP
+                INVALID
+            end:
+                JUMPDEST
+            "#,
+            )
+            .unwrap();
+        assert_eq!(code, vec![PUSH2, 0x00, 0x05, JUMP, INVALID, JUMPDEST]);
+
+        assert!(matches!(
+            Assembler::new().asm("PUSH1 @missing"),
+            Err(EVMError::UndefinedLabel(_))
+        ));
     }
 }
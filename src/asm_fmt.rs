@@ -0,0 +1,155 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::asm::{parse_line, parse_operand, Operand, SourceLine, Statement};
+use crate::error::EVMError;
+
+const INDENT: &str = "    ";
+
+/// 格式化汇编源码:
+/// - 助记符统一大写，指令缩进 4 个空格，标签和 `#include` 顶格
+/// - 立即数输出为最短的偶数位小写 hex，PUSH 宽度保持不变，不改变生成的字节码
+/// - 行尾注释对齐到同一列，连续空行合并为一行
+pub fn format(source: &str) -> Result<String, EVMError> {
+    let lines = source
+        .lines()
+        .map(parse_line)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut codes = Vec::with_capacity(lines.len());
+    for line in &lines {
+        codes.push(match &line.statement {
+            Some(statement) => format_statement(statement)?,
+            None => String::new(),
+        });
+    }
+
+    let comment_column = lines
+        .iter()
+        .zip(&codes)
+        .filter(|(line, code)| line.comment.is_some() && !code.is_empty())
+        .map(|(_, code)| code.len() + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    let mut pending_blank = false;
+    for (i, (line, code)) in lines.iter().zip(&codes).enumerate() {
+        if line.statement.is_none() && line.comment.is_none() {
+            pending_blank = !output.is_empty();
+            continue;
+        }
+        if pending_blank {
+            output.push('\n');
+            pending_blank = false;
+        }
+
+        match line.comment {
+            Some(comment) if code.is_empty() => {
+                // 单独一行的注释和它后面的语句保持相同缩进
+                let indent = lines[i..]
+                    .iter()
+                    .find_map(|l| l.statement.as_ref())
+                    .map_or("", statement_indent);
+                writeln!(output, "{}{}", indent, format_comment(comment)).unwrap();
+            }
+            Some(comment) => {
+                writeln!(
+                    output,
+                    "{:<width$}{}",
+                    code,
+                    format_comment(comment),
+                    width = comment_column
+                )
+                .unwrap();
+            }
+            None => writeln!(output, "{}", code).unwrap(),
+        }
+    }
+    Ok(output)
+}
+
+fn statement_indent(statement: &Statement) -> &'static str {
+    match statement {
+        Statement::Instruction { .. } => INDENT,
+        Statement::Include(_) | Statement::Label(_) => "",
+    }
+}
+
+fn format_comment(comment: &str) -> String {
+    if comment.is_empty() {
+        "//".to_string()
+    } else {
+        format!("// {}", comment)
+    }
+}
+
+fn format_statement(statement: &Statement) -> Result<String, EVMError> {
+    Ok(match statement {
+        Statement::Include(path) => format!("#include \"{}\"", path),
+        Statement::Label(label) => format!("{}:", label),
+        Statement::Instruction { mnemonic, operand } => {
+            let mnemonic = mnemonic.to_ascii_uppercase();
+            match operand {
+                Some(operand) => format!(
+                    "{}{} {}",
+                    INDENT,
+                    mnemonic,
+                    format_operand(&parse_operand(operand)?)
+                ),
+                None => format!("{}{}", INDENT, mnemonic),
+            }
+        }
+    })
+}
+
+fn format_operand(operand: &Operand) -> String {
+    match operand {
+        Operand::Value(value) => {
+            format!("0x{:0width$x}", value, width = operand.min_size() * 2)
+        }
+        Operand::Label(label) => format!("@{}", label),
+        Operand::Placeholder(placeholder) => placeholder.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::Assembler;
+
+    #[test]
+    fn test_format() {
+        let source = r#"
+
+  // entry
+      push2   0x1  // one
+  PUSH1 @end
+jump
+
+
+    end:
+  JUMPDEST // done
+"#;
+        let formatted = format(source).unwrap();
+        assert_eq!(
+            formatted,
+            "    // entry
+    PUSH2 0x01 // one
+    PUSH1 @end
+    JUMP
+
+end:
+    JUMPDEST   // done
+"
+        );
+
+        let assembler = Assembler::new();
+        assert_eq!(
+            assembler.asm(source).unwrap(),
+            assembler.asm(&formatted).unwrap()
+        );
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::asm::{parse_line, parse_operand, Assembler, Operand, Statement};
+use crate::error::EVMError;
+use crate::opcode::*;
+use crate::opcode_table::OPCODE_TABLE;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// STOP/RETURN/REVERT/INVALID/SELFDESTRUCT/JUMP 之后、下一个标签或 JUMPDEST 之前的指令
+    Unreachable,
+    /// PUSH 宽度大于操作数实际需要的字节数
+    OversizedPush { width: usize, min_size: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    /// 从 1 开始的行号
+    pub line: usize,
+    pub kind: LintKind,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            LintKind::Unreachable => write!(f, "line {}: unreachable instruction", self.line),
+            LintKind::OversizedPush { width, min_size } => write!(
+                f,
+                "line {}: PUSH{} operand fits in PUSH{}",
+                self.line, width, min_size
+            ),
+        }
+    }
+}
+
+/// 对单个源码文件做静态检查，不展开 `#include`
+pub fn lint(source: &str) -> Result<Vec<Lint>, EVMError> {
+    let assembler = Assembler::new();
+    let mut lints = vec![];
+    let mut terminated = false;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let (mnemonic, operand) = match parse_line(line)?.statement {
+            Some(Statement::Instruction { mnemonic, operand }) => (mnemonic, operand),
+            // 标签可能是跳转目标，被包含的文件也可能定义标签
+            Some(Statement::Label(_)) | Some(Statement::Include(_)) => {
+                terminated = false;
+                continue;
+            }
+            None => continue,
+        };
+
+        let opcode = assembler
+            .opcode(mnemonic)
+            .ok_or(EVMError::InvalidAsmToken(line.trim().to_string()))?;
+
+        if opcode == JUMPDEST {
+            terminated = false;
+        } else if terminated {
+            lints.push(Lint {
+                line: line_number,
+                kind: LintKind::Unreachable,
+            });
+        }
+
+        if let (PUSH1..=PUSH32, Some(operand)) = (opcode, operand) {
+            let width = (opcode - PUSH1 + 1) as usize;
+            let operand = parse_operand(operand)?;
+            if let Operand::Value(_) = operand {
+                let min_size = operand.min_size();
+                if min_size < width {
+                    lints.push(Lint {
+                        line: line_number,
+                        kind: LintKind::OversizedPush { width, min_size },
+                    });
+                }
+            }
+        }
+
+        if OPCODE_TABLE[&opcode].terminates {
+            terminated = true;
+        }
+    }
+    Ok(lints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint() {
+        let lints = lint(
+            r#"
+    PUSH2 0x0001
+    PUSH2 @end
+    JUMP
+    ADD
+end:
+    PUSH1 0x00
+    RETURN
+    JUMPDEST
+    STOP
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            lints,
+            vec![
+                Lint {
+                    line: 2,
+                    kind: LintKind::OversizedPush {
+                        width: 2,
+                        min_size: 1
+                    }
+                },
+                Lint {
+                    line: 5,
+                    kind: LintKind::Unreachable
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,632 @@
+//! 区块的 RLP 编解码、区块头校验和区块执行
+
+use alloy_primitives::{address, b256, keccak256, Address, Bloom, Bytes, B256, B64, U256};
+use alloy_rlp::{BufMut, Decodable, Encodable, Header as RlpHeader};
+
+use crate::context::BlockContext;
+use crate::error::EVMError;
+use crate::fork::Fork;
+use crate::receipt::{block_bloom, receipts_root, Receipt};
+use crate::state::StateDB;
+use crate::transaction::{
+    blob_base_fee, SignedTransaction, GAS_PER_BLOB, LEGACY_TX_TYPE, MAX_BLOBS_PER_BLOCK,
+};
+use crate::trie::{encode_list, trie_root};
+use crate::vm::Interpreter;
+
+/// 空 ommers 列表的哈希 keccak256(rlp([]))
+pub const EMPTY_OMMERS_HASH: B256 =
+    b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347");
+/// EIP-4788 保存信标链区块根的系统合约
+pub const BEACON_ROOTS_ADDRESS: Address = address!("000F3df6D732807Ef1319fB7B8bB8522d0Beac02");
+
+pub const MIN_GAS_LIMIT: u64 = 5000;
+pub const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+pub const MAX_EXTRA_DATA_SIZE: usize = 32;
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+/// London 激活区块的 base fee
+pub const INITIAL_BASE_FEE: u64 = 1_000_000_000;
+pub const TARGET_BLOB_GAS_PER_BLOCK: u64 = 3 * GAS_PER_BLOB;
+pub const MAX_BLOB_GAS_PER_BLOCK: u64 = MAX_BLOBS_PER_BLOCK as u64 * GAS_PER_BLOB;
+/// Constantinople 之后的出块奖励 2 ether，Paris 之后没有奖励
+pub const BLOCK_REWARD: U256 = U256::from_limbs([2_000_000_000_000_000_000, 0, 0, 0]);
+/// 提款金额的单位
+pub const GWEI: U256 = U256::from_limbs([1_000_000_000, 0, 0, 0]);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Header {
+    pub parent_hash: B256,
+    pub ommers_hash: B256,
+    pub beneficiary: Address,
+    pub state_root: B256,
+    pub transactions_root: B256,
+    pub receipts_root: B256,
+    pub logs_bloom: Bloom,
+    pub difficulty: U256,
+    pub number: u64,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub timestamp: u64,
+    pub extra_data: Bytes,
+    /// Paris 之后是 prevrandao
+    pub mix_hash: B256,
+    pub nonce: B64,
+    /// London 之后才有，下面的字段依次在 Shanghai、Cancun 加入
+    pub base_fee_per_gas: Option<u64>,
+    pub withdrawals_root: Option<B256>,
+    pub blob_gas_used: Option<u64>,
+    pub excess_blob_gas: Option<u64>,
+    pub parent_beacon_block_root: Option<B256>,
+}
+
+impl Encodable for Header {
+    fn encode(&self, out: &mut dyn BufMut) {
+        let mut payload = Vec::new();
+        self.parent_hash.encode(&mut payload);
+        self.ommers_hash.encode(&mut payload);
+        self.beneficiary.encode(&mut payload);
+        self.state_root.encode(&mut payload);
+        self.transactions_root.encode(&mut payload);
+        self.receipts_root.encode(&mut payload);
+        self.logs_bloom.encode(&mut payload);
+        self.difficulty.encode(&mut payload);
+        self.number.encode(&mut payload);
+        self.gas_limit.encode(&mut payload);
+        self.gas_used.encode(&mut payload);
+        self.timestamp.encode(&mut payload);
+        self.extra_data.encode(&mut payload);
+        self.mix_hash.encode(&mut payload);
+        self.nonce.encode(&mut payload);
+        if let Some(base_fee) = self.base_fee_per_gas {
+            base_fee.encode(&mut payload);
+        }
+        if let Some(root) = self.withdrawals_root {
+            root.encode(&mut payload);
+        }
+        if let Some(blob_gas_used) = self.blob_gas_used {
+            blob_gas_used.encode(&mut payload);
+        }
+        if let Some(excess_blob_gas) = self.excess_blob_gas {
+            excess_blob_gas.encode(&mut payload);
+        }
+        if let Some(root) = self.parent_beacon_block_root {
+            root.encode(&mut payload);
+        }
+        out.put_slice(&encode_list(&payload));
+    }
+}
+
+impl Decodable for Header {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let fields = &mut RlpHeader::decode_bytes(buf, true)?;
+        let mut header = Header {
+            parent_hash: Decodable::decode(fields)?,
+            ommers_hash: Decodable::decode(fields)?,
+            beneficiary: Decodable::decode(fields)?,
+            state_root: Decodable::decode(fields)?,
+            transactions_root: Decodable::decode(fields)?,
+            receipts_root: Decodable::decode(fields)?,
+            logs_bloom: Decodable::decode(fields)?,
+            difficulty: Decodable::decode(fields)?,
+            number: Decodable::decode(fields)?,
+            gas_limit: Decodable::decode(fields)?,
+            gas_used: Decodable::decode(fields)?,
+            timestamp: Decodable::decode(fields)?,
+            extra_data: Decodable::decode(fields)?,
+            mix_hash: Decodable::decode(fields)?,
+            nonce: Decodable::decode(fields)?,
+            ..Default::default()
+        };
+        if !fields.is_empty() {
+            header.base_fee_per_gas = Some(Decodable::decode(fields)?);
+        }
+        if !fields.is_empty() {
+            header.withdrawals_root = Some(Decodable::decode(fields)?);
+        }
+        if !fields.is_empty() {
+            header.blob_gas_used = Some(Decodable::decode(fields)?);
+            header.excess_blob_gas = Some(Decodable::decode(fields)?);
+            header.parent_beacon_block_root = Some(Decodable::decode(fields)?);
+        }
+        if !fields.is_empty() {
+            return Err(alloy_rlp::Error::Custom("unknown header fields"));
+        }
+        Ok(header)
+    }
+}
+
+impl Header {
+    pub fn hash(&self) -> B256 {
+        keccak256(alloy_rlp::encode(self))
+    }
+
+    /// 子区块的 base fee (EIP-1559)
+    pub fn next_base_fee(&self) -> u64 {
+        let Some(base_fee) = self.base_fee_per_gas else {
+            return INITIAL_BASE_FEE;
+        };
+        let target = self.gas_limit / ELASTICITY_MULTIPLIER;
+        if target == 0 || self.gas_used == target {
+            return base_fee;
+        }
+        let change = |delta: u64| {
+            (base_fee as u128 * delta as u128
+                / target as u128
+                / BASE_FEE_MAX_CHANGE_DENOMINATOR as u128) as u64
+        };
+        match self.gas_used > target {
+            true => base_fee + change(self.gas_used - target).max(1),
+            false => base_fee - change(target - self.gas_used),
+        }
+    }
+
+    /// 子区块的 excess blob gas (EIP-4844)
+    pub fn next_excess_blob_gas(&self) -> u64 {
+        (self.excess_blob_gas.unwrap_or_default() + self.blob_gas_used.unwrap_or_default())
+            .saturating_sub(TARGET_BLOB_GAS_PER_BLOCK)
+    }
+
+    /// 执行区块中交易使用的区块上下文，`gas_price` 和 `blob_hashes` 由每笔交易设置
+    pub fn block_context(&self, chain_id: u64, fork: Fork) -> BlockContext {
+        let mut blk_ctx = BlockContext::new();
+        let base_fee = U256::from(self.base_fee_per_gas.unwrap_or_default());
+        blk_ctx.chain_id = U256::from(chain_id);
+        blk_ctx.block_number = U256::from(self.number);
+        blk_ctx.block_timestamp = U256::from(self.timestamp);
+        blk_ctx.block_coinbase = self.beneficiary.into_word().into();
+        blk_ctx.block_difficulty = match fork >= Fork::Paris {
+            true => self.mix_hash.into(),
+            false => self.difficulty,
+        };
+        blk_ctx.block_gas_limit = U256::from(self.gas_limit);
+        blk_ctx.base_fee = base_fee;
+        blk_ctx.block_base_fee = base_fee;
+        blk_ctx.block_hash_fee = blob_base_fee(self.excess_blob_gas.unwrap_or_default());
+        blk_ctx
+    }
+
+    /// 检查区块头和父区块是否衔接，以及 `fork` 要求的字段
+    ///
+    /// Paris 之前的难度和 PoW 不检查
+    pub fn validate(&self, parent: &Header, fork: Fork) -> Result<(), EVMError> {
+        let invalid = |reason: &str| Err(EVMError::InvalidBlock(reason.to_string()));
+        if self.parent_hash != parent.hash() {
+            return invalid("parent hash mismatch");
+        }
+        if self.number != parent.number + 1 {
+            return invalid("block number is not parent number + 1");
+        }
+        if self.timestamp <= parent.timestamp {
+            return invalid("timestamp not greater than parent");
+        }
+        if self.extra_data.len() > MAX_EXTRA_DATA_SIZE {
+            return invalid("extra data too long");
+        }
+        if self.gas_limit.abs_diff(parent.gas_limit) >= parent.gas_limit / GAS_LIMIT_BOUND_DIVISOR
+            || self.gas_limit < MIN_GAS_LIMIT
+        {
+            return invalid("invalid gas limit");
+        }
+        if self.gas_used > self.gas_limit {
+            return invalid("gas used exceeds gas limit");
+        }
+
+        match (fork >= Fork::London, self.base_fee_per_gas) {
+            (true, Some(base_fee)) if base_fee == parent.next_base_fee() => {}
+            (true, _) => return invalid("invalid base fee"),
+            (false, Some(_)) => return invalid("base fee before London"),
+            (false, None) => {}
+        }
+        if fork >= Fork::Paris
+            && (!self.difficulty.is_zero()
+                || self.nonce != B64::ZERO
+                || self.ommers_hash != EMPTY_OMMERS_HASH)
+        {
+            return invalid("proof of work fields after Paris");
+        }
+        if (fork >= Fork::Shanghai) != self.withdrawals_root.is_some() {
+            return invalid("withdrawals root presence does not match fork");
+        }
+        match fork >= Fork::Cancun {
+            true => {
+                let Some(blob_gas_used) = self.blob_gas_used else {
+                    return invalid("missing blob gas used");
+                };
+                if blob_gas_used > MAX_BLOB_GAS_PER_BLOCK || blob_gas_used % GAS_PER_BLOB != 0 {
+                    return invalid("invalid blob gas used");
+                }
+                if self.excess_blob_gas != Some(parent.next_excess_blob_gas()) {
+                    return invalid("invalid excess blob gas");
+                }
+                if self.parent_beacon_block_root.is_none() {
+                    return invalid("missing parent beacon block root");
+                }
+            }
+            false if self.blob_gas_used.is_some() || self.parent_beacon_block_root.is_some() => {
+                return invalid("Cancun fields before Cancun");
+            }
+            false => {}
+        }
+        Ok(())
+    }
+}
+
+/// 信标链提款 (EIP-4895)，金额以 gwei 为单位
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub index: u64,
+    pub validator_index: u64,
+    pub address: Address,
+    pub amount: u64,
+}
+
+impl Encodable for Withdrawal {
+    fn encode(&self, out: &mut dyn BufMut) {
+        let mut payload = Vec::new();
+        self.index.encode(&mut payload);
+        self.validator_index.encode(&mut payload);
+        self.address.encode(&mut payload);
+        self.amount.encode(&mut payload);
+        out.put_slice(&encode_list(&payload));
+    }
+}
+
+impl Decodable for Withdrawal {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let fields = &mut RlpHeader::decode_bytes(buf, true)?;
+        let withdrawal = Withdrawal {
+            index: Decodable::decode(fields)?,
+            validator_index: Decodable::decode(fields)?,
+            address: Decodable::decode(fields)?,
+            amount: Decodable::decode(fields)?,
+        };
+        if !fields.is_empty() {
+            return Err(alloy_rlp::Error::Custom("unknown withdrawal fields"));
+        }
+        Ok(withdrawal)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Block {
+    pub header: Header,
+    pub transactions: Vec<SignedTransaction>,
+    pub ommers: Vec<Header>,
+    /// Shanghai 之后才有
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl Block {
+    /// 解码 `rlp([header, transactions, ommers, withdrawals])`，
+    /// typed 交易在列表中是 `type || rlp(fields)` 的字符串
+    pub fn decode(raw: &[u8]) -> Result<Self, EVMError> {
+        let mut buf = raw;
+        let fields = &mut RlpHeader::decode_bytes(&mut buf, true)?;
+        if !buf.is_empty() {
+            return Err(EVMError::InvalidRlp(
+                "trailing bytes after block".to_string(),
+            ));
+        }
+        let header = Header::decode(fields)?;
+
+        let mut list = RlpHeader::decode_bytes(fields, true)?;
+        let mut transactions = Vec::new();
+        while !list.is_empty() {
+            let mut rest = list;
+            let item = RlpHeader::decode(&mut rest)?;
+            if item.payload_length > rest.len() {
+                return Err(alloy_rlp::Error::InputTooShort.into());
+            }
+            // legacy 交易是列表本身，typed 交易是字符串的内容
+            let tx = match item.list {
+                true => &list[..list.len() - rest.len() + item.payload_length],
+                false => &rest[..item.payload_length],
+            };
+            transactions.push(SignedTransaction::decode(tx)?);
+            list = &rest[item.payload_length..];
+        }
+
+        let ommers = Vec::<Header>::decode(fields)?;
+        let withdrawals = match fields.is_empty() {
+            true => None,
+            false => Some(Vec::<Withdrawal>::decode(fields)?),
+        };
+        if !fields.is_empty() {
+            return Err(EVMError::InvalidRlp("unknown block fields".to_string()));
+        }
+        Ok(Block {
+            header,
+            transactions,
+            ommers,
+            withdrawals,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        self.header.encode(&mut payload);
+        let mut transactions = Vec::new();
+        for tx in &self.transactions {
+            match tx.tx_type {
+                LEGACY_TX_TYPE => transactions.extend(tx.encode()),
+                _ => tx.encode().as_slice().encode(&mut transactions),
+            }
+        }
+        payload.extend(encode_list(&transactions));
+        self.ommers.encode(&mut payload);
+        if let Some(withdrawals) = &self.withdrawals {
+            withdrawals.encode(&mut payload);
+        }
+        encode_list(&payload)
+    }
+
+    pub fn hash(&self) -> B256 {
+        self.header.hash()
+    }
+
+    pub fn transactions_root(&self) -> B256 {
+        ordered_trie_root(self.transactions.iter().map(|tx| tx.encode()))
+    }
+
+    pub fn ommers_hash(&self) -> B256 {
+        keccak256(alloy_rlp::encode(&self.ommers))
+    }
+
+    pub fn withdrawals_root(&self) -> Option<B256> {
+        let withdrawals = self.withdrawals.as_ref()?;
+        Some(ordered_trie_root(withdrawals.iter().map(alloy_rlp::encode)))
+    }
+
+    /// 检查区块体和区块头中的根哈希是否一致，Paris 之前还检查 ommers 的高度
+    pub fn validate_body(&self, fork: Fork) -> Result<(), EVMError> {
+        let invalid = |reason: &str| Err(EVMError::InvalidBlock(reason.to_string()));
+        let header = &self.header;
+        if self.transactions_root() != header.transactions_root {
+            return invalid("transactions root mismatch");
+        }
+        if self.ommers_hash() != header.ommers_hash {
+            return invalid("ommers hash mismatch");
+        }
+        if fork >= Fork::Paris && !self.ommers.is_empty() {
+            return invalid("ommers after Paris");
+        }
+        if self
+            .ommers
+            .iter()
+            .any(|ommer| ommer.number >= header.number || header.number - ommer.number > 6)
+        {
+            return invalid("invalid ommer number");
+        }
+        if self.withdrawals_root() != header.withdrawals_root {
+            return invalid("withdrawals root mismatch");
+        }
+        Ok(())
+    }
+}
+
+/// 执行区块后得到的、需要和区块头比较的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockOutput {
+    pub receipts: Vec<Receipt>,
+    pub state_root: B256,
+    pub receipts_root: B256,
+    pub logs_bloom: Bloom,
+    pub gas_used: u64,
+    pub blob_gas_used: u64,
+}
+
+impl BlockOutput {
+    /// 和区块头中的值比较
+    pub fn validate(&self, header: &Header) -> Result<(), EVMError> {
+        let mismatch = |field: &str| Err(EVMError::InvalidBlock(format!("{field} mismatch")));
+        if self.gas_used != header.gas_used {
+            return mismatch("gas used");
+        }
+        if header
+            .blob_gas_used
+            .is_some_and(|used| used != self.blob_gas_used)
+        {
+            return mismatch("blob gas used");
+        }
+        if self.receipts_root != header.receipts_root {
+            return mismatch("receipts root");
+        }
+        if self.logs_bloom != header.logs_bloom {
+            return mismatch("logs bloom");
+        }
+        if self.state_root != header.state_root {
+            return mismatch("state root");
+        }
+        Ok(())
+    }
+}
+
+/// 在 `state` 上执行区块：信标链区块根的系统调用、所有交易、提款和出块奖励
+///
+/// `block_hashes` 是之前区块的 hash，最后一个是父区块，供 BLOCKHASH 使用。
+/// 任何一笔交易无效时整个区块无效，此时 `state` 已经被部分修改，不再可用
+pub fn execute_block(
+    state: Box<dyn StateDB>,
+    block: &Block,
+    fork: Fork,
+    chain_id: u64,
+    block_hashes: &[U256],
+) -> Result<(Box<dyn StateDB>, BlockOutput), EVMError> {
+    let mut blk_ctx = block.header.block_context(chain_id, fork);
+    blk_ctx.block_hashes = block_hashes.to_vec();
+    let mut state = apply_beacon_root(state, &block.header, &blk_ctx, fork);
+
+    let mut output = BlockOutput::default();
+    for tx in &block.transactions {
+        let result;
+        (state, result) = apply_transaction(state, &mut blk_ctx, tx, fork, &mut output);
+        result?;
+    }
+    Ok(finish_block(state, block, fork, output))
+}
+
+/// EIP-4788，系统合约执行失败不影响区块
+pub(crate) fn apply_beacon_root(
+    state: Box<dyn StateDB>,
+    header: &Header,
+    blk_ctx: &BlockContext,
+    fork: Fork,
+) -> Box<dyn StateDB> {
+    let (true, Some(root)) = (fork >= Fork::Cancun, header.parent_beacon_block_root) else {
+        return state;
+    };
+    let mut vm = Interpreter::new_with_fork(state, blk_ctx, fork);
+    let _ = vm.system_call(BEACON_ROOTS_ADDRESS, root.into());
+    vm.into_state()
+}
+
+/// 执行区块中的下一笔交易并把回执加入 `output`
+///
+/// 交易无效或超出区块剩余的 gas 时返回错误，状态和 `output` 不变
+pub(crate) fn apply_transaction(
+    state: Box<dyn StateDB>,
+    blk_ctx: &mut BlockContext,
+    tx: &SignedTransaction,
+    fork: Fork,
+    output: &mut BlockOutput,
+) -> (Box<dyn StateDB>, Result<(), EVMError>) {
+    let invalid = |reason: &str| Err(EVMError::InvalidBlock(reason.to_string()));
+    let msg = match tx.to_message(blk_ctx) {
+        Ok(msg) => msg,
+        Err(e) => return (state, Err(e)),
+    };
+    if U256::from(msg.gas_limit) > blk_ctx.block_gas_limit - U256::from(output.gas_used) {
+        return (
+            state,
+            invalid("transaction gas limit exceeds remaining block gas"),
+        );
+    }
+    if output.blob_gas_used + msg.blob_gas() > MAX_BLOB_GAS_PER_BLOCK {
+        return (state, invalid("blob gas exceeds block limit"));
+    }
+
+    blk_ctx.gas_price = msg.effective_gas_price(blk_ctx.base_fee);
+    blk_ctx.blob_hashes = msg.blob_hashes.clone();
+    let mut vm = Interpreter::new_with_fork(state, blk_ctx, fork);
+    let result = vm.transact(&msg);
+    let state = vm.into_state();
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => return (state, Err(e)),
+    };
+
+    output.gas_used += result.gas_used;
+    output.blob_gas_used += msg.blob_gas();
+    let log_index = output.receipts.iter().map(|r| r.logs.len() as u64).sum();
+    let index = output.receipts.len() as u64;
+    output.receipts.push(Receipt::new(
+        tx.tx_type,
+        &result,
+        output.gas_used,
+        index,
+        log_index,
+    ));
+    (state, Ok(()))
+}
+
+/// 所有交易执行后：计算回执根，处理提款和出块奖励，最后计算状态根
+pub(crate) fn finish_block(
+    mut state: Box<dyn StateDB>,
+    block: &Block,
+    fork: Fork,
+    mut output: BlockOutput,
+) -> (Box<dyn StateDB>, BlockOutput) {
+    let header = &block.header;
+    output.receipts_root = receipts_root(&output.receipts);
+    output.logs_bloom = block_bloom(&output.receipts);
+
+    state.prepare();
+    for withdrawal in block.withdrawals.iter().flatten() {
+        state.add_balance(withdrawal.address, U256::from(withdrawal.amount) * GWEI);
+    }
+    if fork < Fork::Paris {
+        // 每个 ommer 给矿工额外 1/32 的奖励，ommer 的矿工按高度差得到 (8 - 差) / 8
+        let mut reward = BLOCK_REWARD;
+        for ommer in &block.ommers {
+            reward += BLOCK_REWARD / U256::from(32);
+            let ommer_reward =
+                BLOCK_REWARD * U256::from(ommer.number + 8 - header.number) / U256::from(8);
+            state.add_balance(ommer.beneficiary, ommer_reward);
+        }
+        state.add_balance(header.beneficiary, reward);
+    }
+    state.finalize();
+
+    output.state_root = state.state_root();
+    (state, output)
+}
+
+/// 以 rlp(下标) 为键的树根，用于交易、回执和提款
+pub fn ordered_trie_root(items: impl IntoIterator<Item = Vec<u8>>) -> B256 {
+    trie_root(
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| (alloy_rlp::encode(i), item)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trie::EMPTY_ROOT;
+
+    #[test]
+    fn test_header_hash() {
+        // 主网创世区块
+        let header = Header {
+            ommers_hash: EMPTY_OMMERS_HASH,
+            state_root: b256!("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544"),
+            transactions_root: EMPTY_ROOT,
+            receipts_root: EMPTY_ROOT,
+            difficulty: U256::from(0x400000000u64),
+            gas_limit: 5000,
+            extra_data: Bytes::from(
+                hex::decode("11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa")
+                    .unwrap(),
+            ),
+            nonce: B64::from(0x42u64),
+            ..Default::default()
+        };
+        assert_eq!(
+            header.hash(),
+            b256!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3")
+        );
+        let encoded = alloy_rlp::encode(&header);
+        assert_eq!(Header::decode(&mut encoded.as_slice()).unwrap(), header);
+    }
+
+    #[test]
+    fn test_next_base_fee() {
+        let parent = Header {
+            gas_limit: 30_000_000,
+            gas_used: 15_000_000,
+            base_fee_per_gas: Some(1_000_000_000),
+            ..Default::default()
+        };
+        assert_eq!(parent.next_base_fee(), 1_000_000_000);
+        let full = Header {
+            gas_used: 30_000_000,
+            ..parent.clone()
+        };
+        assert_eq!(full.next_base_fee(), 1_125_000_000);
+        let empty = Header {
+            gas_used: 0,
+            ..parent
+        };
+        assert_eq!(empty.next_base_fee(), 875_000_000);
+
+        let blobs = Header {
+            excess_blob_gas: Some(TARGET_BLOB_GAS_PER_BLOCK),
+            blob_gas_used: Some(MAX_BLOB_GAS_PER_BLOCK),
+            ..Default::default()
+        };
+        assert_eq!(blobs.next_excess_blob_gas(), MAX_BLOB_GAS_PER_BLOCK);
+    }
+}
@@ -0,0 +1,235 @@
+//! ethereum/tests BlockchainTests 格式的测试执行器
+//!
+//! 从 `pre` 和创世区块开始依次导入 `blocks` 中的 RLP 区块，标记了 `expectException`
+//! 的区块必须被拒绝，最后比较链头的哈希和状态根
+
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use alloy_primitives::{Bytes, B256, U256};
+use anyhow::{Context as _, Result};
+use serde::Deserialize;
+
+use crate::block::{execute_block, Block, Header};
+use crate::error::EVMError;
+use crate::fork::Fork;
+use crate::genesis::{load_alloc, GenesisAlloc};
+use crate::state::{InMemoryStateDB, StateDB};
+use crate::statetest::{categories, for_each_json, parse_fork, Outcome, Report, SUPPORTED_FORKS};
+use crate::trie::state_root;
+
+/// 测试中交易使用的 chain id
+pub const CHAIN_ID: u64 = 1;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockchainTest {
+    pub network: String,
+    #[serde(rename = "genesisRLP")]
+    pub genesis_rlp: Bytes,
+    pub pre: GenesisAlloc,
+    pub post_state: Option<GenesisAlloc>,
+    /// 状态太大时只给出状态根
+    pub post_state_hash: Option<B256>,
+    #[serde(rename = "lastblockhash")]
+    pub last_block_hash: B256,
+    pub blocks: Vec<TestBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestBlock {
+    /// 无效区块的 RLP 不一定能解码，所以保留原始字符串
+    pub rlp: String,
+    /// 区块应当被拒绝，链头和状态不变
+    pub expect_exception: Option<String>,
+}
+
+/// 解析一个测试文件，文件中可以有多个测试
+pub fn parse(json: &str) -> Result<BTreeMap<String, BlockchainTest>> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// 执行文件或者目录下所有 `.json` 文件中的测试
+pub fn run_path(path: &Path, report: &mut Report) -> Result<()> {
+    for_each_json(path, &mut |path, json| {
+        let tests = parse(json).with_context(|| format!("parse {}", path.display()))?;
+        for (name, test) in &tests {
+            run_test(name, test, report);
+        }
+        Ok(())
+    })
+}
+
+/// 执行一个测试，`network` 不是支持的硬分叉时跳过
+pub fn run_test(name: &str, test: &BlockchainTest, report: &mut Report) {
+    let outcome = match parse_fork(&test.network).filter(|fork| SUPPORTED_FORKS.contains(fork)) {
+        Some(fork) => run_case(test, fork),
+        None => Outcome::Skipped,
+    };
+    report.add(name, &test.network, &categories(&test.pre), outcome);
+}
+
+/// 执行一个测试，解释器 panic 也算作失败
+pub fn run_case(test: &BlockchainTest, fork: Fork) -> Outcome {
+    panic::catch_unwind(AssertUnwindSafe(|| execute(test, fork)))
+        .unwrap_or_else(|_| Outcome::Failed("interpreter panicked".to_string()))
+}
+
+fn execute(test: &BlockchainTest, fork: Fork) -> Outcome {
+    let genesis = match Block::decode(&test.genesis_rlp) {
+        Ok(genesis) => genesis,
+        Err(e) => return Outcome::Failed(format!("invalid genesis block: {}", e)),
+    };
+    let mut state = load_state(&test.pre);
+    if state.state_root() != genesis.header.state_root {
+        return Outcome::Failed(format!(
+            "genesis state root {} != {}",
+            state.state_root(),
+            genesis.header.state_root
+        ));
+    }
+
+    let mut hashes = vec![genesis.hash().into()];
+    let mut head = genesis.header;
+    for (i, block) in test.blocks.iter().enumerate() {
+        // 无效区块执行到一半时状态已经被修改，需要先保存
+        let saved = block.expect_exception.as_ref().map(|_| state.dump());
+        match (import_block(state, &head, &hashes, &block.rlp, fork), saved) {
+            (Ok((new_state, header)), None) => {
+                state = new_state;
+                hashes.push(header.hash().into());
+                head = header;
+            }
+            (Err(_), Some(alloc)) => state = load_state(&alloc),
+            (Ok(_), Some(_)) => {
+                return Outcome::Failed(format!(
+                    "block {}: expected exception {}",
+                    i,
+                    block.expect_exception.as_deref().unwrap_or_default()
+                ))
+            }
+            (Err(e), None) => return Outcome::Failed(format!("block {}: {}", i, e)),
+        }
+    }
+
+    if head.hash() != test.last_block_hash {
+        return Outcome::Failed(format!(
+            "last block hash {} != {}",
+            head.hash(),
+            test.last_block_hash
+        ));
+    }
+    let expected = match (&test.post_state, test.post_state_hash) {
+        (Some(post), _) => state_root(post),
+        (None, Some(hash)) => hash,
+        (None, None) => return Outcome::Passed,
+    };
+    if state.state_root() != expected {
+        return Outcome::Failed(format!("state root {} != {}", state.state_root(), expected));
+    }
+    Outcome::Passed
+}
+
+fn load_state(alloc: &GenesisAlloc) -> Box<dyn StateDB> {
+    let mut state = InMemoryStateDB::new();
+    load_alloc(&mut state, alloc);
+    state.commit();
+    Box::new(state)
+}
+
+/// 解码、校验并执行一个区块，返回执行后的状态和区块头
+///
+/// `hashes` 是父区块及之前的区块 hash
+fn import_block(
+    state: Box<dyn StateDB>,
+    parent: &Header,
+    hashes: &[U256],
+    rlp: &str,
+    fork: Fork,
+) -> Result<(Box<dyn StateDB>, Header), EVMError> {
+    let raw = hex::decode(rlp.trim_start_matches("0x"))
+        .map_err(|e| EVMError::InvalidRlp(e.to_string()))?;
+    let block = Block::decode(&raw)?;
+    block.header.validate(parent, fork)?;
+    block.validate_body(fork)?;
+    let (state, output) = execute_block(state, &block, fork, CHAIN_ID, hashes)?;
+    output.validate(&block.header)?;
+    Ok((state, block.header))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 第 1 个区块包含 EIP-1559 调用、legacy 创建和一笔提款，第 2 个区块重复使用 nonce 0
+    /// 所以无效，第 3 个区块包含 blob 交易和 EIP-2930 交易。
+    ///
+    /// 区块不是由本实现生成的：交易用 ethereum/tests 的发送者私钥签名，由 revm 执行
+    /// (包括 EIP-4788 系统调用)，状态根、交易根、收据根和 bloom 由 alloy-trie 计算，
+    /// 区块头和交易按 RLP 手工编码
+    const FIXTURE: &str = r#"{
+        "chain": {
+            "network": "NETWORK",
+            "genesisRLP": "GENESIS_RLP",
+            "pre": {
+                "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": { "balance": "0x0de0b6b3a7640000", "nonce": "0x00", "code": "0x", "storage": {} },
+                "0x1000000000000000000000000000000000000001": {
+                    "balance": "0x00",
+                    "nonce": "0x00",
+                    "code": "0x600035600055602a60005260ff60206000a16020602060206000600060045af160015560206020f3",
+                    "storage": { "0x00": "0x05" }
+                },
+                "0x000f3df6d732807ef1319fb7b8bb8522d0beac02": { "balance": "0x00", "nonce": "0x01", "code": "0x6000354255", "storage": {} }
+            },
+            "postStateHash": "0xa1340e612e6bce684264ace3091fcae52574b2b19d91f4605494f38b562d5bf8",
+            "lastblockhash": "0x9f12b6dbead283699c7c9afc9647972d5b43f5bee3acd5ca55b4f649340deae4",
+            "sealEngine": "NoProof",
+            "blocks": [
+                { "rlp": "BLOCK_1" },
+                { "rlp": "INVALID_BLOCK", "expectException": "TransactionException.NONCE_MISMATCH_TOO_LOW" },
+                { "rlp": "BLOCK_2" }
+            ]
+        }
+    }"#;
+
+    const GENESIS_RLP: &str = "0xf9023cf90236a00000000000000000000000000000000000000000000000000000000000000000a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347940000000000000000000000000000000000000000a0280ae8840c8513dde48ef363a0a6ecd44dcf21d64194f9be1a20cc9ab7fea066a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421b901000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000080808401c9c380808080a0000000000000000000000000000000000000000000000000000000000000000088000000000000000007a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b4218080a00000000000000000000000000000000000000000000000000000000000000000c0c0c0";
+    const BLOCK_1: &str = "0xf9033df9023ca0ae90d6c1c8d09a2db1e00325b65d1c63b34654cc22c43822c2de59fb8e22f145a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347942adc25665018aa1fe0e6bc666dac8fc2697ff9baa0ae5e5ddee95b2c34f4c6ac2708144475e48af716004fb8f374f6a6c8c65d4fbea0052ed213d18549e5a11c51b79492c002f1934df4148a3f2c7e4cbd47fcb8b6c1a0348199ea467cdf472d46f0c4a59b37a548732ae7eb29a78eb962d0d8e7a87172b901000000000000000000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000020000000000000000000000000000000000000000800000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000080018401c9c380830193d60c8365766da0222222222222222222222222222222222222222222222222222222222222222288000000000000000007a021d47182da1ad78ffa0a80cd14c14397c1eadee68dd5bdaa601944ecb7a9a9188080a00c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0cf8e1b88602f8830180010a830186a094100000000000000000000000000000000000000180a00101010101010101010101010101010101010101010101010101010101010101c001a05386160cd37715d5ce12497371afb04c6ec8e372a5cdfd862aaff92d06cbdd0ca069dafc129c5912c8fa65211408b7b42334bc718cfa2a8d3f51159e52c8c1835cf857010a830186a080038b6133ff6000526002601ef325a0ac6f1a283c60be489a7f91a47d2d63d9d1bd78f93b2dc6be5e5c790ab311ab8ba0569b885b541c0f226851f0bf501cfb8e40904671ec9420c41b0853530c02beb5c0d9d8800194300000000000000000000000000000000000000305";
+    const INVALID_BLOCK: &str = "0xf902a4f90236a0f2d169c4c46c1d3012cac3592efffb19171a2b575f7f4719e61471a70235e4a8a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347940000000000000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000000000a0f064c349cdaf96113bff27dc1fb86a170743bf5c4820f37e9fa6bafcb51111b7a00000000000000000000000000000000000000000000000000000000000000000b901000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000080028401c9c380801880a0000000000000000000000000000000000000000000000000000000000000000088000000000000000007a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b4218080a00000000000000000000000000000000000000000000000000000000000000000f867b86502f8620180010a8275309410000000000000000000000000000000000000018080c080a02b52fb28483bd5ec8b776348044154a9ef75ed11faf919c5c3358205ef142d70a067354de2170d5c65213c1455e4aeb1edf2f2028fac5fff9fcaa82dad58ebd1d9c0c0";
+    const BLOCK_2: &str = "0xf90396f9023ea0f2d169c4c46c1d3012cac3592efffb19171a2b575f7f4719e61471a70235e4a8a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347942adc25665018aa1fe0e6bc666dac8fc2697ff9baa0a1340e612e6bce684264ace3091fcae52574b2b19d91f4605494f38b562d5bf8a0e278dd14663ef33b97aeb082d3cb4e3a2d320fcfd9fb7a3786e625425a546667a0071674615e464a39c60224d63e87888e2a99cd43e0d6661a7f71cfb15b720716b901000000000000000000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000020000000000000000000000000000000000000000800000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000080028401c9c38082ed88188365766da0222222222222222222222222222222222222222222222222222222222222222288000000000000000007a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b4218302000080a01818181818181818181818181818181818181818181818181818181818181818f90150b88b03f8880102020a830186a094100000000000000000000000000000000000000180820002c001e1a0010000000000000000000000000000000000000000000000000000000000000180a02a93ea71fc58631522621d05d57515618a06c3155a99a5b4093ba95c3637e804a04d61d0284967ec5381e0737d71e9535b5aed13aea2f9dca09fec9af0f9b1cdd6b8c101f8be010309830186a09410000000000000000000000000000000000000018003f85bf859941000000000000000000000000000000000000001f842a00000000000000000000000000000000000000000000000000000000000000000a0000000000000000000000000000000000000000000000000000000000000000180a017308fa8f03c07719554c7af69aec7e4fad421aac9306bbdc874fc782658e38ba00f1075a1fbcd682e7d5b3047a72c6a590af63e87c2d4d57ff0d596057b8f3414c0c0";
+
+    fn fixture(network: &str) -> BlockchainTest {
+        let json = FIXTURE
+            .replace("NETWORK", network)
+            .replace("GENESIS_RLP", GENESIS_RLP)
+            .replace("INVALID_BLOCK", INVALID_BLOCK)
+            .replace("BLOCK_1", BLOCK_1)
+            .replace("BLOCK_2", BLOCK_2);
+        parse(&json).unwrap().remove("chain").unwrap()
+    }
+
+    #[test]
+    fn test_run_blockchain_tests() {
+        let mut report = Report::default();
+        run_test("chain", &fixture("Cancun"), &mut report);
+        run_test("chain", &fixture("Prague"), &mut report);
+        assert_eq!(report.failures, Vec::<String>::new());
+        assert_eq!(report.total.passed, 1);
+        assert_eq!(report.total.skipped, 1);
+        assert_eq!(report.categories["log"].passed, 1);
+
+        // 无效区块没有被标记时整个测试失败
+        let mut test = fixture("Cancun");
+        test.blocks[1].expect_exception = None;
+        assert!(matches!(run_case(&test, Fork::Cancun), Outcome::Failed(_)));
+        // 标记为无效的有效区块也是失败
+        let mut test = fixture("Cancun");
+        test.blocks[0].expect_exception = Some("invalid".to_string());
+        assert!(matches!(run_case(&test, Fork::Cancun), Outcome::Failed(_)));
+        // 无法解码的区块被拒绝
+        let mut test = fixture("Cancun");
+        test.blocks[1].rlp = "0xzz".to_string();
+        assert_eq!(run_case(&test, Fork::Cancun), Outcome::Passed);
+    }
+}
@@ -0,0 +1,472 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use anyhow::Result;
+
+use crate::{
+    context::Context,
+    error::EVMError,
+    genesis::GenesisAlloc,
+    opcode::{CALLDATACOPY, CODECOPY, EXTCODECOPY, MCOPY, MSTORE, MSTORE8, RETURNDATACOPY},
+    proof::AccountProof,
+    state::StateDB,
+};
+
+/// 条件断点，设置了的条件全部满足时在指令执行前命中
+#[derive(Clone, Default, Debug)]
+pub struct Breakpoint {
+    pub pc: Option<usize>,
+    pub opcode: Option<u8>,
+    pub address: Option<Address>,
+    pub depth: Option<usize>,
+    pub stack_top: Option<U256>,
+}
+
+impl Breakpoint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pc(mut self, pc: usize) -> Self {
+        self.pc = Some(pc);
+        self
+    }
+
+    pub fn opcode(mut self, opcode: u8) -> Self {
+        self.opcode = Some(opcode);
+        self
+    }
+
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn stack_top(mut self, value: U256) -> Self {
+        self.stack_top = Some(value);
+        self
+    }
+
+    pub(crate) fn matches(&self, ctx: &Context, opcode: u8) -> bool {
+        self.pc.is_none_or(|pc| pc == ctx.pc)
+            && self.opcode.is_none_or(|op| op == opcode)
+            && self.address.is_none_or(|address| address == ctx.contract)
+            && self.depth.is_none_or(|depth| depth == ctx.depth)
+            && self
+                .stack_top
+                .is_none_or(|value| !ctx.stack.is_empty() && ctx.stack.peek() == value)
+    }
+}
+
+/// 监视点，在指令执行后命中
+#[derive(Clone, Debug)]
+pub enum Watchpoint {
+    /// 通过 `StateDB::get_state`/`set_state` 读写 `address` 的 `slot`
+    Storage { address: Address, slot: U256 },
+    /// 当前帧 `[offset, offset + size)` 的内存内容发生变化
+    Memory { offset: usize, size: usize },
+}
+
+/// 断点或者监视点的一次命中
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hit {
+    /// 命中时正在执行的指令和帧
+    pub pc: usize,
+    pub depth: usize,
+    pub contract: Address,
+    pub event: Event,
+}
+
+impl Hit {
+    pub(crate) fn new(ctx: &Context, pc: usize, event: Event) -> Self {
+        Self {
+            pc,
+            depth: ctx.depth,
+            contract: ctx.contract,
+            event,
+        }
+    }
+}
+
+/// 命中的断点或者监视点，编号是添加时返回的编号
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Breakpoint(usize),
+    StorageRead {
+        watchpoint: usize,
+        value: U256,
+    },
+    StorageWrite {
+        watchpoint: usize,
+        value: U256,
+    },
+    MemoryChange {
+        watchpoint: usize,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+}
+
+/// 可能写内存的指令，CALL 类指令的返回数据在子帧返回时单独检查
+pub(crate) fn writes_memory(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        MSTORE | MSTORE8 | CALLDATACOPY | CODECOPY | EXTCODECOPY | RETURNDATACOPY | MCOPY
+    )
+}
+
+/// 读取一段内存，超出内存大小的部分为 0
+pub(crate) fn read_range(memory: &[u8], offset: usize, size: usize) -> Vec<u8> {
+    (offset..offset + size)
+        .map(|i| memory.get(i).copied().unwrap_or(0))
+        .collect()
+}
+
+/// 被监视的存储槽位和还没有处理的访问
+#[derive(Default)]
+pub(crate) struct StorageWatch {
+    pub slots: Vec<(usize, Address, U256)>,
+    pub events: Vec<Event>,
+}
+
+impl StorageWatch {
+    fn access(&mut self, address: Address, slot: U256, value: U256, write: bool) {
+        for &(watchpoint, a, s) in &self.slots {
+            if a == address && s == slot {
+                self.events.push(match write {
+                    true => Event::StorageWrite { watchpoint, value },
+                    false => Event::StorageRead { watchpoint, value },
+                });
+            }
+        }
+    }
+}
+
+/// 包装 StateDB，记录被监视的存储槽位的读写
+pub(crate) struct WatchedState {
+    pub inner: Box<dyn StateDB>,
+    pub watch: Rc<RefCell<StorageWatch>>,
+}
+
+impl StateDB for WatchedState {
+    fn create_object(&mut self, address: Address) {
+        self.inner.create_object(address)
+    }
+
+    fn create_contract(&mut self, caller: Address, code: Bytes) -> Address {
+        self.inner.create_contract(caller, code)
+    }
+
+    fn set_code(&mut self, contract: Address, code: Bytes) {
+        self.inner.set_code(contract, code)
+    }
+
+    fn transfer(&mut self, from: Address, to: Address, value: U256) -> Result<(), EVMError> {
+        self.inner.transfer(from, to, value)
+    }
+
+    fn sub_balance(&mut self, address: Address, value: U256) -> Result<U256, EVMError> {
+        self.inner.sub_balance(address, value)
+    }
+
+    fn add_balance(&mut self, address: Address, value: U256) -> U256 {
+        self.inner.add_balance(address, value)
+    }
+
+    fn get_balance(&self, address: Address) -> U256 {
+        self.inner.get_balance(address)
+    }
+
+    fn get_nonce(&self, address: Address) -> u64 {
+        self.inner.get_nonce(address)
+    }
+
+    fn set_nonce(&mut self, address: Address, nonce: u64) {
+        self.inner.set_nonce(address, nonce)
+    }
+
+    fn get_code(&self, address: Address) -> Bytes {
+        self.inner.get_code(address)
+    }
+
+    fn get_code_hash(&self, address: Address) -> U256 {
+        self.inner.get_code_hash(address)
+    }
+
+    fn get_code_size(&self, address: Address) -> usize {
+        self.inner.get_code_size(address)
+    }
+
+    fn exists(&self, address: Address) -> bool {
+        self.inner.exists(address)
+    }
+
+    fn get_state(&self, address: Address, slot: U256) -> U256 {
+        let value = self.inner.get_state(address, slot);
+        self.watch.borrow_mut().access(address, slot, value, false);
+        value
+    }
+
+    fn set_state(&mut self, address: Address, slot: U256, value: U256) {
+        self.inner.set_state(address, slot, value);
+        self.watch.borrow_mut().access(address, slot, value, true);
+    }
+
+    fn get_transition_state(&self, address: Address, slot: U256) -> U256 {
+        self.inner.get_transition_state(address, slot)
+    }
+
+    fn set_transition_state(&mut self, address: Address, slot: U256, value: U256) {
+        self.inner.set_transition_state(address, slot, value)
+    }
+
+    fn get_committed_state(&self, address: Address, slot: U256) -> U256 {
+        self.inner.get_committed_state(address, slot)
+    }
+
+    fn access_address(&mut self, address: Address) -> bool {
+        self.inner.access_address(address)
+    }
+
+    fn access_slot(&mut self, address: Address, slot: U256) -> bool {
+        self.inner.access_slot(address, slot)
+    }
+
+    fn add_refund(&mut self, gas: u64) {
+        self.inner.add_refund(gas)
+    }
+
+    fn sub_refund(&mut self, gas: u64) {
+        self.inner.sub_refund(gas)
+    }
+
+    fn get_refund(&self) -> u64 {
+        self.inner.get_refund()
+    }
+
+    fn selfdestruct(&mut self, address: Address) {
+        self.inner.selfdestruct(address)
+    }
+
+    fn is_created(&self, address: Address) -> bool {
+        self.inner.is_created(address)
+    }
+
+    fn snapshot(&mut self) -> usize {
+        self.inner.snapshot()
+    }
+
+    fn revert_to_snapshot(&mut self, snapshot: usize) {
+        self.inner.revert_to_snapshot(snapshot)
+    }
+
+    fn prepare(&mut self) {
+        self.inner.prepare()
+    }
+
+    fn finalize(&mut self) {
+        self.inner.finalize()
+    }
+
+    fn commit(&mut self) {
+        self.inner.commit()
+    }
+
+    fn add_log(&mut self, address: Address, topics: Vec<U256>, data: Vec<u8>) {
+        self.inner.add_log(address, topics, data)
+    }
+
+    fn logs(&self) -> &[(Address, Vec<U256>, Vec<u8>)] {
+        self.inner.logs()
+    }
+
+    fn dump(&self) -> GenesisAlloc {
+        self.inner.dump()
+    }
+
+    fn state_root(&self) -> B256 {
+        self.inner.state_root()
+    }
+
+    fn get_proof(&self, address: Address, slots: &[U256]) -> Result<AccountProof, EVMError> {
+        self.inner.get_proof(address, slots)
+    }
+
+    fn take_error(&mut self) -> Option<EVMError> {
+        self.inner.take_error()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BlockContext;
+    use crate::test_utils::deploy;
+    use crate::vm::Interpreter;
+
+    fn word(value: u8) -> Vec<u8> {
+        let mut word = vec![0; 32];
+        word[31] = value;
+        word
+    }
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        let (state, caller, contract) = deploy(
+            r#"
+            PUSH1 1
+            PUSH1 2
+            PUSH1 3
+            POP
+            POP
+            POP
+            STOP
+        "#,
+        );
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        vm.add_breakpoint(Breakpoint::new().opcode(0x50).address(caller));
+        let id = vm.add_breakpoint(Breakpoint::new().opcode(0x50).stack_top(U256::from(2)));
+        vm.run(caller, caller, contract, Bytes::new(), U256::ZERO)
+            .unwrap();
+
+        // 直接运行时只记录命中，不会停下
+        assert_eq!(
+            vm.hits(),
+            [Hit {
+                pc: 7,
+                depth: 0,
+                contract,
+                event: Event::Breakpoint(id),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resume() {
+        let (state, caller, contract) = deploy(
+            r#"
+            PUSH1 7
+            PUSH1 1
+            SSTORE
+            PUSH1 1
+            SLOAD
+            PUSH1 0
+            MSTORE
+            STOP
+        "#,
+        );
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let slot = vm.add_watchpoint(Watchpoint::Storage {
+            address: contract,
+            slot: U256::from(1),
+        });
+        let memory = vm.add_watchpoint(Watchpoint::Memory {
+            offset: 0,
+            size: 32,
+        });
+        let bp = vm.add_breakpoint(Breakpoint::new().pc(7));
+        let mut exec = vm.start_call(caller, caller, contract, Bytes::new(), U256::ZERO);
+
+        let value = U256::from(7);
+        let events = |hits: &[Hit]| {
+            hits.iter()
+                .map(|hit| (hit.pc, hit.event.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            events(exec.resume()),
+            [(
+                4,
+                Event::StorageWrite {
+                    watchpoint: slot,
+                    value
+                }
+            )]
+        );
+        assert_eq!(exec.context().pc, 5);
+
+        // 断点停在指令执行前
+        assert_eq!(events(exec.resume()), [(7, Event::Breakpoint(bp))]);
+        assert_eq!(exec.context().pc, 7);
+
+        assert_eq!(
+            events(exec.resume()),
+            [(
+                7,
+                Event::StorageRead {
+                    watchpoint: slot,
+                    value
+                }
+            )]
+        );
+        assert_eq!(
+            events(exec.resume()),
+            [(
+                10,
+                Event::MemoryChange {
+                    watchpoint: memory,
+                    old: vec![0; 32],
+                    new: word(7),
+                }
+            )]
+        );
+        assert!(exec.resume().is_empty());
+        assert!(exec.is_finished());
+        assert_eq!(exec.interpreter().hits().len(), 4);
+    }
+
+    #[test]
+    fn test_memory_watchpoint_return_data() {
+        // 部署一个把 0x2a 写入内存并返回的合约，调用它并把返回数据写到 0x40
+        let (state, caller, contract) = deploy(
+            r#"
+            PUSH19 0x69602a60005260206000f3600052600a6016f3
+            PUSH1 0
+            MSTORE
+            PUSH1 0x13
+            PUSH1 0x0d
+            PUSH1 0
+            CREATE
+            PUSH1 0x20
+            PUSH1 0x40
+            PUSH1 0
+            PUSH1 0
+            PUSH1 0
+            DUP6
+            GAS
+            CALL
+            STOP
+        "#,
+        );
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let id = vm.add_watchpoint(Watchpoint::Memory {
+            offset: 0x40,
+            size: 32,
+        });
+        vm.run(caller, caller, contract, Bytes::new(), U256::ZERO)
+            .unwrap();
+        assert_eq!(
+            vm.take_hits(),
+            [Hit {
+                pc: 42,
+                depth: 0,
+                contract,
+                event: Event::MemoryChange {
+                    watchpoint: id,
+                    old: vec![0; 32],
+                    new: word(0x2a),
+                },
+            }]
+        );
+        assert!(vm.hits().is_empty());
+    }
+}
@@ -0,0 +1,370 @@
+//! 本地链：在内存状态上打包交易出块，作为集成测试的开发网络
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use alloy_primitives::{keccak256, Address, B256, U256};
+
+use crate::block::{
+    apply_beacon_root, apply_transaction, finish_block, Block, BlockOutput, Header,
+    EMPTY_OMMERS_HASH, GAS_LIMIT_BOUND_DIVISOR, INITIAL_BASE_FEE, MIN_GAS_LIMIT,
+};
+use crate::context::{BlockContext, BLOCK_HASH_HISTORY};
+use crate::error::EVMError;
+use crate::fork::Fork;
+use crate::receipt::Receipt;
+use crate::state::{InMemoryStateDB, StateDB};
+use crate::transaction::{ExecutionResult, Message, SignedTransaction};
+use crate::trie::EMPTY_ROOT;
+use crate::vm::Interpreter;
+
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+/// 相邻区块默认的时间间隔，单位秒
+pub const DEFAULT_BLOCK_TIME: u64 = 12;
+
+pub struct LocalChain {
+    state: Box<dyn StateDB>,
+    fork: Fork,
+    chain_id: u64,
+    /// 下标是区块高度，第一个是创世区块
+    blocks: Vec<Block>,
+    receipts: Vec<Vec<Receipt>>,
+    /// 已打包交易的 hash 到 (区块高度, 交易下标)
+    transactions: HashMap<B256, (u64, usize)>,
+    /// 等待打包的交易，按加入的顺序执行
+    pending: VecDeque<SignedTransaction>,
+    /// 最近一次出块时丢弃的交易 hash 和原因
+    dropped: Vec<(B256, EVMError)>,
+    pub coinbase: Address,
+    /// 新区块的目标 gas limit，每个区块最多向它调整父区块的 1/1024
+    pub gas_limit: u64,
+    pub block_time: u64,
+}
+
+impl LocalChain {
+    /// 以 `state` 为创世状态，和 `load_alloc` 一样需要调用方先提交
+    pub fn new(state: InMemoryStateDB, chain_id: u64, fork: Fork) -> Self {
+        let mut header = Header {
+            ommers_hash: EMPTY_OMMERS_HASH,
+            state_root: state.state_root(),
+            transactions_root: EMPTY_ROOT,
+            receipts_root: EMPTY_ROOT,
+            gas_limit: DEFAULT_GAS_LIMIT,
+            base_fee_per_gas: Some(INITIAL_BASE_FEE),
+            ..Default::default()
+        };
+        if fork < Fork::Paris {
+            // 不模拟 PoW，难度固定为 1
+            header.difficulty = U256::from(1);
+        }
+        if fork >= Fork::Shanghai {
+            header.withdrawals_root = Some(EMPTY_ROOT);
+        }
+        if fork >= Fork::Cancun {
+            header.blob_gas_used = Some(0);
+            header.excess_blob_gas = Some(0);
+            header.parent_beacon_block_root = Some(B256::ZERO);
+        }
+        let genesis = Block {
+            header,
+            withdrawals: (fork >= Fork::Shanghai).then(Vec::new),
+            ..Default::default()
+        };
+
+        LocalChain {
+            state: Box::new(state),
+            fork,
+            chain_id,
+            blocks: vec![genesis],
+            receipts: vec![Vec::new()],
+            transactions: HashMap::new(),
+            pending: VecDeque::new(),
+            dropped: Vec::new(),
+            coinbase: Address::ZERO,
+            gas_limit: DEFAULT_GAS_LIMIT,
+            block_time: DEFAULT_BLOCK_TIME,
+        }
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn fork(&self) -> Fork {
+        self.fork
+    }
+
+    /// 最新区块之后的状态
+    pub fn state(&self) -> &dyn StateDB {
+        self.state.as_ref()
+    }
+
+    pub fn head(&self) -> &Header {
+        &self.blocks[self.blocks.len() - 1].header
+    }
+
+    pub fn block(&self, number: u64) -> Option<&Block> {
+        self.blocks.get(number as usize)
+    }
+
+    pub fn block_by_hash(&self, hash: B256) -> Option<&Block> {
+        self.blocks.iter().find(|block| block.hash() == hash)
+    }
+
+    pub fn receipts(&self, number: u64) -> Option<&[Receipt]> {
+        self.receipts.get(number as usize).map(Vec::as_slice)
+    }
+
+    /// 已打包的交易所在的区块高度和下标
+    pub fn find_transaction(&self, hash: B256) -> Option<(u64, usize)> {
+        self.transactions.get(&hash).copied()
+    }
+
+    /// 加入等待打包的队列，返回交易 hash
+    pub fn add_transaction(&mut self, tx: SignedTransaction) -> B256 {
+        let hash = tx.hash();
+        self.pending.push_back(tx);
+        hash
+    }
+
+    pub fn pending_transactions(&self) -> impl Iterator<Item = &SignedTransaction> {
+        self.pending.iter()
+    }
+
+    /// 最近一次出块时因为无效被丢弃的交易
+    pub fn dropped_transactions(&self) -> &[(B256, EVMError)] {
+        &self.dropped
+    }
+
+    /// 下一个区块的上下文，用于在最新状态上模拟执行
+    pub fn pending_block_context(&self) -> BlockContext {
+        let header = self.next_header(self.head().timestamp + self.block_time);
+        let mut blk_ctx = header.block_context(self.chain_id, self.fork);
+        blk_ctx.block_hashes = self.block_hashes(header.number);
+        blk_ctx
+    }
+
+    /// 在最新状态上执行消息，不保存修改
+    pub fn call(
+        &mut self,
+        msg: &Message,
+        blk_ctx: &BlockContext,
+    ) -> Result<ExecutionResult, EVMError> {
+        let state = std::mem::replace(&mut self.state, Box::new(InMemoryStateDB::new()));
+        let mut vm = Interpreter::new_with_fork(state, blk_ctx, self.fork);
+        let result = vm.simulate(msg);
+        self.state = vm.into_state();
+        result
+    }
+
+    /// 以父区块的时间加上 `block_time` 出块
+    pub fn mine_block(&mut self) -> Result<&Block, EVMError> {
+        self.mine_block_at(self.head().timestamp + self.block_time)
+    }
+
+    /// 打包队列中的交易出块
+    ///
+    /// 无效的交易被丢弃，记录在 `dropped_transactions` 中；区块剩余 gas 不够的交易
+    /// 留到下一个区块，同一发送者之后的交易也一起留下，保持 nonce 的顺序
+    pub fn mine_block_at(&mut self, timestamp: u64) -> Result<&Block, EVMError> {
+        if timestamp <= self.head().timestamp {
+            return Err(EVMError::InvalidBlock(
+                "timestamp not greater than parent".to_string(),
+            ));
+        }
+        let mut header = self.next_header(timestamp);
+        let mut blk_ctx = header.block_context(self.chain_id, self.fork);
+        blk_ctx.block_hashes = self.block_hashes(header.number);
+
+        let state = std::mem::replace(&mut self.state, Box::new(InMemoryStateDB::new()));
+        let mut state = apply_beacon_root(state, &header, &blk_ctx, self.fork);
+        let mut output = BlockOutput::default();
+        let mut transactions = Vec::new();
+        let mut deferred = VecDeque::new();
+        let mut deferred_senders = HashSet::new();
+        self.dropped.clear();
+        while let Some(tx) = self.pending.pop_front() {
+            let sender = match tx.recover_sender() {
+                Ok(sender) => sender,
+                Err(e) => {
+                    self.dropped.push((tx.hash(), e));
+                    continue;
+                }
+            };
+            if deferred_senders.contains(&sender) {
+                deferred.push_back(tx);
+                continue;
+            }
+            let result;
+            (state, result) = apply_transaction(state, &mut blk_ctx, &tx, self.fork, &mut output);
+            match result {
+                Ok(()) => transactions.push(tx),
+                Err(EVMError::InvalidBlock(_)) if tx.gas_limit <= header.gas_limit => {
+                    deferred_senders.insert(sender);
+                    deferred.push_back(tx);
+                }
+                Err(e) => self.dropped.push((tx.hash(), e)),
+            }
+        }
+        self.pending = deferred;
+
+        let mut block = Block {
+            header: header.clone(),
+            transactions,
+            ommers: Vec::new(),
+            withdrawals: (self.fork >= Fork::Shanghai).then(Vec::new),
+        };
+        let (state, output) = finish_block(state, &block, self.fork, output);
+        self.state = state;
+
+        header.state_root = output.state_root;
+        header.transactions_root = block.transactions_root();
+        header.receipts_root = output.receipts_root;
+        header.logs_bloom = output.logs_bloom;
+        header.gas_used = output.gas_used;
+        if self.fork >= Fork::Cancun {
+            header.blob_gas_used = Some(output.blob_gas_used);
+        }
+        block.header = header;
+        for (i, tx) in block.transactions.iter().enumerate() {
+            self.transactions
+                .insert(tx.hash(), (block.header.number, i));
+        }
+        self.blocks.push(block);
+        self.receipts.push(output.receipts);
+        Ok(&self.blocks[self.blocks.len() - 1])
+    }
+
+    /// 区块 `number` 之前最近 256 个区块的 hash，最后一个是父区块
+    pub fn block_hashes(&self, number: u64) -> Vec<U256> {
+        let end = (number as usize).min(self.blocks.len());
+        let start = end.saturating_sub(BLOCK_HASH_HISTORY as usize);
+        self.blocks[start..end]
+            .iter()
+            .map(|block| block.hash().into())
+            .collect()
+    }
+
+    /// 执行交易之前的区块头，根哈希和 gas 在出块后填入
+    fn next_header(&self, timestamp: u64) -> Header {
+        let parent = self.head();
+        let bound = parent.gas_limit / GAS_LIMIT_BOUND_DIVISOR - 1;
+        let mut header = Header {
+            parent_hash: parent.hash(),
+            ommers_hash: EMPTY_OMMERS_HASH,
+            beneficiary: self.coinbase,
+            difficulty: parent.difficulty,
+            number: parent.number + 1,
+            gas_limit: self
+                .gas_limit
+                .clamp(parent.gas_limit - bound, parent.gas_limit + bound)
+                .max(MIN_GAS_LIMIT),
+            timestamp,
+            base_fee_per_gas: Some(parent.next_base_fee()),
+            withdrawals_root: parent.withdrawals_root.map(|_| EMPTY_ROOT),
+            ..Default::default()
+        };
+        if self.fork >= Fork::Paris {
+            // prevrandao 由父区块的值推导，结果可以复现
+            header.mix_hash = keccak256(parent.mix_hash);
+        }
+        if self.fork >= Fork::Cancun {
+            header.excess_blob_gas = Some(parent.next_excess_blob_gas());
+            header.parent_beacon_block_root = Some(B256::ZERO);
+        }
+        header
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::execute_block;
+    use crate::test_utils::{dev_account, genesis};
+    use crate::transaction::EIP1559_TX_TYPE;
+    use alloy_primitives::hex;
+
+    const CHAIN_ID: u64 = 1337;
+
+    #[test]
+    fn test_local_chain() {
+        let account = dev_account();
+        let contract = Address::repeat_byte(0xcc);
+        // SSTORE(0, BLOCKHASH(NUMBER - 1)); SSTORE(1, PREVRANDAO)
+        let code = hex!("600143034060005544600155");
+        let genesis = || genesis(std::slice::from_ref(&account), &[(contract, &code)]);
+        let mut chain = LocalChain::new(genesis(), CHAIN_ID, Fork::Cancun);
+
+        let call = |nonce: u64, gas_limit: u64| {
+            let mut tx = SignedTransaction::new(EIP1559_TX_TYPE, CHAIN_ID);
+            tx.nonce = nonce;
+            tx.gas_price = U256::from(10_000_000_000u64);
+            tx.max_priority_fee_per_gas = Some(U256::from(1));
+            tx.gas_limit = gas_limit;
+            tx.to = Some(contract);
+            account.sign_transaction(tx)
+        };
+
+        // 空区块的 base fee 下降 1/8
+        chain.mine_block().unwrap();
+        assert_eq!(chain.head().number, 1);
+        assert_eq!(chain.head().base_fee_per_gas, Some(875_000_000));
+
+        chain.gas_limit = 29_990_000;
+        chain.add_transaction(call(0, 100_000));
+        // nonce 重复，被丢弃
+        let duplicate = chain.add_transaction(call(0, 100_000));
+        // 超出区块剩余的 gas，留到下一个区块
+        chain.add_transaction(call(1, 29_950_000));
+        // 同一发送者之后的交易跟着留下
+        chain.add_transaction(call(2, 100_000));
+        let block = chain.mine_block().unwrap();
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(block.header.gas_limit, 29_990_000);
+        assert_eq!(chain.pending_transactions().count(), 2);
+        let dropped = chain.dropped_transactions();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].0, duplicate);
+        assert!(matches!(dropped[0].1, EVMError::InvalidTransaction(_)));
+        let receipts = chain.receipts(2).unwrap();
+        assert!(receipts[0].success);
+        assert_eq!(receipts[0].cumulative_gas_used, chain.head().gas_used);
+        assert_eq!(
+            chain.state().get_state(contract, U256::ZERO),
+            chain.block(1).unwrap().hash().into()
+        );
+        assert_eq!(
+            chain.state().get_state(contract, U256::from(1)),
+            chain.head().mix_hash.into()
+        );
+
+        assert!(chain.mine_block_at(chain.head().timestamp).is_err());
+        let timestamp = chain.head().timestamp + 1;
+        let block = chain.mine_block_at(timestamp).unwrap();
+        assert_eq!(block.transactions.len(), 2);
+        assert_eq!(chain.pending_transactions().count(), 0);
+        assert!(chain.dropped_transactions().is_empty());
+        let hash = chain.block(3).unwrap().transactions[0].hash();
+        assert_eq!(chain.find_transaction(hash), Some((3, 0)));
+        let head = chain.head().hash();
+        assert_eq!(chain.block_by_hash(head).unwrap().header.number, 3);
+
+        // 导入区块时的校验和执行结果一致
+        let mut state: Box<dyn StateDB> = Box::new(genesis());
+        for number in 1..=3 {
+            let block = chain.block(number).unwrap();
+            let parent = &chain.block(number - 1).unwrap().header;
+            block.header.validate(parent, Fork::Cancun).unwrap();
+            block.validate_body(Fork::Cancun).unwrap();
+            let output;
+            (state, output) = execute_block(
+                state,
+                block,
+                Fork::Cancun,
+                CHAIN_ID,
+                &chain.block_hashes(number),
+            )
+            .unwrap();
+            output.validate(&block.header).unwrap();
+        }
+    }
+}
@@ -0,0 +1,770 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+
+use alloy_primitives::{Address, Bytes, U256};
+use anyhow::{anyhow, bail, Context as _, Result};
+use serde_json::{json, Value};
+
+use crate::asm::Assembler;
+use crate::blocktest;
+use crate::chain::{LocalChain, DEFAULT_GAS_LIMIT};
+use crate::context::{BlockContext, Context};
+use crate::dev_accounts::{accounts_from_mnemonic, prefund, DEFAULT_BALANCE, DEFAULT_MNEMONIC};
+use crate::disasm::disassemble;
+use crate::fork::Fork;
+use crate::fork_state::{ForkStateDB, RemoteState, RpcBackend};
+use crate::genesis::{load_alloc, parse_alloc, to_json};
+use crate::gui::Debugger;
+use crate::node::{Node, DEFAULT_CHAIN_ID};
+use crate::opcode_table::OPCODE_TABLE;
+use crate::state::{InMemoryStateDB, StateDB};
+use crate::statetest::{self, Counts, Report};
+use crate::vm::Interpreter;
+use crate::{asm_fmt, asm_lint};
+
+pub const USAGE: &str = "\
+usage:
+    evm-disasm disasm [<hex|file|->] [--json]
+    evm-disasm asm [fmt|lint] [<file|->] [--json]
+    evm-disasm run --code <hex|file|-> [--calldata <hex|file>] [--value <n>] [--gas <n>]
+                   [--fork <name>] [--alloc <file>] [--dump <file>] [--trace] [--json]
+                   [--fork-url <url> [--fork-block <n>] [--cache <file>]]
+    evm-disasm debug --code <hex|file|-> [--calldata <hex|file>] [--value <n>] [--gas <n>]
+                     [--fork <name>] [--alloc <file>] [--fork-url <url> [--fork-block <n>] [--cache <file>]]
+    evm-disasm opcodes [--fork <name>] [--json]
+    evm-disasm statetest <file|dir> [--json]
+    evm-disasm blocktest <file|dir> [--json]
+    evm-disasm node [--host <ip>] [--port <n>] [--chain-id <n>] [--fork <name>] [--alloc <file>]
+                    [--accounts <n>] [--mnemonic <words>] [--block-time <seconds>]
+
+省略输入或者输入为 `-` 时从 stdin 读取";
+
+/// 不带值的选项，其他 `--name` 后面必须跟一个值
+const FLAGS: &[&str] = &["json", "trace"];
+
+/// 合约代码部署在这个地址，调用者为零地址
+const CONTRACT: Address = Address::repeat_byte(0xcc);
+
+/// 解析后的命令行参数，选项可以是 `--name value` 或者 `--name=value`
+#[derive(Debug, Default)]
+pub struct Args {
+    pub positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg);
+                continue;
+            };
+            let option = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None if FLAGS.contains(&name) => (name.to_string(), None),
+                None => {
+                    let value = args.next().ok_or(anyhow!("--{} requires a value", name))?;
+                    (name.to_string(), Some(value))
+                }
+            };
+            parsed.options.push(option);
+        }
+        Ok(parsed)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| n == name)
+    }
+
+    /// 选项的值，重复出现时取最后一个
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// 拒绝子命令不认识的选项和多余的位置参数
+    fn check(&self, options: &[&str], max_positional: usize) -> Result<()> {
+        if let Some((name, _)) = self
+            .options
+            .iter()
+            .find(|(n, _)| !options.contains(&n.as_str()))
+        {
+            bail!("unknown option --{}", name);
+        }
+        if self.positional.len() > max_positional {
+            bail!("unexpected argument {}", self.positional[max_positional]);
+        }
+        Ok(())
+    }
+}
+
+/// 执行子命令，`args` 不包括程序名
+pub fn run(args: Vec<String>, out: &mut dyn Write) -> Result<()> {
+    let Some((command, rest)) = args.split_first() else {
+        bail!("missing command");
+    };
+    let args = Args::parse(rest.iter().cloned())?;
+    match command.as_str() {
+        "disasm" => disasm_command(&args, out),
+        "asm" => asm_command(&args, out),
+        "run" => run_command(&args, out),
+        "debug" => debug_command(&args),
+        "opcodes" => opcodes_command(&args, out),
+        "statetest" => test_command(&args, out, statetest::run_path),
+        "blocktest" => test_command(&args, out, blocktest::run_path),
+        "node" => node_command(&args, out),
+        _ => bail!("unknown command {}", command),
+    }
+}
+
+/// 读取输入：省略或者 `-` 读 stdin，存在的文件读文件，否则参数本身就是输入
+fn read_input(arg: Option<&str>) -> Result<String> {
+    match arg {
+        None | Some("-") => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .context("read stdin")?;
+            Ok(input)
+        }
+        Some(arg) if Path::new(arg).is_file() => {
+            fs::read_to_string(arg).with_context(|| format!("read {}", arg))
+        }
+        Some(arg) => Ok(arg.to_string()),
+    }
+}
+
+/// 十六进制输入，可以带 `0x` 前缀和空白
+pub fn parse_hex(input: &str) -> Result<Vec<u8>> {
+    let hex: String = input.split_whitespace().collect();
+    let hex = hex.strip_prefix("0x").unwrap_or(&hex);
+    hex::decode(hex).map_err(|e| anyhow!("invalid hex input: {}", e))
+}
+
+fn read_hex(arg: Option<&str>) -> Result<Vec<u8>> {
+    parse_hex(&read_input(arg)?)
+}
+
+fn fork(args: &Args) -> Result<Option<Fork>> {
+    args.value("fork")
+        .map(|name| Fork::from_str(name).map_err(Into::into))
+        .transpose()
+}
+
+fn print_json(out: &mut dyn Write, value: &Value) -> Result<()> {
+    writeln!(out, "{}", serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+fn disasm_command(args: &Args, out: &mut dyn Write) -> Result<()> {
+    args.check(&["json"], 1)?;
+    let code = read_hex(args.positional.first().map(String::as_str))?;
+    let instructions = disassemble(&code);
+
+    if args.flag("json") {
+        let instructions: Vec<_> = instructions
+            .iter()
+            .map(|inst| {
+                json!({
+                    "pc": inst.pc,
+                    "opcode": format!("0x{:02x}", inst.opcode),
+                    "name": inst.name(),
+                    "operand": (!inst.operand.is_empty())
+                        .then(|| format!("0x{}", hex::encode(inst.operand))),
+                })
+            })
+            .collect();
+        return print_json(out, &Value::Array(instructions));
+    }
+    for inst in &instructions {
+        writeln!(out, "{:04x} {}", inst.pc, inst)?;
+    }
+    Ok(())
+}
+
+/// `asm <file>` 输出字节码，`asm fmt <file>` 输出格式化后的源码，`asm lint <file>` 输出检查结果
+fn asm_command(args: &Args, out: &mut dyn Write) -> Result<()> {
+    args.check(&["json"], 2)?;
+    let (mode, path) = match args.positional.first().map(String::as_str) {
+        Some(mode @ ("fmt" | "lint")) => (mode, args.positional.get(1)),
+        _ => {
+            args.check(&["json"], 1)?;
+            ("asm", args.positional.first())
+        }
+    };
+    let path = path.map(String::as_str).filter(|path| *path != "-");
+    let source = || read_input(Some(path.unwrap_or("-")));
+
+    match mode {
+        "fmt" => write!(out, "{}", asm_fmt::format(&source()?)?)?,
+        "lint" => {
+            let lints = asm_lint::lint(&source()?)?;
+            if args.flag("json") {
+                let lints: Vec<_> = lints
+                    .iter()
+                    .map(|lint| json!({ "line": lint.line, "message": lint.to_string() }))
+                    .collect();
+                print_json(out, &Value::Array(lints))?;
+            } else {
+                for lint in &lints {
+                    writeln!(out, "{}: {}", path.unwrap_or("<stdin>"), lint)?;
+                }
+            }
+            if !lints.is_empty() {
+                bail!("{} lint(s) found", lints.len());
+            }
+        }
+        _ => {
+            // 从文件汇编时 `#include` 相对于文件所在目录
+            let object = match path {
+                Some(path) => Assembler::new().asm_file(path)?,
+                None => Assembler::new().asm_object(&source()?)?,
+            };
+            if args.flag("json") {
+                let link_references: Vec<_> = object
+                    .link_references
+                    .iter()
+                    .map(|r| json!({ "placeholder": r.placeholder, "offset": r.offset }))
+                    .collect();
+                print_json(
+                    out,
+                    &json!({
+                        "code": format!("0x{}", hex::encode(&object.code)),
+                        "linkReferences": link_references,
+                        "labels": object.labels,
+                    }),
+                )?;
+            } else {
+                writeln!(out, "0x{}", hex::encode(&object.code))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `run`/`debug` 共用的执行环境：先加载 `--alloc`，代码部署在 `CONTRACT`，由零地址调用
+///
+/// `--value` 先加到调用者的余额再转给合约，执行失败时不退回；`--gas` 默认为区块的 gas limit。
+/// 指定 `--fork-url` 时在上游节点的状态上执行，`--cache` 的文件在结束时写入
+struct Call {
+    state: Box<dyn StateDB>,
+    /// 分叉时的远程状态，执行后检查读取错误
+    remote: Option<Rc<RemoteState>>,
+    fork: Fork,
+    /// 最外层帧
+    ctx: Context,
+}
+
+impl Call {
+    fn from_args(args: &Args) -> Result<Self> {
+        let code = read_hex(Some(args.value("code").ok_or(anyhow!("missing --code"))?))?;
+        let calldata = match args.value("calldata") {
+            Some(calldata) => read_hex(Some(calldata))?,
+            None => Vec::new(),
+        };
+        let value = match args.value("value") {
+            Some(value) => U256::from_str(value).context("invalid --value")?,
+            None => U256::ZERO,
+        };
+        let gas = match args.value("gas") {
+            Some(gas) => gas.parse().context("invalid --gas")?,
+            None => DEFAULT_GAS_LIMIT,
+        };
+
+        let remote = match args.value("fork-url") {
+            Some(url) => {
+                let backend = match args.value("fork-block") {
+                    Some(block) => {
+                        RpcBackend::new(url, block.parse().context("invalid --fork-block")?)?
+                    }
+                    None => RpcBackend::latest(url)?,
+                };
+                Some(Rc::new(match args.value("cache") {
+                    Some(path) => RemoteState::with_cache(backend, path)?,
+                    None => RemoteState::new(backend),
+                }))
+            }
+            None if args.value("fork-block").is_some() || args.value("cache").is_some() => {
+                bail!("--fork-block and --cache require --fork-url")
+            }
+            None => None,
+        };
+        let mut state: Box<dyn StateDB> = match &remote {
+            Some(remote) => Box::new(ForkStateDB::new(remote.clone())),
+            None => Box::new(InMemoryStateDB::new()),
+        };
+        if let Some(path) = args.value("alloc") {
+            let json = fs::read_to_string(path).with_context(|| format!("read {}", path))?;
+            load_alloc(state.as_mut(), &parse_alloc(&json)?);
+        }
+        // 保留 alloc 中已有的余额和存储，只替换代码
+        for address in [Address::ZERO, CONTRACT] {
+            if !state.exists(address) {
+                state.create_object(address);
+            }
+        }
+        state.set_code(CONTRACT, code.clone().into());
+        state.add_balance(Address::ZERO, value);
+        state.transfer(Address::ZERO, CONTRACT, value)?;
+        state.commit();
+
+        let mut ctx = Context::new();
+        ctx.contract = CONTRACT;
+        ctx.code = code.into();
+        ctx.call_data = calldata.into();
+        ctx.value = value;
+        ctx.gas = gas;
+        check_remote(remote.as_deref())?;
+        Ok(Call {
+            state,
+            remote,
+            fork: fork(args)?.unwrap_or(Fork::LATEST),
+            ctx,
+        })
+    }
+}
+
+/// 读取远程状态出错时读到的值为 0，结果不可信
+fn check_remote(remote: Option<&RemoteState>) -> Result<()> {
+    match remote.and_then(RemoteState::take_error) {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+/// `run`/`debug` 共用的选项
+const CALL_OPTIONS: &[&str] = &[
+    "code",
+    "calldata",
+    "value",
+    "gas",
+    "fork",
+    "alloc",
+    "fork-url",
+    "fork-block",
+    "cache",
+];
+
+fn run_command(args: &Args, out: &mut dyn Write) -> Result<()> {
+    args.check(&[CALL_OPTIONS, &["dump", "trace", "json"]].concat(), 0)?;
+    let call = Call::from_args(args)?;
+    let blk_ctx = BlockContext::new();
+    let mut vm = Interpreter::new_with_fork(call.state, &blk_ctx, call.fork);
+    // 跟踪写到 stderr，stdout 只有执行结果，`--json` 时仍然是合法的 JSON
+    if args.flag("trace") {
+        vm.set_trace(Some(Box::new(io::stderr())));
+    }
+
+    let (ctx, result) = vm.start(call.ctx).finish();
+    check_remote(call.remote.as_deref())?;
+    let return_data = format!("0x{}", hex::encode(&ctx.output));
+    if let Some(path) = args.value("dump") {
+        fs::write(path, to_json(&vm.state().dump())? + "\n")
+            .with_context(|| format!("write {}", path))?;
+    }
+
+    if args.flag("json") {
+        return print_json(
+            out,
+            &json!({
+                "success": result.is_ok(),
+                "error": result.as_ref().err().map(ToString::to_string),
+                "returnData": return_data,
+                "instructions": vm.instruction_count(),
+            }),
+        );
+    }
+    match &result {
+        Ok(()) => writeln!(out, "success")?,
+        Err(e) => writeln!(out, "error: {}", e)?,
+    }
+    writeln!(out, "return: {}", return_data)?;
+    writeln!(out, "instructions: {}", vm.instruction_count())?;
+    Ok(())
+}
+
+fn debug_command(args: &Args) -> Result<()> {
+    args.check(CALL_OPTIONS, 0)?;
+    let call = Call::from_args(args)?;
+    let blk_ctx = BlockContext::new();
+    let mut vm = Interpreter::new_with_fork(call.state, &blk_ctx, call.fork);
+    Debugger::new(vm.start(call.ctx)).run()?;
+    check_remote(call.remote.as_deref())?;
+    Ok(())
+}
+
+/// 列出指令表，指定 `--fork` 时只列出该硬分叉启用的指令
+fn opcodes_command(args: &Args, out: &mut dyn Write) -> Result<()> {
+    args.check(&["fork", "json"], 0)?;
+    let fork = fork(args)?;
+    let infos: Vec<_> = (0..=u8::MAX)
+        .filter_map(|opcode| OPCODE_TABLE.get(&opcode))
+        .filter(|info| fork.is_none_or(|fork| info.is_enabled(fork)))
+        .collect();
+
+    if args.flag("json") {
+        let infos: Vec<_> = infos
+            .iter()
+            .map(|info| {
+                json!({
+                    "opcode": format!("0x{:02x}", info.opcode),
+                    "name": info.name,
+                    "inputs": info.inputs,
+                    "outputs": info.outputs,
+                    "immediateSize": info.immediate_size,
+                    "staticGas": info.static_gas,
+                    "fork": info.fork.name(),
+                    "description": info.description,
+                })
+            })
+            .collect();
+        return print_json(out, &Value::Array(infos));
+    }
+    for info in infos {
+        writeln!(
+            out,
+            "0x{:02x}  {:<14} {:>2} {:>2} {:>5}  {:<14} {}",
+            info.opcode,
+            info.name,
+            info.inputs,
+            info.outputs,
+            info.static_gas,
+            info.fork.name(),
+            info.description
+        )?;
+    }
+    Ok(())
+}
+
+/// 启动本地 JSON-RPC 节点，默认收到交易后立即出块，`--block-time` 改为定时出块
+fn node_command(args: &Args, out: &mut dyn Write) -> Result<()> {
+    args.check(
+        &[
+            "host",
+            "port",
+            "chain-id",
+            "fork",
+            "alloc",
+            "accounts",
+            "mnemonic",
+            "block-time",
+        ],
+        0,
+    )?;
+    let number = |name: &str, default: u64| -> Result<u64> {
+        match args.value(name) {
+            Some(value) => value.parse().with_context(|| format!("invalid --{}", name)),
+            None => Ok(default),
+        }
+    };
+    let port = u16::try_from(number("port", 8545)?).context("invalid --port")?;
+    let mnemonic = args.value("mnemonic").unwrap_or(DEFAULT_MNEMONIC);
+    let accounts = accounts_from_mnemonic(mnemonic, "", number("accounts", 10)? as usize)?;
+
+    let mut state = InMemoryStateDB::new();
+    if let Some(path) = args.value("alloc") {
+        let json = fs::read_to_string(path).with_context(|| format!("read {}", path))?;
+        load_alloc(&mut state, &parse_alloc(&json)?);
+    }
+    prefund(&mut state, &accounts, DEFAULT_BALANCE);
+    state.commit();
+    let chain = LocalChain::new(
+        state,
+        number("chain-id", DEFAULT_CHAIN_ID)?,
+        fork(args)?.unwrap_or(Fork::LATEST),
+    );
+    let mut node = Node::new(chain);
+    if args.value("block-time").is_some() {
+        node.automine = false;
+        node.block_time = Some(Duration::from_secs(number("block-time", 0)?.max(1)));
+    }
+
+    let host = args.value("host").unwrap_or("127.0.0.1");
+    let listener =
+        TcpListener::bind((host, port)).with_context(|| format!("bind {}:{}", host, port))?;
+    writeln!(out, "accounts:")?;
+    for (i, account) in accounts.iter().enumerate() {
+        writeln!(out, "({}) {} {}", i, account.address, account.secret_key())?;
+    }
+    writeln!(out, "listening on http://{}", listener.local_addr()?)?;
+    out.flush()?;
+    node.serve(listener)?;
+    Ok(())
+}
+
+/// 执行 GeneralStateTests 或 BlockchainTests，`run_path` 决定测试格式
+fn test_command(
+    args: &Args,
+    out: &mut dyn Write,
+    run_path: fn(&Path, &mut Report) -> Result<()>,
+) -> Result<()> {
+    args.check(&["json"], 1)?;
+    let Some(path) = args.positional.first() else {
+        bail!("missing test file or directory");
+    };
+    let mut report = Report::default();
+    run_path(Path::new(path), &mut report)?;
+
+    if args.flag("json") {
+        let counts = |counts: &Counts| {
+            json!({
+                "passed": counts.passed,
+                "failed": counts.failed,
+                "skipped": counts.skipped,
+            })
+        };
+        let forks: serde_json::Map<_, _> = report
+            .forks
+            .iter()
+            .map(|(fork, c)| (fork.clone(), counts(c)))
+            .collect();
+        let categories: serde_json::Map<_, _> = report
+            .categories
+            .iter()
+            .map(|(category, c)| (category.to_string(), counts(c)))
+            .collect();
+        return print_json(
+            out,
+            &json!({
+                "total": counts(&report.total),
+                "forks": forks,
+                "categories": categories,
+                "failures": report.failures,
+            }),
+        );
+    }
+    write!(out, "{}", report)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{genesis, spawn_node};
+
+    fn output(args: &[&str]) -> Result<String> {
+        let mut out = Vec::new();
+        run(args.iter().map(|s| s.to_string()).collect(), &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_args() {
+        let args =
+            Args::parse(["a", "--json", "--code", "0x00", "--value=5", "b"].map(String::from))
+                .unwrap();
+        assert_eq!(args.positional, ["a", "b"]);
+        assert!(args.flag("json"));
+        assert_eq!(args.value("code"), Some("0x00"));
+        assert_eq!(args.value("value"), Some("5"));
+        assert!(Args::parse(["--code".to_string()]).is_err());
+        assert!(output(&["run", "--code", "00", "--foo", "1"]).is_err());
+        assert!(output(&["statetest"]).is_err());
+        assert!(output(&["blocktest"]).is_err());
+        assert!(output(&["node", "--port", "65536"]).is_err());
+    }
+
+    #[test]
+    fn test_disasm() {
+        assert_eq!(
+            output(&["disasm", "0x6080 6040\n52"]).unwrap(),
+            "0000 PUSH1 0x80\n0002 PUSH1 0x40\n0004 MSTORE\n"
+        );
+        let json: Value =
+            serde_json::from_str(&output(&["disasm", "60ff00", "--json"]).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            json!([
+                { "pc": 0, "opcode": "0x60", "name": "PUSH1", "operand": "0xff" },
+                { "pc": 2, "opcode": "0x00", "name": "STOP", "operand": null },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        // 返回 calldata 的第一个字加上 callvalue
+        let code = "60003534015f5260205ff3";
+        let json: Value = serde_json::from_str(
+            &output(&[
+                "run",
+                "--code",
+                code,
+                "--calldata",
+                "0x01",
+                "--value",
+                "0x10",
+                "--json",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["success"], true);
+        assert_eq!(
+            json["returnData"],
+            format!("0x01{}{:02x}", "00".repeat(30), 0x10)
+        );
+        assert_eq!(json["instructions"], 9);
+
+        // 跟踪写到 stderr，不影响 JSON 输出
+        let json: Value = serde_json::from_str(
+            &output(&["run", "--code", "5f5ffd", "--trace", "--json"]).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["error"], "revert");
+        assert_eq!(json["instructions"], 3);
+
+        assert_eq!(
+            output(&["run", "--code", "5f5ffd"]).unwrap(),
+            "error: revert\nreturn: 0x\ninstructions: 3\n"
+        );
+
+        // 子调用返回数据之后以 STOP 结束，最外层没有返回数据
+        let code = "602a600052602060006020600060045afa00";
+        let json: Value =
+            serde_json::from_str(&output(&["run", "--code", code, "--json"]).unwrap()).unwrap();
+        assert_eq!(json["success"], true);
+        assert_eq!(json["returnData"], "0x");
+
+        // value 转给了合约，SELFBALANCE 能看到
+        let json: Value = serde_json::from_str(
+            &output(&[
+                "run",
+                "--code",
+                "475f5260205ff3",
+                "--value",
+                "0x10",
+                "--json",
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            json["returnData"],
+            format!("0x{}{:02x}", "00".repeat(31), 0x10)
+        );
+
+        // 默认的 gas 不够把内存扩展到 4 GB
+        assert_eq!(
+            output(&["run", "--code", "63f000000051"]).unwrap(),
+            "error: out of gas\nreturn: 0x\ninstructions: 2\n"
+        );
+        assert_eq!(
+            output(&["run", "--code", "5f5f01", "--gas", "6"]).unwrap(),
+            "error: out of gas\nreturn: 0x\ninstructions: 2\n"
+        );
+        assert!(output(&["run", "--code", "5f5f01", "--gas", "7"])
+            .unwrap()
+            .starts_with("success"));
+    }
+
+    #[test]
+    fn test_run_alloc_dump() {
+        let dir = std::env::temp_dir().join(format!("evm-disasm-cli-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let alloc = dir.join("alloc.json");
+        let dump = dir.join("dump.json");
+        fs::write(
+            &alloc,
+            format!(
+                r#"{{ "{}": {{ "balance": "0x1", "storage": {{ "0x0": "0x5" }} }} }}"#,
+                CONTRACT
+            ),
+        )
+        .unwrap();
+
+        // slot 0 加 1
+        output(&[
+            "run",
+            "--code",
+            "5f546001015f55",
+            "--alloc",
+            alloc.to_str().unwrap(),
+            "--dump",
+            dump.to_str().unwrap(),
+        ])
+        .unwrap();
+        let state = parse_alloc(&fs::read_to_string(&dump).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let account = &state[&CONTRACT];
+        assert_eq!(account.balance, U256::from(1));
+        assert_eq!(account.storage[&U256::ZERO], U256::from(6));
+        assert!(state.contains_key(&Address::ZERO));
+    }
+
+    #[test]
+    fn test_run_fork() {
+        let url = spawn_node(|| {
+            let mut state = genesis(&[], &[]);
+            state.create_object(CONTRACT);
+            state.set_state(CONTRACT, U256::from(1), U256::from(42));
+            state.commit();
+            state
+        });
+        let cache =
+            std::env::temp_dir().join(format!("evm-disasm-fork-{}.json", std::process::id()));
+        let _ = fs::remove_file(&cache);
+        let cache = cache.to_str().unwrap();
+
+        // 返回 SLOAD(1)，存储从上游读取
+        let code = "60015460005260206000f3";
+        let expected = format!("0x{}{:02x}", "00".repeat(31), 42);
+        let run = |url: &str| -> Result<Value> {
+            let text = output(&[
+                "run",
+                "--code",
+                code,
+                "--fork-url",
+                url,
+                "--fork-block",
+                "0",
+                "--cache",
+                cache,
+                "--json",
+            ])?;
+            Ok(serde_json::from_str(&text).unwrap())
+        };
+        assert_eq!(run(&url).unwrap()["returnData"], expected);
+
+        // 结束时写入了缓存，上游不可用时从缓存读取
+        let dead = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        assert_eq!(run(&dead).unwrap()["returnData"], expected);
+        // 缓存中没有的状态读取失败
+        fs::remove_file(cache).unwrap();
+        assert!(run(&dead).is_err());
+        fs::remove_file(cache).unwrap();
+
+        assert!(output(&["run", "--code", "00", "--fork-block", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_opcodes() {
+        let text = output(&["opcodes", "--fork", "frontier"]).unwrap();
+        assert!(text.lines().any(|line| line.starts_with("0x01  ADD ")));
+        assert!(!text.contains("PUSH0"));
+
+        let json: Value = serde_json::from_str(&output(&["opcodes", "--json"]).unwrap()).unwrap();
+        let push0 = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|info| info["name"] == "PUSH0")
+            .unwrap();
+        assert_eq!(push0["fork"], "Shanghai");
+        assert_eq!(push0["outputs"], 1);
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{mem::Memory, stack::Stack};
-use alloy_primitives::{Address, U256};
+use crate::{error::EVMError, gas, mem::Memory, stack::Stack, u256::u256_to_usize};
+use alloy_primitives::{Address, Bytes, U256};
 
 pub struct Context {
     pub stack: Stack,
@@ -8,11 +8,24 @@ pub struct Context {
     pub caller: Address,
     pub origin: Address,
     pub contract: Address,
-    pub code: Vec<u8>,
-    pub call_data: Vec<u8>,
-    pub return_data: Vec<u8>,
+    pub code: Bytes,
+    pub call_data: Bytes,
+    /// 最近一次子调用的返回数据，RETURNDATASIZE/RETURNDATACOPY 读取
+    pub return_data: Bytes,
+    /// 当前帧 RETURN/REVERT 的数据，STOP 结束时为空
+    pub output: Bytes,
     pub value: U256,
     pub depth: usize,
+    /// 剩余 gas，默认不限制
+    pub gas: u64,
+    /// STATICCALL 及其子调用中不能修改状态
+    pub is_static: bool,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Context {
@@ -24,15 +37,61 @@ impl Context {
             caller: Address::ZERO,
             origin: Address::ZERO,
             contract: Address::ZERO,
-            code: Vec::new(),
-            call_data: Vec::new(),
-            return_data: Vec::new(),
+            code: Bytes::new(),
+            call_data: Bytes::new(),
+            return_data: Bytes::new(),
+            output: Bytes::new(),
             value: U256::ZERO,
             depth: 0,
+            gas: u64::MAX,
+            is_static: false,
+        }
+    }
+
+    /// 创建子调用帧，内存和父帧共用同一个缓冲区
+    pub fn new_frame(parent: &Context) -> Self {
+        let mut ctx = Context::new();
+        ctx.memory = parent.memory.new_frame();
+        ctx.depth = parent.depth + 1;
+        ctx.origin = parent.origin;
+        ctx.is_static = parent.is_static;
+        ctx
+    }
+
+    pub fn use_gas(&mut self, gas: u64) -> Result<(), EVMError> {
+        if self.gas < gas {
+            self.gas = 0;
+            return Err(EVMError::OutOfGas);
         }
+        self.gas -= gas;
+        Ok(())
+    }
+
+    /// 为访问 `[offset, offset + size)` 扣除内存扩展的 gas 并扩展内存，返回转换后的 (offset, size)
+    ///
+    /// size 为 0 时不访问内存，offset 可以是任意值
+    pub fn expand_memory(&mut self, offset: U256, size: U256) -> Result<(usize, usize), EVMError> {
+        if size.is_zero() {
+            return Ok((0, 0));
+        }
+        let (offset, size) = (u256_to_usize(offset), u256_to_usize(size));
+        let end = offset.checked_add(size).ok_or(EVMError::OutOfGas)?;
+        if end > u32::MAX as usize {
+            return Err(EVMError::OutOfGas);
+        }
+        let words = gas::words(end as u64);
+        let current = gas::words(self.memory.len() as u64);
+        if words > current {
+            self.use_gas(gas::memory_gas(words) - gas::memory_gas(current))?;
+        }
+        self.memory.expand(offset, size);
+        Ok((offset, size))
     }
 }
 
+/// BLOCKHASH 可以查询的区块数
+pub const BLOCK_HASH_HISTORY: u64 = 256;
+
 pub struct BlockContext {
     pub chain_id: U256,
     pub block_number: U256,
@@ -44,7 +103,16 @@ pub struct BlockContext {
     pub block_hash_fee: U256,
     pub gas_price: U256,
     pub base_fee: U256,
-    pub blob_hash: U256,
+    /// 交易的 blob versioned hashes，BLOBHASH 按下标读取
+    pub blob_hashes: Vec<U256>,
+    /// 之前区块的 hash，最后一个是父区块，BLOCKHASH 只使用最近 256 个
+    pub block_hashes: Vec<U256>,
+}
+
+impl Default for BlockContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BlockContext {
@@ -59,13 +127,45 @@ impl BlockContext {
             block_hash_fee: U256::ZERO,
             gas_price: U256::ZERO,
             base_fee: U256::ZERO,
-            blob_hash: U256::ZERO,
+            blob_hashes: Vec::new(),
+            block_hashes: Vec::new(),
             chain_id: U256::ZERO,
         }
     }
 
+    /// 最近 256 个区块的 hash，其它区块和未知的 hash 返回 0
     pub fn get_block_hash(&self, block_number: U256) -> U256 {
-        // TODO implement block hash, 只支持查询最近256个区块的hash
-        U256::ZERO
+        if block_number >= self.block_number
+            || self.block_number - block_number > U256::from(BLOCK_HASH_HISTORY)
+        {
+            return U256::ZERO;
+        }
+        let distance = (self.block_number - block_number).to::<usize>();
+        self.block_hashes
+            .len()
+            .checked_sub(distance)
+            .map_or(U256::ZERO, |i| self.block_hashes[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_block_hash() {
+        let mut blk_ctx = BlockContext::new();
+        blk_ctx.block_number = U256::from(300);
+        blk_ctx.block_hashes = (0..300).map(U256::from).collect();
+        assert_eq!(blk_ctx.get_block_hash(U256::from(299)), U256::from(299));
+        assert_eq!(blk_ctx.get_block_hash(U256::from(44)), U256::from(44));
+        assert_eq!(blk_ctx.get_block_hash(U256::from(43)), U256::ZERO);
+        assert_eq!(blk_ctx.get_block_hash(U256::from(300)), U256::ZERO);
+        assert_eq!(blk_ctx.get_block_hash(U256::MAX), U256::ZERO);
+
+        // 只知道最近几个区块时，更早的区块返回 0
+        blk_ctx.block_hashes = vec![U256::from(298), U256::from(299)];
+        assert_eq!(blk_ctx.get_block_hash(U256::from(298)), U256::from(298));
+        assert_eq!(blk_ctx.get_block_hash(U256::from(297)), U256::ZERO);
     }
 }
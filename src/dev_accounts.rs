@@ -0,0 +1,274 @@
+//! 测试账户：由助记词或种子按 BIP-39/BIP-32 派生密钥，签名交易并在状态中预先充值
+
+use alloy_primitives::{keccak256, uint, Address, B256, U256};
+use hmac::{Hmac, Mac};
+use k256::ecdsa::SigningKey;
+use k256::elliptic_curve::PrimeField;
+use k256::{NonZeroScalar, Scalar};
+use sha2::Sha512;
+
+use crate::error::EVMError;
+use crate::state::StateDB;
+use crate::transaction::{Authorization, SignedTransaction, LEGACY_TX_TYPE};
+
+/// 和 anvil、hardhat 相同的默认助记词
+pub const DEFAULT_MNEMONIC: &str = "test test test test test test test test test test test junk";
+/// 以太坊账户的派生路径，最后一级是账户下标
+pub const DERIVATION_PATH: &str = "m/44'/60'/0'/0";
+/// 每个测试账户默认的余额 10000 ether
+pub const DEFAULT_BALANCE: U256 = uint!(10000000000000000000000_U256);
+
+const HARDENED: u32 = 1 << 31;
+
+#[derive(Debug, Clone)]
+pub struct DevAccount {
+    key: SigningKey,
+    pub address: Address,
+}
+
+impl DevAccount {
+    pub fn from_secret_key(secret_key: &B256) -> Result<Self, EVMError> {
+        let key = SigningKey::from_slice(secret_key.as_slice())
+            .map_err(|_| EVMError::InvalidKey("invalid secret key".to_string()))?;
+        Ok(Self::from_signing_key(key))
+    }
+
+    fn from_signing_key(key: SigningKey) -> Self {
+        let point = key.verifying_key().to_encoded_point(false);
+        let hash = keccak256(&point.as_bytes()[1..]);
+        DevAccount {
+            key,
+            address: Address::from_slice(&hash[12..]),
+        }
+    }
+
+    pub fn secret_key(&self) -> B256 {
+        B256::from_slice(&self.key.to_bytes())
+    }
+
+    /// 签名交易，legacy 交易有 chain id 时按 EIP-155 计算 v
+    pub fn sign_transaction(&self, mut tx: SignedTransaction) -> SignedTransaction {
+        let (y_parity, r, s) = self.sign_hash(tx.signature_hash());
+        tx.v = match (tx.tx_type, tx.chain_id) {
+            (LEGACY_TX_TYPE, Some(chain_id)) => chain_id * 2 + 35 + y_parity as u64,
+            (LEGACY_TX_TYPE, None) => 27 + y_parity as u64,
+            _ => y_parity as u64,
+        };
+        tx.r = r;
+        tx.s = s;
+        tx
+    }
+
+    /// 签名 EIP-7702 授权，把本账户的代码委托给 `address`
+    pub fn sign_authorization(
+        &self,
+        chain_id: U256,
+        address: Address,
+        nonce: u64,
+    ) -> Authorization {
+        let mut authorization = Authorization {
+            chain_id,
+            address,
+            nonce,
+            ..Default::default()
+        };
+        (authorization.y_parity, authorization.r, authorization.s) =
+            self.sign_hash(authorization.signature_hash());
+        authorization
+    }
+
+    /// 返回 (y parity, r, s)，s 在低半区
+    fn sign_hash(&self, hash: B256) -> (u8, U256, U256) {
+        let (mut sig, mut recid) = self
+            .key
+            .sign_prehash_recoverable(hash.as_slice())
+            .expect("signing a 32 byte hash");
+        if let Some(normalized) = sig.normalize_s() {
+            sig = normalized;
+            recid = k256::ecdsa::RecoveryId::from_byte(recid.to_byte() ^ 1).unwrap();
+        }
+        let bytes = sig.to_bytes();
+        (
+            recid.to_byte(),
+            U256::from_be_slice(&bytes[..32]),
+            U256::from_be_slice(&bytes[32..]),
+        )
+    }
+}
+
+/// 由助记词派生 `count` 个账户，不检查助记词的单词表和校验和
+pub fn accounts_from_mnemonic(
+    mnemonic: &str,
+    passphrase: &str,
+    count: usize,
+) -> Result<Vec<DevAccount>, EVMError> {
+    accounts_from_seed(&mnemonic_to_seed(mnemonic, passphrase), count)
+}
+
+/// 由 BIP-32 种子按 `DERIVATION_PATH` 派生 `count` 个账户
+pub fn accounts_from_seed(seed: &[u8], count: usize) -> Result<Vec<DevAccount>, EVMError> {
+    (0..count)
+        .map(|i| derive_key(seed, &format!("{}/{}", DERIVATION_PATH, i)))
+        .map(|key| key.map(DevAccount::from_signing_key))
+        .collect()
+}
+
+/// BIP-39：PBKDF2-HMAC-SHA512(mnemonic, "mnemonic" + passphrase, 2048)
+pub fn mnemonic_to_seed(mnemonic: &str, passphrase: &str) -> [u8; 64] {
+    let mnemonic = mnemonic.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut salt = format!("mnemonic{}", passphrase).into_bytes();
+    // 输出正好是一个块，块序号为 1
+    salt.extend(1u32.to_be_bytes());
+    let mut block = hmac_sha512(mnemonic.as_bytes(), &salt);
+    let mut seed = block;
+    for _ in 1..2048 {
+        block = hmac_sha512(mnemonic.as_bytes(), &block);
+        seed.iter_mut().zip(&block).for_each(|(s, b)| *s ^= b);
+    }
+    seed
+}
+
+/// BIP-32 私钥派生，`path` 形如 `m/44'/60'/0'/0/0`
+pub fn derive_key(seed: &[u8], path: &str) -> Result<SigningKey, EVMError> {
+    let invalid = || EVMError::InvalidKey(format!("invalid derivation path {}", path));
+    let mut parts = path.split('/');
+    if parts.next() != Some("m") {
+        return Err(invalid());
+    }
+
+    let master = hmac_sha512(b"Bitcoin seed", seed);
+    let mut key = secret_scalar(&master[..32])?;
+    let mut chain_code = master[32..].to_vec();
+    for part in parts {
+        let index = match part.strip_suffix('\'') {
+            Some(index) => index
+                .parse::<u32>()
+                .ok()
+                .filter(|&i| i < HARDENED)
+                .map(|i| i | HARDENED),
+            None => part.parse::<u32>().ok().filter(|&i| i < HARDENED),
+        }
+        .ok_or_else(invalid)?;
+
+        let mut data = Vec::with_capacity(37);
+        if index >= HARDENED {
+            data.push(0);
+            data.extend_from_slice(&key.to_bytes());
+        } else {
+            let public = SigningKey::from(key).verifying_key().to_encoded_point(true);
+            data.extend_from_slice(public.as_bytes());
+        }
+        data.extend(index.to_be_bytes());
+        let child = hmac_sha512(&chain_code, &data);
+        // 无效的子密钥概率可以忽略，直接报错
+        let tweak = secret_scalar(&child[..32])?;
+        key = Option::from(NonZeroScalar::new(*key + *tweak))
+            .ok_or_else(|| EVMError::InvalidKey("invalid child key".to_string()))?;
+        chain_code = child[32..].to_vec();
+    }
+    Ok(SigningKey::from(key))
+}
+
+/// 给每个账户增加 `balance`，和 `load_alloc` 一样由调用方提交
+pub fn prefund(state: &mut dyn StateDB, accounts: &[DevAccount], balance: U256) {
+    for account in accounts {
+        state.add_balance(account.address, balance);
+    }
+}
+
+fn secret_scalar(bytes: &[u8]) -> Result<NonZeroScalar, EVMError> {
+    let scalar: Option<Scalar> = Scalar::from_repr(*k256::FieldBytes::from_slice(bytes)).into();
+    scalar
+        .and_then(|scalar| NonZeroScalar::new(scalar).into())
+        .ok_or_else(|| EVMError::InvalidKey("invalid secret key".to_string()))
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BlockContext;
+    use crate::state::InMemoryStateDB;
+    use crate::transaction::{EIP1559_TX_TYPE, EIP2930_TX_TYPE, EIP4844_TX_TYPE, EIP7702_TX_TYPE};
+    use crate::vm::Interpreter;
+    use alloy_primitives::{address, b256, Bytes};
+
+    #[test]
+    fn test_accounts_from_mnemonic() {
+        let accounts = accounts_from_mnemonic(DEFAULT_MNEMONIC, "", 2).unwrap();
+        assert_eq!(
+            accounts[0].secret_key(),
+            b256!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+        );
+        assert_eq!(
+            accounts[0].address,
+            address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266")
+        );
+        assert_eq!(
+            accounts[1].address,
+            address!("70997970C51812dc3A010C7d01b50e0d17dc79C8")
+        );
+        assert!(derive_key(&[0; 64], "44'/60'").is_err());
+        assert!(derive_key(&[0; 64], "m/x").is_err());
+    }
+
+    #[test]
+    fn test_sign_transactions() {
+        let accounts = accounts_from_mnemonic(DEFAULT_MNEMONIC, "", 1).unwrap();
+        let account = &accounts[0];
+        let mut state = InMemoryStateDB::new();
+        prefund(&mut state, &accounts, DEFAULT_BALANCE);
+        state.commit();
+        let mut blk_ctx = BlockContext::new();
+        blk_ctx.chain_id = U256::from(1);
+        blk_ctx.block_gas_limit = U256::from(30_000_000);
+        blk_ctx.base_fee = U256::from(7);
+
+        let mut state: Box<dyn StateDB> = Box::new(state);
+        for (nonce, tx_type) in [
+            LEGACY_TX_TYPE,
+            EIP2930_TX_TYPE,
+            EIP1559_TX_TYPE,
+            EIP4844_TX_TYPE,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut tx = SignedTransaction::new(tx_type, 1);
+            tx.nonce = nonce as u64;
+            tx.gas_price = U256::from(10);
+            tx.gas_limit = 21000;
+            tx.to = Some(Address::repeat_byte(1));
+            tx.value = U256::from(1);
+            if tx_type == EIP4844_TX_TYPE {
+                tx.blob_hashes = vec![U256::from(1) << 248];
+            }
+            let raw = account.sign_transaction(tx).encode();
+
+            // 和外部的交易一样解码后执行
+            let decoded = SignedTransaction::decode(&raw).unwrap();
+            let msg = decoded.to_message(&blk_ctx).unwrap();
+            assert_eq!(msg.caller, account.address);
+            blk_ctx.gas_price = msg.effective_gas_price(blk_ctx.base_fee);
+            blk_ctx.blob_hashes = msg.blob_hashes.clone();
+            let mut vm = Interpreter::new(state, &blk_ctx);
+            assert!(vm.transact(&msg).unwrap().is_success());
+            state = vm.into_state();
+        }
+        assert_eq!(state.get_nonce(account.address), 4);
+        assert_eq!(state.get_balance(Address::repeat_byte(1)), U256::from(4));
+
+        let authorization = account.sign_authorization(U256::from(1), Address::repeat_byte(2), 4);
+        assert_eq!(authorization.recover_authority().unwrap(), account.address);
+        let mut tx = SignedTransaction::new(EIP7702_TX_TYPE, 1);
+        tx.to = Some(account.address);
+        tx.authorization_list = vec![authorization];
+        let tx = account.sign_transaction(tx);
+        assert_eq!(tx.recover_sender().unwrap(), account.address);
+    }
+}
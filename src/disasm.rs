@@ -0,0 +1,76 @@
+use std::fmt;
+
+use crate::opcode_table::OPCODE_TABLE;
+
+/// 反汇编得到的一条指令
+pub struct Instruction<'a> {
+    pub pc: usize,
+    pub opcode: u8,
+    /// PUSH 的立即数，代码末尾被截断时比指令宽度短
+    pub operand: &'a [u8],
+}
+
+impl Instruction<'_> {
+    /// 指令名，未定义的 opcode 为 None
+    pub fn name(&self) -> Option<&'static str> {
+        OPCODE_TABLE.get(&self.opcode).map(|info| info.name)
+    }
+}
+
+impl fmt::Display for Instruction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "UNKNOWN(0x{:02x})", self.opcode)?,
+        }
+        if !self.operand.is_empty() {
+            write!(f, " 0x{}", hex::encode(self.operand))?;
+        }
+        Ok(())
+    }
+}
+
+/// 按顺序反汇编字节码，PUSH 的立即数不会被当成指令
+pub fn disassemble(code: &[u8]) -> Vec<Instruction<'_>> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let immediate_size = OPCODE_TABLE
+            .get(&opcode)
+            .map_or(0, |info| info.immediate_size);
+        let end = (pc + 1 + immediate_size).min(code.len());
+        instructions.push(Instruction {
+            pc,
+            opcode,
+            operand: &code[pc + 1..end],
+        });
+        pc += 1 + immediate_size;
+    }
+    instructions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let code = hex::decode("6080604052fe0c61ff").unwrap();
+        let lines: Vec<_> = disassemble(&code)
+            .iter()
+            .map(|inst| format!("{:04x} {}", inst.pc, inst))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "0000 PUSH1 0x80",
+                "0002 PUSH1 0x40",
+                "0004 MSTORE",
+                "0005 INVALID",
+                "0006 UNKNOWN(0x0c)",
+                "0007 PUSH2 0xff",
+            ]
+        );
+    }
+}
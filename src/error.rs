@@ -12,8 +12,60 @@ pub enum EVMError {
     InvalidOpcode(u8),
     #[error("stop")]
     Stop,
+    #[error("stack underflow")]
+    StackUnderflow,
+    #[error("stack overflow")]
+    StackOverflow,
+    #[error("unknown fork: {0}")]
+    UnknownFork(String),
+    #[error("out of gas")]
+    OutOfGas,
+    #[error("write protection")]
+    WriteProtection,
+    #[error("return data out of bounds")]
+    ReturnDataOutOfBounds,
+    #[error("contract address collision")]
+    CreateCollision,
+    #[error("max code size exceeded")]
+    CodeSizeLimit,
+    #[error("invalid code: starts with 0xEF")]
+    InvalidCode,
+    #[error("precompile failure")]
+    PrecompileFailure,
+    #[error("invalid transaction: {0}")]
+    InvalidTransaction(String),
+    #[error("EIP-7702 execution unsupported")]
+    SetCodeUnsupported,
+    #[error("invalid proof: {0}")]
+    InvalidProof(String),
+    #[error("invalid rlp: {0}")]
+    InvalidRlp(String),
+    #[error("invalid block: {0}")]
+    InvalidBlock(String),
+    #[error("invalid key: {0}")]
+    InvalidKey(String),
+    #[error("rpc error: {0}")]
+    Rpc(String),
+    #[error("fork cache error: {0}")]
+    ForkCache(String),
 
     // Asm Error
     #[error("invalid asm token {0}")]
     InvalidAsmToken(String),
+    #[error("asm include error: {0}")]
+    AsmInclude(String),
+    #[error("asm include cycle: {0}")]
+    IncludeCycle(String),
+    #[error("unlinked library: {0}")]
+    UnlinkedLibrary(String),
+    #[error("undefined label: {0}")]
+    UndefinedLabel(String),
+    #[error("duplicate label: {0}")]
+    DuplicateLabel(String),
+}
+
+impl From<alloy_rlp::Error> for EVMError {
+    fn from(e: alloy_rlp::Error) -> Self {
+        EVMError::InvalidRlp(e.to_string())
+    }
 }
@@ -0,0 +1,295 @@
+use crate::{
+    breakpoint::Hit,
+    context::Context,
+    error::EVMError,
+    history::{History, StorageWrite},
+    opcode::SSTORE,
+    vm::{Action, Frame, Interpreter},
+};
+
+/// `run_until` 的停止条件，在每条指令执行后检查
+pub enum Until<'f> {
+    /// 当前帧的 pc 等于给定值
+    Pc(usize),
+    /// 当前帧的调用深度等于给定值
+    Depth(usize),
+    /// 自定义条件
+    Predicate(Box<dyn FnMut(&Context) -> bool + 'f>),
+}
+
+impl Until<'_> {
+    fn is_met(&mut self, ctx: &Context) -> bool {
+        match self {
+            Until::Pc(pc) => ctx.pc == *pc,
+            Until::Depth(depth) => ctx.depth == *depth,
+            Until::Predicate(predicate) => predicate(ctx),
+        }
+    }
+}
+
+/// 可以逐条执行的一次调用，跨越 CALL/CREATE 等调用边界
+///
+/// 由 `Interpreter::start` 或 `Interpreter::start_call` 创建，
+/// 中途丢弃时会清空解释器上剩余的调用帧
+pub struct Execution<'v, 'a> {
+    vm: &'v mut Interpreter<'a>,
+    /// 最外层帧结束后保存它的 Context 和执行结果
+    finished: Option<(Context, Result<(), EVMError>)>,
+    history: Option<History>,
+}
+
+impl<'v, 'a> Execution<'v, 'a> {
+    pub(crate) fn new(vm: &'v mut Interpreter<'a>) -> Self {
+        Self {
+            vm,
+            finished: None,
+            history: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.is_some()
+    }
+
+    /// 执行结果，执行结束前为 None
+    pub fn result(&self) -> Option<&Result<(), EVMError>> {
+        self.finished.as_ref().map(|(_, result)| result)
+    }
+
+    /// 当前帧的 Context，执行结束后是最外层帧的 Context
+    pub fn context(&self) -> &Context {
+        match &self.finished {
+            Some((ctx, _)) => ctx,
+            None => &self.frames().last().expect("frame stack is empty").ctx,
+        }
+    }
+
+    /// 调用栈，第一个是最外层帧，最后一个是当前帧
+    pub fn frames(&self) -> &[Frame] {
+        &self.vm.frames
+    }
+
+    pub fn interpreter(&self) -> &Interpreter<'a> {
+        self.vm
+    }
+
+    /// 从现在开始用 `step` 执行的每一步都记录到执行日志
+    pub fn record_history(&mut self) {
+        if self.history.is_none() && !self.is_finished() {
+            self.history = Some(History::new(self.frames()));
+        }
+    }
+
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    pub fn history_mut(&mut self) -> Option<&mut History> {
+        self.history.as_mut()
+    }
+
+    /// 执行一条指令，执行结束后返回 false
+    ///
+    /// 子帧执行完最后一条指令后，下一步会先回到父帧，再执行父帧的下一条指令。
+    /// 如果执行日志不在最新位置，先回到最新位置再执行
+    pub fn step(&mut self) -> bool {
+        self.step_with(true);
+        !self.is_finished()
+    }
+
+    /// 执行一条指令，停在断点上没有执行指令时返回 false
+    fn step_with(&mut self, skip_breakpoint: bool) -> bool {
+        if let Some(history) = &mut self.history {
+            history.seek(history.len());
+        }
+
+        self.vm.skip_breakpoint = skip_breakpoint;
+        while !self.is_finished() {
+            let storage = self.history.as_ref().and_then(|_| self.storage_write());
+            let count = self.vm.instruction_count();
+            let mut frame = self.vm.frames.pop().expect("frame stack is empty");
+            let action = self.vm.execute(&mut frame.ctx);
+            if let Action::Pause = action {
+                self.vm.frames.push(frame);
+                return false;
+            }
+            // 代码执行完后的返回不算一步
+            let ran_off_end =
+                count == self.vm.instruction_count() && matches!(action, Action::Return(Ok(_)));
+
+            self.finished = self.vm.handle_action(frame, action);
+            if let Some(history) = &mut self.history {
+                let root = self.finished.as_ref().map(|(ctx, _)| ctx);
+                history.record(storage, &self.vm.frames, root);
+            }
+            if !ran_off_end {
+                break;
+            }
+        }
+        true
+    }
+
+    /// 一直执行到命中断点或者监视点，返回这期间的命中，执行结束时为空
+    ///
+    /// 断点命中时停在指令执行前，再次调用时从这条指令继续；监视点命中时停在指令执行后
+    pub fn resume(&mut self) -> &[Hit] {
+        let start = self.vm.hits().len();
+        let mut skip_breakpoint = true;
+        while !self.is_finished() && self.step_with(skip_breakpoint) {
+            skip_breakpoint = false;
+            if self.vm.hits().len() > start {
+                break;
+            }
+        }
+        &self.vm.hits()[start..]
+    }
+
+    /// 下一条指令是 SSTORE 时，它将要写入的存储
+    fn storage_write(&self) -> Option<StorageWrite> {
+        let ctx = self.context();
+        if ctx.code.get(ctx.pc) != Some(&SSTORE) || ctx.stack.len() < 2 {
+            return None;
+        }
+        let slot = ctx.stack.peek();
+        Some(StorageWrite {
+            address: ctx.contract,
+            slot,
+            old: self.vm.state().get_state(ctx.contract, slot),
+            new: ctx.stack.as_slice()[ctx.stack.len() - 2],
+        })
+    }
+
+    /// 执行一条指令，如果进入了子调用，一直执行到回到当前帧
+    pub fn step_over(&mut self) -> bool {
+        let depth = self.frames().len();
+        while self.step() {
+            if self.frames().len() <= depth {
+                break;
+            }
+        }
+        !self.is_finished()
+    }
+
+    /// 一直执行到满足条件或者执行结束，至少执行一条指令
+    pub fn run_until(&mut self, mut until: Until) -> bool {
+        while self.step() {
+            if until.is_met(self.context()) {
+                break;
+            }
+        }
+        !self.is_finished()
+    }
+
+    /// 执行到结束，返回最外层帧的 Context 和执行结果，不记录执行日志
+    pub fn finish(mut self) -> (Context, Result<(), EVMError>) {
+        match self.finished.take() {
+            Some(done) => done,
+            None => {
+                self.vm.pause_on_break = false;
+                self.vm.run_frames()
+            }
+        }
+    }
+}
+
+impl Drop for Execution<'_, '_> {
+    fn drop(&mut self) {
+        // 子帧的内存先释放
+        while self.vm.frames.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BlockContext;
+    use crate::test_utils::deploy;
+    use alloy_primitives::{Bytes, U256};
+
+    // 部署一个把 0x2a 写入内存并返回的合约，然后调用它
+    const CALLER: &str = r#"
+        PUSH19 0x69602a60005260206000f3600052600a6016f3
+        PUSH1 0
+        MSTORE
+        PUSH1 0x13
+        PUSH1 0x0d
+        PUSH1 0
+        CREATE
+        PUSH1 0x20
+        PUSH1 0x40
+        PUSH1 0
+        PUSH1 0
+        PUSH1 0
+        DUP6
+        GAS
+        CALL
+        STOP
+    "#;
+
+    #[test]
+    fn test_step() {
+        let (state, caller, contract) = deploy(CALLER);
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let mut exec = vm.start_call(caller, caller, contract, Bytes::new(), U256::ZERO);
+
+        assert_eq!(exec.context().pc, 0);
+        assert!(exec.step());
+        assert_eq!(exec.context().pc, 20);
+        assert_eq!(exec.context().stack.len(), 1);
+
+        // CREATE 之后进入初始化代码
+        assert!(exec.run_until(Until::Depth(1)));
+        assert_eq!(exec.frames().len(), 2);
+        assert_eq!(exec.context().pc, 0);
+
+        // 初始化代码执行 RETURN 后回到父帧
+        assert!(exec.run_until(Until::Depth(0)));
+        assert_ne!(exec.context().stack.peek(), U256::ZERO);
+
+        while exec.step() {}
+        assert!(matches!(exec.result(), Some(Ok(()))));
+        assert_eq!(exec.context().stack.peek(), U256::from(1));
+    }
+
+    #[test]
+    fn test_step_over() {
+        let (state, caller, contract) = deploy(CALLER);
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let mut exec = vm.start_call(caller, caller, contract, Bytes::new(), U256::ZERO);
+
+        // 停在 CALL 上，跳过整个子调用
+        assert!(exec.run_until(Until::Pc(42)));
+        assert!(exec.step_over());
+        assert_eq!(exec.frames().len(), 1);
+        assert_eq!(exec.context().pc, 43);
+        assert_eq!(exec.context().return_data.len(), 32);
+
+        assert!(!exec.step());
+        assert!(exec.is_finished());
+    }
+
+    #[test]
+    fn test_run_until_predicate() {
+        let (state, caller, contract) = deploy(CALLER);
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let mut exec = vm.start_call(caller, caller, contract, Bytes::new(), U256::ZERO);
+
+        assert!(exec.run_until(Until::Predicate(Box::new(|ctx| ctx.stack.len() == 6))));
+        assert_eq!(exec.context().pc, 40);
+
+        let (mut ctx, result) = exec.finish();
+        assert!(result.is_ok());
+        assert_eq!(ctx.memory.read32(0x40), U256::from(0x2a));
+
+        // 中途丢弃的执行不会影响下一次执行
+        let mut exec = vm.start_call(caller, caller, contract, Bytes::new(), U256::ZERO);
+        exec.run_until(Until::Depth(1));
+        drop(exec);
+        assert!(vm
+            .run(caller, caller, contract, Bytes::new(), U256::ZERO)
+            .is_ok());
+    }
+}
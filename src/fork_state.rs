@@ -0,0 +1,757 @@
+//! 分叉状态：按需从上游 JSON-RPC 节点读取固定区块的状态，修改只保存在本地
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256, U64};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::EVMError;
+use crate::genesis::{GenesisAccount, GenesisAlloc};
+use crate::proof::AccountProof;
+use crate::state::{InMemoryStateDB, StateDB, StateObject};
+
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 远程状态的来源，所有查询都基于同一个区块
+pub trait StateBackend {
+    fn block_number(&self) -> u64;
+    /// 账户的余额、nonce 和代码，`storage` 为空
+    fn account(&self, address: Address) -> Result<GenesisAccount, EVMError>;
+    fn storage(&self, address: Address, slot: U256) -> Result<U256, EVMError>;
+}
+
+/// 通过 HTTP JSON-RPC 查询上游节点，只支持 `http://` 地址
+pub struct RpcBackend {
+    host: String,
+    port: u16,
+    path: String,
+    block: u64,
+}
+
+impl RpcBackend {
+    pub fn new(url: &str, block: u64) -> Result<Self, EVMError> {
+        let invalid = || {
+            EVMError::Rpc(format!(
+                "invalid url {}, expected http://host:port/path",
+                url
+            ))
+        };
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(RpcBackend {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            block,
+        })
+    }
+
+    /// 固定在上游当前的最新区块
+    pub fn latest(url: &str) -> Result<Self, EVMError> {
+        let mut backend = Self::new(url, 0)?;
+        let [number] = backend.batch([("eth_blockNumber", json!([]))])?;
+        backend.block = parse::<U64>(number)?.to();
+        Ok(backend)
+    }
+
+    /// 一次发送多个请求，按请求的顺序返回结果
+    fn batch<const N: usize>(&self, calls: [(&str, Value); N]) -> Result<[Value; N], EVMError> {
+        let requests: Vec<_> = calls
+            .into_iter()
+            .enumerate()
+            .map(|(id, (method, params))| {
+                json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+            })
+            .collect();
+        let body = self.post(&Value::Array(requests).to_string())?;
+        let responses: Vec<Value> =
+            serde_json::from_slice(&body).map_err(|e| EVMError::Rpc(e.to_string()))?;
+
+        let mut results: [Value; N] = std::array::from_fn(|_| Value::Null);
+        let mut found = [false; N];
+        for mut response in responses {
+            let id = response["id"].as_u64().map(|id| id as usize);
+            let Some(id) = id.filter(|&id| id < N) else {
+                return Err(EVMError::Rpc(format!("unexpected response {}", response)));
+            };
+            if let Some(error) = response.get("error") {
+                return Err(EVMError::Rpc(match error["message"].as_str() {
+                    Some(message) => message.to_string(),
+                    None => error.to_string(),
+                }));
+            }
+            results[id] = response["result"].take();
+            found[id] = true;
+        }
+        match found.iter().all(|&found| found) {
+            true => Ok(results),
+            false => Err(EVMError::Rpc("missing response".to_string())),
+        }
+    }
+
+    /// 发送一个 HTTP POST 请求，返回响应体
+    fn post(&self, body: &str) -> Result<Vec<u8>, EVMError> {
+        let io_error = |e: std::io::Error| EVMError::Rpc(e.to_string());
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).map_err(io_error)?;
+        stream
+            .set_read_timeout(Some(RPC_TIMEOUT))
+            .map_err(io_error)?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\n\
+             Host: {}:{}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            self.port,
+            body.len(),
+            body
+        )
+        .map_err(io_error)?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).map_err(io_error)?;
+
+        let end = response
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(|| EVMError::Rpc("invalid http response".to_string()))?;
+        let head = String::from_utf8_lossy(&response[..end]).to_string();
+        let body = &response[end + 4..];
+        let status = head.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("200") {
+            return Err(EVMError::Rpc(format!("http status {}", status)));
+        }
+        let chunked = head.lines().any(|line| {
+            line.split_once(':').is_some_and(|(name, value)| {
+                name.trim().eq_ignore_ascii_case("transfer-encoding")
+                    && value.trim().eq_ignore_ascii_case("chunked")
+            })
+        });
+        match chunked {
+            true => decode_chunked(body),
+            false => Ok(body.to_vec()),
+        }
+    }
+}
+
+impl StateBackend for RpcBackend {
+    fn block_number(&self) -> u64 {
+        self.block
+    }
+
+    fn account(&self, address: Address) -> Result<GenesisAccount, EVMError> {
+        let block = format!("{:#x}", self.block);
+        let [balance, nonce, code] = self.batch([
+            ("eth_getBalance", json!([address, block])),
+            ("eth_getTransactionCount", json!([address, block])),
+            ("eth_getCode", json!([address, block])),
+        ])?;
+        Ok(GenesisAccount {
+            balance: parse(balance)?,
+            nonce: parse::<U64>(nonce)?.to(),
+            code: parse(code)?,
+            storage: BTreeMap::new(),
+        })
+    }
+
+    fn storage(&self, address: Address, slot: U256) -> Result<U256, EVMError> {
+        let block = format!("{:#x}", self.block);
+        let [value] = self.batch([("eth_getStorageAt", json!([address, slot, block]))])?;
+        parse(value)
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: Value) -> Result<T, EVMError> {
+    serde_json::from_value(value).map_err(|e| EVMError::Rpc(e.to_string()))
+}
+
+/// `Transfer-Encoding: chunked` 的响应体
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, EVMError> {
+    let invalid = || EVMError::Rpc("invalid chunked response".to_string());
+    let mut out = Vec::new();
+    loop {
+        let end = body
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(invalid)?;
+        let size = std::str::from_utf8(&body[..end]).map_err(|_| invalid())?;
+        let size = usize::from_str_radix(size.split(';').next().unwrap().trim(), 16)
+            .map_err(|_| invalid())?;
+        body = &body[end + 2..];
+        if size == 0 {
+            return Ok(out);
+        }
+        if body.len() < size + 2 {
+            return Err(invalid());
+        }
+        out.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
+
+/// 已经从远程读取的状态，值为 0 的槽位也要保存
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    block: u64,
+    accounts: BTreeMap<Address, GenesisAccount>,
+    storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+}
+
+/// 上游状态和读取过的缓存，多个 `ForkStateDB` 可以共享
+pub struct RemoteState {
+    backend: Box<dyn StateBackend>,
+    cache: RefCell<Cache>,
+    cache_path: Option<PathBuf>,
+    /// 读取接口不能返回错误，远程请求的第一个错误保存在这里
+    error: RefCell<Option<EVMError>>,
+}
+
+impl RemoteState {
+    pub fn new(backend: impl StateBackend + 'static) -> Self {
+        let cache = Cache {
+            block: backend.block_number(),
+            ..Default::default()
+        };
+        RemoteState {
+            backend: Box::new(backend),
+            cache: RefCell::new(cache),
+            cache_path: None,
+            error: RefCell::new(None),
+        }
+    }
+
+    /// 使用磁盘上的缓存文件，文件不存在或者区块不同时从空缓存开始
+    pub fn with_cache(
+        backend: impl StateBackend + 'static,
+        path: impl Into<PathBuf>,
+    ) -> Result<Self, EVMError> {
+        let mut remote = Self::new(backend);
+        let path = path.into();
+        if path.exists() {
+            let json = fs::read_to_string(&path).map_err(|e| EVMError::ForkCache(e.to_string()))?;
+            let cache: Cache =
+                serde_json::from_str(&json).map_err(|e| EVMError::ForkCache(e.to_string()))?;
+            if cache.block == remote.block_number() {
+                remote.cache = RefCell::new(cache);
+            }
+        }
+        remote.cache_path = Some(path);
+        Ok(remote)
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.backend.block_number()
+    }
+
+    /// 把缓存写入 `with_cache` 指定的文件
+    pub fn save_cache(&self) -> Result<(), EVMError> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string(&*self.cache.borrow())
+            .map_err(|e| EVMError::ForkCache(e.to_string()))?;
+        fs::write(path, json).map_err(|e| EVMError::ForkCache(e.to_string()))
+    }
+
+    /// 取出读取远程状态时的错误，出错时读到的值为 0，执行结果不可信。
+    /// `ForkStateDB` 上执行的交易会取出它并返回错误
+    pub fn take_error(&self) -> Option<EVMError> {
+        self.error.borrow_mut().take()
+    }
+
+    fn record_error(&self, error: EVMError) {
+        self.error.borrow_mut().get_or_insert(error);
+    }
+
+    /// 先查缓存，读取成功的账户写入缓存
+    fn fetch_account(&self, address: Address) -> Result<GenesisAccount, EVMError> {
+        if let Some(account) = self.cache.borrow().accounts.get(&address) {
+            return Ok(account.clone());
+        }
+        let account = self.backend.account(address)?;
+        let mut cache = self.cache.borrow_mut();
+        cache.accounts.insert(address, account.clone());
+        Ok(account)
+    }
+
+    fn fetch_storage(&self, address: Address, slot: U256) -> Result<U256, EVMError> {
+        let cached = self
+            .cache
+            .borrow()
+            .storage
+            .get(&address)
+            .and_then(|storage| storage.get(&slot).copied());
+        if let Some(value) = cached {
+            return Ok(value);
+        }
+        let value = self.backend.storage(address, slot)?;
+        let mut cache = self.cache.borrow_mut();
+        cache
+            .storage
+            .entry(address)
+            .or_default()
+            .insert(slot, value);
+        Ok(value)
+    }
+
+    /// 出错时记录错误并返回空账户
+    fn account(&self, address: Address) -> GenesisAccount {
+        self.fetch_account(address).unwrap_or_else(|e| {
+            self.record_error(e);
+            GenesisAccount::default()
+        })
+    }
+
+    fn storage(&self, address: Address, slot: U256) -> U256 {
+        self.fetch_storage(address, slot).unwrap_or_else(|e| {
+            self.record_error(e);
+            U256::ZERO
+        })
+    }
+}
+
+/// 释放时把缓存写入 `with_cache` 指定的文件，需要处理写入错误时先调用 `save_cache`
+impl Drop for RemoteState {
+    fn drop(&mut self) {
+        let _ = self.save_cache();
+    }
+}
+
+/// 以远程状态为基础的 `StateDB`
+///
+/// 账户和槽位第一次被修改前从远程加载到本地的 `InMemoryStateDB`，之后只读写本地。
+/// 状态根、`dump` 和证明只包括加载到本地的账户
+pub struct ForkStateDB {
+    remote: Rc<RemoteState>,
+    local: InMemoryStateDB,
+    loaded_accounts: HashSet<Address>,
+    loaded_slots: HashSet<(Address, U256)>,
+    /// 在本地被删除的账户，存储不再从远程读取
+    cleared: HashSet<Address>,
+}
+
+impl ForkStateDB {
+    pub fn new(remote: Rc<RemoteState>) -> Self {
+        ForkStateDB {
+            remote,
+            local: InMemoryStateDB::new(),
+            loaded_accounts: HashSet::new(),
+            loaded_slots: HashSet::new(),
+            cleared: HashSet::new(),
+        }
+    }
+
+    pub fn remote(&self) -> &Rc<RemoteState> {
+        &self.remote
+    }
+
+    /// 账户在本地修改之前先从远程加载，读取失败时不标记为已加载，下次访问重新读取
+    fn load_account(&mut self, address: Address) {
+        if self.loaded_accounts.contains(&address) {
+            return;
+        }
+        let account = match self.remote.fetch_account(address) {
+            Ok(account) => account,
+            Err(e) => return self.remote.record_error(e),
+        };
+        self.loaded_accounts.insert(address);
+        if account.balance.is_zero() && account.nonce == 0 && account.code.is_empty() {
+            return;
+        }
+        let mut object = match account.code.is_empty() {
+            true => StateObject::new_with_address(address),
+            false => StateObject::new_with_code(address, account.code),
+        };
+        object.balance = account.balance;
+        object.nonce = account.nonce;
+        self.local.load_account(object);
+    }
+
+    fn load_slot(&mut self, address: Address, slot: U256) {
+        if self.is_local_slot(address, slot) {
+            return;
+        }
+        let value = match self.remote.fetch_storage(address, slot) {
+            Ok(value) => value,
+            Err(e) => return self.remote.record_error(e),
+        };
+        self.loaded_slots.insert((address, slot));
+        self.local.load_storage(address, slot, value);
+    }
+
+    fn is_local_slot(&self, address: Address, slot: U256) -> bool {
+        self.cleared.contains(&address) || self.loaded_slots.contains(&(address, slot))
+    }
+}
+
+impl StateDB for ForkStateDB {
+    fn create_object(&mut self, address: Address) {
+        self.load_account(address);
+        self.local.create_object(address);
+    }
+
+    fn create_contract(&mut self, caller: Address, code: Bytes) -> Address {
+        self.load_account(caller);
+        self.load_account(caller.create(self.get_nonce(caller)));
+        self.local.create_contract(caller, code)
+    }
+
+    fn set_code(&mut self, cotnract: Address, code: Bytes) {
+        self.load_account(cotnract);
+        self.local.set_code(cotnract, code);
+    }
+
+    fn transfer(&mut self, from: Address, to: Address, value: U256) -> Result<(), EVMError> {
+        self.load_account(from);
+        self.load_account(to);
+        self.local.transfer(from, to, value)
+    }
+
+    fn sub_balance(&mut self, address: Address, value: U256) -> Result<U256, EVMError> {
+        self.load_account(address);
+        self.local.sub_balance(address, value)
+    }
+
+    fn add_balance(&mut self, address: Address, value: U256) -> U256 {
+        self.load_account(address);
+        self.local.add_balance(address, value)
+    }
+
+    fn get_balance(&self, address: Address) -> U256 {
+        match self.loaded_accounts.contains(&address) {
+            true => self.local.get_balance(address),
+            false => self.remote.account(address).balance,
+        }
+    }
+
+    fn get_nonce(&self, address: Address) -> u64 {
+        match self.loaded_accounts.contains(&address) {
+            true => self.local.get_nonce(address),
+            false => self.remote.account(address).nonce,
+        }
+    }
+
+    fn set_nonce(&mut self, address: Address, nonce: u64) {
+        self.load_account(address);
+        self.local.set_nonce(address, nonce);
+    }
+
+    fn get_code(&self, address: Address) -> Bytes {
+        match self.loaded_accounts.contains(&address) {
+            true => self.local.get_code(address),
+            false => self.remote.account(address).code,
+        }
+    }
+
+    fn get_code_hash(&self, address: Address) -> U256 {
+        match self.loaded_accounts.contains(&address) {
+            true => self.local.get_code_hash(address),
+            false => {
+                let code = self.remote.account(address).code;
+                match code.is_empty() {
+                    true => U256::ZERO,
+                    false => keccak256(&code).into(),
+                }
+            }
+        }
+    }
+
+    fn get_code_size(&self, address: Address) -> usize {
+        self.get_code(address).len()
+    }
+
+    /// 远程无法区分空账户和不存在的账户，都当作不存在
+    fn exists(&self, address: Address) -> bool {
+        match self.loaded_accounts.contains(&address) {
+            true => self.local.exists(address),
+            false => {
+                let account = self.remote.account(address);
+                !account.balance.is_zero() || account.nonce != 0 || !account.code.is_empty()
+            }
+        }
+    }
+
+    fn get_state(&self, address: Address, slot: U256) -> U256 {
+        match self.is_local_slot(address, slot) {
+            true => self.local.get_state(address, slot),
+            false => self.remote.storage(address, slot),
+        }
+    }
+
+    fn set_state(&mut self, address: Address, slot: U256, value: U256) {
+        self.load_slot(address, slot);
+        self.local.set_state(address, slot, value);
+    }
+
+    fn get_committed_state(&self, address: Address, slot: U256) -> U256 {
+        match self.is_local_slot(address, slot) {
+            true => self.local.get_committed_state(address, slot),
+            false => self.remote.storage(address, slot),
+        }
+    }
+
+    fn get_transition_state(&self, address: Address, slot: U256) -> U256 {
+        self.local.get_transition_state(address, slot)
+    }
+
+    fn set_transition_state(&mut self, address: Address, slot: U256, value: U256) {
+        self.local.set_transition_state(address, slot, value);
+    }
+
+    fn access_address(&mut self, address: Address) -> bool {
+        self.local.access_address(address)
+    }
+
+    fn access_slot(&mut self, address: Address, slot: U256) -> bool {
+        self.local.access_slot(address, slot)
+    }
+
+    fn add_refund(&mut self, gas: u64) {
+        self.local.add_refund(gas);
+    }
+
+    fn sub_refund(&mut self, gas: u64) {
+        self.local.sub_refund(gas);
+    }
+
+    fn get_refund(&self) -> u64 {
+        self.local.get_refund()
+    }
+
+    fn selfdestruct(&mut self, address: Address) {
+        self.load_account(address);
+        self.local.selfdestruct(address);
+    }
+
+    fn is_created(&self, address: Address) -> bool {
+        self.local.is_created(address)
+    }
+
+    fn snapshot(&mut self) -> usize {
+        self.local.snapshot()
+    }
+
+    fn revert_to_snapshot(&mut self, snapshot: usize) {
+        self.local.revert_to_snapshot(snapshot);
+    }
+
+    fn prepare(&mut self) {
+        self.local.prepare();
+    }
+
+    fn finalize(&mut self) {
+        self.local.finalize();
+        // 被删除的账户的存储也一起删除了
+        let cleared: Vec<_> = self
+            .loaded_accounts
+            .iter()
+            .filter(|&&address| !self.local.exists(address))
+            .copied()
+            .collect();
+        self.cleared.extend(cleared);
+    }
+
+    fn commit(&mut self) {
+        self.local.commit();
+    }
+
+    fn add_log(&mut self, address: Address, topics: Vec<U256>, data: Vec<u8>) {
+        self.local.add_log(address, topics, data);
+    }
+
+    fn logs(&self) -> &[(Address, Vec<U256>, Vec<u8>)] {
+        self.local.logs()
+    }
+
+    fn dump(&self) -> GenesisAlloc {
+        self.local.dump()
+    }
+
+    fn state_root(&self) -> B256 {
+        self.local.state_root()
+    }
+
+    fn get_proof(&self, address: Address, slots: &[U256]) -> Result<AccountProof, EVMError> {
+        self.local.get_proof(address, slots)
+    }
+
+    fn take_error(&mut self) -> Option<EVMError> {
+        self.remote.take_error()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BlockContext;
+    use crate::fork::Fork;
+    use crate::test_utils::{genesis, spawn_node};
+    use crate::transaction::Message;
+    use crate::vm::Interpreter;
+    use alloy_primitives::hex;
+    use std::cell::Cell;
+    use std::net::TcpListener;
+
+    const USER: Address = Address::repeat_byte(0xaa);
+    const COUNTER: Address = Address::repeat_byte(0xcc);
+
+    /// 在本地节点上提供远程状态，节点只有创世区块
+    fn mock_server() -> String {
+        spawn_node(|| {
+            // SSTORE(1, SLOAD(1) + 1)
+            let mut state = genesis(&[], &[(COUNTER, &hex!("6001546001016001550000"))]);
+            state.add_balance(USER, U256::from(1_000_000));
+            state.set_nonce(USER, 5);
+            state.set_state(COUNTER, U256::from(1), U256::from(42));
+            state.commit();
+            state
+        })
+    }
+
+    /// 第一次读取 `fail` 账户时失败，其它请求交给 `inner`
+    struct FlakyBackend {
+        inner: RpcBackend,
+        fail: Cell<Option<Address>>,
+    }
+
+    impl StateBackend for FlakyBackend {
+        fn block_number(&self) -> u64 {
+            self.inner.block_number()
+        }
+
+        fn account(&self, address: Address) -> Result<GenesisAccount, EVMError> {
+            if self.fail.get() == Some(address) {
+                self.fail.set(None);
+                return Err(EVMError::Rpc("connection reset".to_string()));
+            }
+            self.inner.account(address)
+        }
+
+        fn storage(&self, address: Address, slot: U256) -> Result<U256, EVMError> {
+            self.inner.storage(address, slot)
+        }
+    }
+
+    /// 没有服务的地址，任何远程请求都会失败
+    fn dead_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[test]
+    fn test_fork_state() {
+        let url = mock_server();
+        let path = std::env::temp_dir().join(format!("evm-fork-cache-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        // 释放时会写入缓存文件，删除文件之前先释放所有 RemoteState
+        {
+            let backend = RpcBackend::latest(&url).unwrap();
+            assert_eq!(backend.block_number(), 0);
+            let remote = Rc::new(RemoteState::with_cache(backend, &path).unwrap());
+            let state = ForkStateDB::new(remote.clone());
+            assert_eq!(state.get_balance(USER), U256::from(1_000_000));
+            assert_eq!(state.get_nonce(USER), 5);
+            assert_eq!(state.get_code_size(COUNTER), 11);
+            assert_eq!(state.get_state(COUNTER, U256::from(1)), U256::from(42));
+            assert!(!state.exists(Address::repeat_byte(0xbb)));
+
+            // 在远程状态上执行交易，修改只保存在本地
+            let mut blk_ctx = BlockContext::new();
+            blk_ctx.block_gas_limit = U256::from(30_000_000);
+            let mut vm = Interpreter::new_with_fork(Box::new(state), &blk_ctx, Fork::Cancun);
+            let msg = Message {
+                caller: USER,
+                to: Some(COUNTER),
+                nonce: 5,
+                gas_limit: 100_000,
+                value: U256::from(1),
+                ..Default::default()
+            };
+            assert!(vm.transact(&msg).unwrap().is_success());
+            let state = vm.into_state();
+            assert_eq!(state.get_state(COUNTER, U256::from(1)), U256::from(43));
+            assert_eq!(state.get_nonce(USER), 6);
+            assert_eq!(state.get_balance(USER), U256::from(999_999));
+            assert_eq!(state.get_balance(COUNTER), U256::from(1));
+            assert!(remote.take_error().is_none());
+
+            // 共享缓存的另一个分叉看到的还是远程状态
+            let fork = ForkStateDB::new(remote.clone());
+            assert_eq!(fork.get_state(COUNTER, U256::from(1)), U256::from(42));
+            assert_eq!(fork.get_nonce(USER), 5);
+
+            // 上游不可用时从磁盘缓存读取
+            remote.save_cache().unwrap();
+            let backend = RpcBackend::new(&dead_url(), 0).unwrap();
+            let cached = Rc::new(RemoteState::with_cache(backend, &path).unwrap());
+            let fork = ForkStateDB::new(cached.clone());
+            assert_eq!(fork.get_state(COUNTER, U256::from(1)), U256::from(42));
+            assert_eq!(fork.get_code(COUNTER), state.get_code(COUNTER));
+            assert!(!fork.exists(Address::repeat_byte(0xbb)));
+            assert!(cached.take_error().is_none());
+            assert_eq!(fork.get_state(COUNTER, U256::from(2)), U256::ZERO);
+            assert!(matches!(cached.take_error(), Some(EVMError::Rpc(_))));
+
+            // 缓存属于别的区块时不使用
+            let backend = RpcBackend::new(&dead_url(), 1).unwrap();
+            let other = ForkStateDB::new(Rc::new(RemoteState::with_cache(backend, &path).unwrap()));
+            assert_eq!(other.get_balance(USER), U256::ZERO);
+            assert!(other.remote().take_error().is_some());
+
+            // 上游没有固定的区块
+            assert!(RpcBackend::new(&url, 7).unwrap().account(USER).is_err());
+            assert!(RpcBackend::new("https://example.com", 0).is_err());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_fork_state_error() {
+        let backend = FlakyBackend {
+            inner: RpcBackend::latest(&mock_server()).unwrap(),
+            fail: Cell::new(Some(COUNTER)),
+        };
+        let state = ForkStateDB::new(Rc::new(RemoteState::new(backend)));
+        let mut blk_ctx = BlockContext::new();
+        blk_ctx.block_gas_limit = U256::from(30_000_000);
+        let mut vm = Interpreter::new_with_fork(Box::new(state), &blk_ctx, Fork::Cancun);
+        let msg = Message {
+            caller: USER,
+            to: Some(COUNTER),
+            nonce: 5,
+            gas_limit: 100_000,
+            value: U256::from(1),
+            ..Default::default()
+        };
+
+        // 加载合约账户失败，交易出错并且没有修改状态
+        assert!(matches!(vm.transact(&msg), Err(EVMError::Rpc(_))));
+        // 失败的账户没有被当作空账户加载，重试时重新读取
+        assert!(vm.transact(&msg).unwrap().is_success());
+        let state = vm.into_state();
+        assert_eq!(state.get_nonce(USER), 6);
+        assert_eq!(state.get_balance(COUNTER), U256::from(1));
+        assert_eq!(state.get_state(COUNTER, U256::from(1)), U256::from(43));
+    }
+}
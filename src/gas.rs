@@ -0,0 +1,71 @@
+//! gas 常量和计算，数值以 Berlin 之后的规则为准 (EIP-2929/3529/3860)
+
+/// 复制类指令每个字的费用
+pub const COPY_WORD: u64 = 3;
+/// KECCAK256 和 CREATE2 每个字的哈希费用
+pub const KECCAK256_WORD: u64 = 6;
+/// LOG 数据每个字节的费用
+pub const LOG_DATA: u64 = 8;
+/// EXP 指数每个字节的费用
+pub const EXP_BYTE: u64 = 50;
+
+/// 冷账户访问的总费用，指令表中的静态 gas 已经包含热访问的 100
+pub const COLD_ACCOUNT_ACCESS: u64 = 2600;
+pub const COLD_SLOAD: u64 = 2100;
+pub const WARM_STORAGE_READ: u64 = 100;
+
+pub const SSTORE_SET: u64 = 20000;
+pub const SSTORE_RESET: u64 = 2900;
+pub const SSTORE_CLEARS_REFUND: u64 = 4800;
+/// 剩余 gas 不超过这个值时 SSTORE 失败 (EIP-2200)
+pub const SSTORE_SENTRY: u64 = 2300;
+
+pub const CALL_VALUE: u64 = 9000;
+/// 带 value 的调用额外给子调用的 gas
+pub const CALL_STIPEND: u64 = 2300;
+pub const NEW_ACCOUNT: u64 = 25000;
+
+pub const CODE_DEPOSIT_BYTE: u64 = 200;
+pub const INITCODE_WORD: u64 = 2;
+pub const MAX_CODE_SIZE: usize = 24576;
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+pub const TX: u64 = 21000;
+pub const TX_CREATE: u64 = 53000;
+pub const TX_DATA_ZERO: u64 = 4;
+pub const TX_DATA_NON_ZERO: u64 = 16;
+pub const TX_ACCESS_LIST_ADDRESS: u64 = 2400;
+pub const TX_ACCESS_LIST_STORAGE_KEY: u64 = 1900;
+/// EIP-7702 每个授权的费用
+pub const TX_AUTHORIZATION: u64 = 25000;
+
+/// 退款最多为消耗 gas 的 1/5 (EIP-3529)
+pub const MAX_REFUND_QUOTIENT: u64 = 5;
+
+/// 字节数向上取整到字数
+pub fn words(size: u64) -> u64 {
+    size.div_ceil(32)
+}
+
+/// 使用 `words` 个字内存的总费用
+pub fn memory_gas(words: u64) -> u64 {
+    3 * words + words * words / 512
+}
+
+/// 子调用最多能得到剩余 gas 的 63/64 (EIP-150)
+pub fn all_but_one_64th(gas: u64) -> u64 {
+    gas - gas / 64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_gas() {
+        assert_eq!(words(0), 0);
+        assert_eq!(words(33), 2);
+        assert_eq!(memory_gas(1), 3);
+        assert_eq!(memory_gas(1024), 3 * 1024 + 2048);
+    }
+}
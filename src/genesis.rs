@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use alloy_primitives::{Address, Bytes, U256};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::state::StateDB;
+
+/// geth genesis 文件中 `alloc` 的一项
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisAccount {
+    #[serde(default, with = "quantity")]
+    pub balance: U256,
+    #[serde(default, with = "quantity", skip_serializing_if = "is_zero")]
+    pub nonce: u64,
+    #[serde(default, skip_serializing_if = "is_empty")]
+    pub code: Bytes,
+    /// 值为 0 的槽位不会被导出
+    #[serde(default, with = "storage", skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+pub type GenesisAlloc = BTreeMap<Address, GenesisAccount>;
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+fn is_empty(code: &Bytes) -> bool {
+    code.is_empty()
+}
+
+/// 解析 alloc JSON，也接受带 `alloc` 字段的完整 genesis 文件
+pub fn parse_alloc(json: &str) -> Result<GenesisAlloc> {
+    let mut value: Value = serde_json::from_str(json)?;
+    if let Some(alloc) = value.get_mut("alloc").filter(|alloc| alloc.is_object()) {
+        value = alloc.take();
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// 按 geth 的格式输出，地址和槽位按顺序排列
+pub fn to_json(alloc: &GenesisAlloc) -> Result<String> {
+    Ok(serde_json::to_string_pretty(alloc)?)
+}
+
+/// 把账户写入 state 并提交，已存在的账户会被覆盖
+pub fn load_alloc(state: &mut dyn StateDB, alloc: &GenesisAlloc) {
+    for (&address, account) in alloc {
+        state.create_object(address);
+        state.add_balance(address, account.balance);
+        state.set_nonce(address, account.nonce);
+        if !account.code.is_empty() {
+            state.set_code(address, account.code.clone());
+        }
+        for (&slot, &value) in &account.storage {
+            state.set_state(address, slot, value);
+        }
+    }
+    state.commit();
+}
+
+/// geth 的数值可以是十六进制字符串、十进制字符串或者 JSON 数字，导出时统一为十六进制字符串
+pub(crate) mod quantity {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Quantity {
+        String(String),
+        Number(u64),
+    }
+
+    pub fn parse<T: TryFrom<U256>>(s: &str) -> Result<T> {
+        let value = U256::from_str(s).map_err(|e| anyhow!("invalid quantity {}: {}", s, e))?;
+        T::try_from(value).map_err(|_| anyhow!("quantity out of range: {}", s))
+    }
+
+    pub fn serialize<T: std::fmt::LowerHex, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", value))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<U256>,
+        D: Deserializer<'de>,
+    {
+        match Quantity::deserialize(deserializer)? {
+            Quantity::String(s) => parse(&s).map_err(serde::de::Error::custom),
+            Quantity::Number(n) => T::try_from(U256::from(n))
+                .map_err(|_| serde::de::Error::custom("quantity out of range")),
+        }
+    }
+}
+
+/// 槽位和值都导出为 32 字节的十六进制字符串，读取时也接受省略了前导零的写法
+mod storage {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        storage: &BTreeMap<U256, U256>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            storage
+                .iter()
+                .map(|(slot, value)| (format!("{:#066x}", slot), format!("{:#066x}", value))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<U256, U256>, D::Error> {
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .iter()
+            .map(|(slot, value)| Ok((quantity::parse(slot)?, quantity::parse(value)?)))
+            .collect::<Result<_>>()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::InMemoryStateDB;
+
+    const GENESIS: &str = r#"{
+        "config": { "chainId": 1 },
+        "alloc": {
+            "0x1000000000000000000000000000000000000001": {
+                "balance": "0xde0b6b3a7640000",
+                "nonce": "0x2",
+                "code": "0x600160005500",
+                "storage": {
+                    "0x01": "0x2a",
+                    "0x0000000000000000000000000000000000000000000000000000000000000002": "0x00"
+                }
+            },
+            "2000000000000000000000000000000000000002": { "balance": "100", "nonce": 7 }
+        }
+    }"#;
+
+    #[test]
+    fn test_load_and_dump() {
+        let alloc = parse_alloc(GENESIS).unwrap();
+        let contract: Address = "0x1000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+        let eoa: Address = "0x2000000000000000000000000000000000000002"
+            .parse()
+            .unwrap();
+
+        let mut state = InMemoryStateDB::new();
+        load_alloc(&mut state, &alloc);
+        assert_eq!(
+            state.get_balance(contract),
+            U256::from(1_000_000_000_000_000_000u64)
+        );
+        assert_eq!(state.get_nonce(contract), 2);
+        assert_eq!(state.get_code_size(contract), 6);
+        assert_eq!(state.get_state(contract, U256::from(1)), U256::from(42));
+        assert_eq!(state.get_balance(eoa), U256::from(100));
+        assert_eq!(state.get_nonce(eoa), 7);
+
+        // 导出时去掉值为 0 的槽位，再次加载得到相同的 state
+        let dump = state.dump();
+        assert_eq!(dump[&contract].storage.len(), 1);
+        let json = to_json(&dump).unwrap();
+        assert!(json.contains(&format!(r#""{:#066x}": "{:#066x}""#, 1, 42)));
+        assert!(json.contains(r#""nonce": "0x7""#));
+        assert_eq!(parse_alloc(&json).unwrap(), dump);
+
+        let mut reloaded = InMemoryStateDB::new();
+        load_alloc(&mut reloaded, &dump);
+        assert_eq!(reloaded.dump(), dump);
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::BTreeSet;
+use std::io;
+
+use alloy_primitives::U256;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame as UiFrame,
+};
+
+use crate::{
+    disasm::disassemble, execution::Execution, history::History, opcode_table::OPCODE_TABLE,
+    vm::FrameKind,
+};
+
+const HELP: &str = "s step  p back  n next  o out  c continue  b breakpoint  : command  q quit";
+
+/// 终端调试器，显示代码、栈、内存、存储写入和调用栈
+///
+/// 执行过程记录在执行日志中，界面显示日志当前位置的状态，可以后退到之前的任意一步。
+/// 命令模式 (`:`) 支持 `b <pc>`、`bo <opcode>` 切换断点，`d <depth>` 执行到给定调用深度，
+/// `wm <offset> <size>`、`ws <slot>` 跳到最后一次写入这段内存或者当前合约存储的指令
+pub struct Debugger<'v, 'a> {
+    exec: Execution<'v, 'a>,
+    pc_breakpoints: BTreeSet<usize>,
+    opcode_breakpoints: BTreeSet<u8>,
+    /// 正在输入的命令，None 表示不在命令模式
+    input: Option<String>,
+    message: String,
+    quit: bool,
+}
+
+impl<'v, 'a> Debugger<'v, 'a> {
+    pub fn new(mut exec: Execution<'v, 'a>) -> Self {
+        exec.record_history();
+        Self {
+            exec,
+            pc_breakpoints: BTreeSet::new(),
+            opcode_breakpoints: BTreeSet::new(),
+            input: None,
+            message: String::new(),
+            quit: false,
+        }
+    }
+
+    pub fn execution(&self) -> &Execution<'v, 'a> {
+        &self.exec
+    }
+
+    pub fn history(&self) -> &History {
+        self.exec.history().expect("history is not recorded")
+    }
+
+    fn history_mut(&mut self) -> &mut History {
+        self.exec.history_mut().expect("history is not recorded")
+    }
+
+    pub fn toggle_pc_breakpoint(&mut self, pc: usize) {
+        if !self.pc_breakpoints.remove(&pc) {
+            self.pc_breakpoints.insert(pc);
+        }
+    }
+
+    pub fn toggle_opcode_breakpoint(&mut self, opcode: u8) {
+        if !self.opcode_breakpoints.remove(&opcode) {
+            self.opcode_breakpoints.insert(opcode);
+        }
+    }
+
+    /// 下一条要执行的指令是否命中断点
+    fn at_breakpoint(&self) -> bool {
+        let frame = self.history().frame();
+        self.pc_breakpoints.contains(&frame.pc)
+            || frame
+                .opcode()
+                .is_some_and(|opcode| self.opcode_breakpoints.contains(&opcode))
+    }
+
+    /// 前进一步，不在日志最新位置时只在日志中前进
+    pub fn step(&mut self) -> bool {
+        if self.history_mut().step_forward() {
+            return true;
+        }
+        if self.exec.is_finished() {
+            return false;
+        }
+
+        let running = self.exec.step();
+        if !running {
+            self.message = match self.exec.result() {
+                Some(Ok(_)) => "execution finished".to_string(),
+                Some(Err(e)) => format!("execution failed: {:?}", e),
+                None => unreachable!(),
+            };
+        }
+        running
+    }
+
+    /// 至少执行一条指令，直到 `stop` 返回 true、命中断点或者执行结束
+    fn run_until(&mut self, mut stop: impl FnMut(&Self) -> bool) -> bool {
+        while self.step() {
+            if stop(self) {
+                return true;
+            }
+            if self.at_breakpoint() {
+                self.message = format!("breakpoint at 0x{:04x}", self.history().frame().pc);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 在日志中后退一步
+    pub fn step_back(&mut self) -> bool {
+        self.history_mut().step_back()
+    }
+
+    /// 执行一条指令，跳过其中的子调用
+    pub fn step_over(&mut self) -> bool {
+        let depth = self.history().frames().len();
+        self.run_until(|dbg| dbg.history().frames().len() <= depth)
+    }
+
+    /// 执行到当前帧返回父帧
+    pub fn step_out(&mut self) -> bool {
+        let depth = self.history().frames().len();
+        self.run_until(|dbg| dbg.history().frames().len() < depth)
+    }
+
+    /// 执行到下一个断点
+    pub fn resume(&mut self) -> bool {
+        self.run_until(|_| false)
+    }
+
+    /// 执行到调用深度等于 `depth` 的帧
+    pub fn run_to_depth(&mut self, depth: usize) -> bool {
+        self.run_until(|dbg| dbg.history().frame().depth == depth)
+    }
+
+    /// 执行命令模式下输入的一行命令
+    pub fn command(&mut self, line: &str) -> Result<(), String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts[..] {
+            [] => {}
+            ["b", pc] => self.toggle_pc_breakpoint(parse_number(pc)?),
+            ["bo", name] => {
+                let info = OPCODE_TABLE
+                    .values()
+                    .find(|info| info.name.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("unknown opcode: {}", name))?;
+                self.toggle_opcode_breakpoint(info.opcode);
+            }
+            ["d", depth] => {
+                self.run_to_depth(parse_number(depth)?);
+            }
+            ["wm", offset, size] => {
+                let (offset, size) = (parse_number(offset)?, parse_number(size)?);
+                let step = self
+                    .history()
+                    .last_memory_write(offset, size)
+                    .ok_or("memory was not written")?;
+                self.history_mut().seek(step);
+            }
+            ["ws", slot] => {
+                let slot = U256::from(parse_number(slot)?);
+                let address = self.history().frame().contract;
+                let step = self
+                    .history()
+                    .last_storage_write(address, slot)
+                    .ok_or("storage was not written")?;
+                self.history_mut().seek(step);
+            }
+            ["q"] => self.quit = true,
+            _ => return Err(format!("unknown command: {}", line)),
+        }
+        Ok(())
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if let Some(input) = &mut self.input {
+            match key {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let line = self.input.take().unwrap_or_default();
+                    if let Err(e) = self.command(&line) {
+                        self.message = e;
+                    }
+                }
+                KeyCode::Esc => self.input = None,
+                _ => {}
+            }
+            return;
+        }
+
+        self.message.clear();
+        match key {
+            KeyCode::Char('s') => {
+                self.step();
+            }
+            KeyCode::Char('p') => {
+                self.step_back();
+            }
+            KeyCode::Char('n') => {
+                self.step_over();
+            }
+            KeyCode::Char('o') => {
+                self.step_out();
+            }
+            KeyCode::Char('c') => {
+                self.resume();
+            }
+            KeyCode::Char('b') => {
+                let pc = self.history().frame().pc;
+                self.toggle_pc_breakpoint(pc);
+            }
+            KeyCode::Char(':') => self.input = Some(String::new()),
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => {}
+        }
+    }
+
+    /// 在终端中运行调试器，直到按下 q
+    pub fn run(mut self) -> io::Result<()> {
+        let mut terminal = ratatui::init();
+        let result = self.event_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key.code);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn draw(&self, frame: &mut UiFrame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(frame.area());
+        let [code, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+        let [stack, memory, storage, calls] = Layout::vertical([
+            Constraint::Percentage(30),
+            Constraint::Percentage(35),
+            Constraint::Percentage(15),
+            Constraint::Percentage(20),
+        ])
+        .areas(right);
+
+        self.draw_code(frame, code);
+        self.draw_stack(frame, stack);
+        self.draw_memory(frame, memory);
+        self.draw_storage(frame, storage);
+        self.draw_call_stack(frame, calls);
+        self.draw_status(frame, status);
+    }
+
+    fn draw_code(&self, frame: &mut UiFrame, area: Rect) {
+        let view = self.history().frame();
+        let instructions = disassemble(&view.code);
+        let items: Vec<ListItem> = instructions
+            .iter()
+            .map(|inst| {
+                let opcode_break = self.opcode_breakpoints.contains(&inst.opcode);
+                let marker = if self.pc_breakpoints.contains(&inst.pc) || opcode_break {
+                    '*'
+                } else {
+                    ' '
+                };
+                ListItem::new(format!("{} {:04x}  {}", marker, inst.pc, inst))
+            })
+            .collect();
+
+        let mut state = ListState::default()
+            .with_selected(instructions.iter().position(|inst| inst.pc == view.pc));
+        let list = List::new(items)
+            .block(
+                Block::bordered().title(format!("Code {} (depth {})", view.contract, view.depth)),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            );
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_stack(&self, frame: &mut UiFrame, area: Rect) {
+        let stack = &self.history().frame().stack;
+        let items: Vec<ListItem> = stack
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, value)| ListItem::new(format!("{:>4}  0x{:064x}", i, value)))
+            .collect();
+        let list =
+            List::new(items).block(Block::bordered().title(format!("Stack ({})", stack.len())));
+        frame.render_widget(list, area);
+    }
+
+    fn draw_memory(&self, frame: &mut UiFrame, area: Rect) {
+        let memory = &self.history().frame().memory;
+        // 按每行 16 字节显示内存内容和 ASCII 列
+        let lines: Vec<Line> = memory
+            .chunks(16)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let bytes: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+                let ascii: String = chunk
+                    .iter()
+                    .map(|&b| {
+                        if b.is_ascii_graphic() || b == b' ' {
+                            b as char
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                Line::from(format!(
+                    "{:06x}  {:<47}  {}",
+                    i * 16,
+                    bytes.join(" "),
+                    ascii
+                ))
+            })
+            .collect();
+        let paragraph = Paragraph::new(lines)
+            .block(Block::bordered().title(format!("Memory ({} bytes)", memory.len())));
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_storage(&self, frame: &mut UiFrame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .history()
+            .storage_writes()
+            .map(|(step, write)| {
+                ListItem::new(format!(
+                    "#{} {} [0x{:x}] 0x{:x} -> 0x{:x}",
+                    step, write.address, write.slot, write.old, write.new
+                ))
+            })
+            .collect();
+        let list = List::new(items).block(Block::bordered().title("Storage writes"));
+        frame.render_widget(list, area);
+    }
+
+    fn draw_call_stack(&self, frame: &mut UiFrame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .history()
+            .frames()
+            .iter()
+            .rev()
+            .map(|f| {
+                let kind = match f.kind {
+                    FrameKind::Root => "TX",
+                    FrameKind::Call { .. } => "CALL",
+                    FrameKind::Create { .. } => "CREATE",
+                };
+                ListItem::new(format!(
+                    "{:>3} {:<6} {} pc=0x{:04x}",
+                    f.depth, kind, f.contract, f.pc
+                ))
+            })
+            .collect();
+        let list = List::new(items).block(Block::bordered().title("Call stack"));
+        frame.render_widget(list, area);
+    }
+
+    fn draw_status(&self, frame: &mut UiFrame, area: Rect) {
+        let history = self.history();
+        let position = format!("step {}/{}", history.cursor(), history.len());
+        let text = match &self.input {
+            Some(input) => format!(":{}", input),
+            None if self.message.is_empty() => format!("{}  |  {}", position, HELP),
+            None => format!("{}  |  {}  |  {}", position, self.message, HELP),
+        };
+        frame.render_widget(Paragraph::new(text).block(Block::bordered()), area);
+    }
+}
+
+/// 解析十进制或者 0x 开头的十六进制数
+fn parse_number(s: &str) -> Result<usize, String> {
+    let result = match s.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|_| format!("invalid number: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BlockContext;
+    use crate::test_utils::deploy;
+    use crate::vm::Interpreter;
+    use alloy_primitives::Bytes;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    // 写入存储后部署一个把 0x2a 写入内存并返回的合约，然后调用它
+    const CODE: &str = r#"
+        PUSH1 0x2a
+        PUSH1 1
+        SSTORE
+        PUSH19 0x69602a60005260206000f3600052600a6016f3
+        PUSH1 0
+        MSTORE
+        PUSH1 0x13
+        PUSH1 0x0d
+        PUSH1 0
+        CREATE
+        PUSH1 0x20
+        PUSH1 0x40
+        PUSH1 0
+        PUSH1 0
+        PUSH1 0
+        DUP6
+        GAS
+        CALL
+        STOP
+    "#;
+
+    fn screen(dbg: &Debugger) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(160, 48)).unwrap();
+        terminal.draw(|frame| dbg.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_debugger() {
+        let (state, caller, contract) = deploy(CODE);
+
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let exec = vm.start_call(caller, caller, contract, Bytes::new(), U256::ZERO);
+        let mut dbg = Debugger::new(exec);
+
+        dbg.command("bo call").unwrap();
+        dbg.handle_key(KeyCode::Char('c'));
+        assert_eq!(dbg.execution().context().pc, 0x2f);
+        assert_eq!(dbg.history().storage_writes().count(), 1);
+
+        let text = screen(&dbg);
+        assert!(text.contains("* 002f  CALL"));
+        assert!(text.contains("[0x1] 0x0 -> 0x2a"));
+        assert!(text.contains("000000  00 00"));
+
+        // 进入子调用，调用栈显示两层
+        dbg.command("d 1").unwrap();
+        assert_eq!(dbg.execution().frames().len(), 2);
+        assert!(screen(&dbg).contains("  1 CALL"));
+
+        dbg.handle_key(KeyCode::Char('o'));
+        assert_eq!(dbg.execution().context().pc, 0x30);
+        assert!(dbg.command("x").is_err());
+
+        dbg.handle_key(KeyCode::Char('c'));
+        assert!(dbg.execution().is_finished());
+        assert!(screen(&dbg).contains("execution finished"));
+
+        // 后退到写入调用返回数据的 RETURN，再回到最后
+        dbg.command("wm 0x40 32").unwrap();
+        assert_eq!(dbg.history().frames().len(), 2);
+        // 被调用的合约没有写入过槽位 1，回到最后在最外层帧中查找
+        assert!(dbg.command("ws 1").is_err());
+        dbg.handle_key(KeyCode::Char('c'));
+        assert!(dbg.history().is_live());
+        dbg.command("ws 1").unwrap();
+        assert_eq!(dbg.history().frame().pc, 4);
+        dbg.handle_key(KeyCode::Char('p'));
+        assert_eq!(dbg.history().frame().pc, 2);
+        assert!(screen(&dbg).contains("step 1/"));
+        // 在日志中前进也会停在断点
+        dbg.handle_key(KeyCode::Char('c'));
+        assert_eq!(dbg.history().frame().pc, 0x2f);
+        dbg.handle_key(KeyCode::Char('c'));
+        assert!(dbg.history().is_live());
+        assert!(!dbg.step());
+    }
+}
@@ -0,0 +1,580 @@
+use alloy_primitives::{Address, Bytes, U256};
+
+use crate::{
+    breakpoint::read_range,
+    context::Context,
+    opcode::{
+        CALL, CALLCODE, CALLDATACOPY, CODECOPY, DELEGATECALL, EXTCODECOPY, MCOPY, MSTORE, MSTORE8,
+        RETURNDATACOPY, STATICCALL,
+    },
+    opcode_table::OPCODE_TABLE,
+    vm::{Frame, FrameKind},
+};
+
+/// 某一时刻一个调用帧的内容
+#[derive(Clone)]
+pub struct FrameView {
+    pub kind: FrameKind,
+    pub contract: Address,
+    pub depth: usize,
+    pub pc: usize,
+    pub code: Bytes,
+    /// 从栈底到栈顶
+    pub stack: Vec<U256>,
+    pub memory: Vec<u8>,
+}
+
+impl FrameView {
+    fn new(ctx: &Context, kind: &FrameKind) -> Self {
+        Self {
+            kind: kind.clone(),
+            contract: ctx.contract,
+            depth: ctx.depth,
+            pc: ctx.pc,
+            code: ctx.code.clone(),
+            stack: ctx.stack.as_slice().to_vec(),
+            memory: ctx.memory.data().to_vec(),
+        }
+    }
+
+    /// 下一条要执行的指令
+    pub fn opcode(&self) -> Option<u8> {
+        self.code.get(self.pc).copied()
+    }
+}
+
+/// 一次 SSTORE 写入
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageWrite {
+    pub address: Address,
+    pub slot: U256,
+    pub old: U256,
+    pub new: U256,
+}
+
+/// 一步执行对调用帧的修改，除了 `StorageWrite` 都作用于当前最内层的帧
+#[derive(Clone)]
+pub enum Change {
+    Pc {
+        old: usize,
+        new: usize,
+    },
+    StackPop(U256),
+    StackPush(U256),
+    /// 内存扩展，新增部分为 0
+    MemoryResize {
+        old: usize,
+        new: usize,
+    },
+    MemoryWrite {
+        offset: usize,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+    StorageWrite(StorageWrite),
+    /// 进入子调用帧，新帧的栈和内存为空，之后的修改作用于子帧
+    EnterFrame {
+        kind: FrameKind,
+        contract: Address,
+        depth: usize,
+        code: Bytes,
+    },
+    /// 子帧返回，之后的修改作用于父帧
+    ExitFrame,
+}
+
+/// 执行日志中的一步
+pub struct Step {
+    /// 执行这一步的帧和指令，代码执行完后的返回没有指令
+    pub pc: usize,
+    pub depth: usize,
+    pub opcode: Option<u8>,
+    /// 这一步之后的帧数
+    pub frames_after: usize,
+    pub changes: Vec<Change>,
+}
+
+impl Step {
+    /// Pc/栈/内存的修改作用于哪一层帧
+    fn target_depth(&self) -> usize {
+        (self.depth + 1).min(self.frames_after) - 1
+    }
+}
+
+/// 按步记录的差量执行日志，可以在已执行的历史中前后移动
+///
+/// `frames` 是 `cursor` 位置的调用栈，`cursor` 等于日志长度时和解释器的实际状态一致。
+/// 每一步只记录指令实际改动的栈顶元素和内存范围，不比较整个帧
+pub struct History {
+    steps: Vec<Step>,
+    cursor: usize,
+    frames: Vec<FrameView>,
+    /// `cursor` 之前返回的子帧，保存返回前的内容以便回退，后返回的在后面
+    exited: Vec<FrameView>,
+}
+
+impl History {
+    pub(crate) fn new(frames: &[Frame]) -> Self {
+        Self {
+            steps: Vec::new(),
+            cursor: 0,
+            frames: frames
+                .iter()
+                .map(|frame| FrameView::new(&frame.ctx, &frame.kind))
+                .collect(),
+            exited: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// 当前位置，表示已经执行了多少步
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// 当前位置是否是最新状态
+    pub fn is_live(&self) -> bool {
+        self.cursor == self.steps.len()
+    }
+
+    /// 当前位置的调用栈，第一个是最外层帧
+    pub fn frames(&self) -> &[FrameView] {
+        &self.frames
+    }
+
+    /// 当前位置的最内层帧
+    pub fn frame(&self) -> &FrameView {
+        self.frames.last().expect("frame stack is empty")
+    }
+
+    /// 当前位置之前的所有存储写入和写入它的步数
+    pub fn storage_writes(&self) -> impl Iterator<Item = (usize, &StorageWrite)> {
+        self.steps[..self.cursor]
+            .iter()
+            .enumerate()
+            .flat_map(|(i, step)| {
+                step.changes.iter().filter_map(move |change| match change {
+                    Change::StorageWrite(write) => Some((i, write)),
+                    _ => None,
+                })
+            })
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.cursor -= 1;
+        for change in self.steps[self.cursor].changes.iter().rev() {
+            undo(&mut self.frames, &mut self.exited, change);
+        }
+        true
+    }
+
+    pub fn step_forward(&mut self) -> bool {
+        if self.is_live() {
+            return false;
+        }
+        for change in &self.steps[self.cursor].changes {
+            apply(&mut self.frames, &mut self.exited, change);
+        }
+        self.cursor += 1;
+        true
+    }
+
+    /// 移动到执行完 `cursor` 步的位置
+    pub fn seek(&mut self, cursor: usize) {
+        let cursor = cursor.min(self.steps.len());
+        while self.cursor > cursor {
+            self.step_back();
+        }
+        while self.cursor < cursor {
+            self.step_forward();
+        }
+    }
+
+    /// 当前帧中最后一次写入 `[offset, offset + size)` 内存的步数
+    pub fn last_memory_write(&self, offset: usize, size: usize) -> Option<usize> {
+        let depth = self.frame().depth;
+        for (i, step) in self.steps[..self.cursor].iter().enumerate().rev() {
+            if step.target_depth() == depth {
+                let written = step.changes.iter().any(|change| match change {
+                    Change::MemoryWrite { offset: o, new, .. } => {
+                        *o < offset + size && offset < *o + new.len()
+                    }
+                    _ => false,
+                });
+                if written {
+                    return Some(i);
+                }
+            }
+            // 当前帧在这一步被创建，更早的写入属于其他帧
+            if step.depth < depth {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// 最后一次写入 `address` 的 `slot` 的步数
+    pub fn last_storage_write(&self, address: Address, slot: U256) -> Option<usize> {
+        self.storage_writes()
+            .filter(|(_, write)| write.address == address && write.slot == slot)
+            .last()
+            .map(|(i, _)| i)
+    }
+
+    /// 根据执行的指令记录一步，只能在最新位置调用
+    ///
+    /// 帧内的修改由指令弹出的元素个数和写入的内存范围决定；
+    /// 子帧返回时父帧压入结果，并把返回数据写入调用时指定的内存范围
+    pub(crate) fn record(
+        &mut self,
+        storage: Option<StorageWrite>,
+        frames: &[Frame],
+        finished: Option<&Context>,
+    ) {
+        debug_assert!(self.is_live());
+        let current = self.frame();
+        let (pc, depth, opcode) = (current.pc, current.depth, current.opcode());
+
+        // 最外层帧结束后仍然保留它的最终状态
+        let mut after: Vec<(&Context, &FrameKind)> = frames
+            .iter()
+            .map(|frame| (&frame.ctx, &frame.kind))
+            .collect();
+        if let Some(root) = finished {
+            after.push((root, &FrameKind::Root));
+        }
+
+        let mut changes = Vec::new();
+        let kept = after.len().min(self.frames.len());
+        changes.extend((kept..self.frames.len()).map(|_| Change::ExitFrame));
+        let target = kept - 1;
+        let (target_ctx, _) = after[target];
+        let (pops, write) = match self.frames.get(kept) {
+            Some(child) => (0, return_write(&child.kind, target_ctx)),
+            None => (
+                opcode
+                    .and_then(|opcode| OPCODE_TABLE.get(&opcode))
+                    .map_or(0, |info| info.inputs),
+                opcode.and_then(|opcode| memory_write(opcode, &self.frames[target].stack)),
+            ),
+        };
+        diff_frame(&self.frames[target], target_ctx, pops, write, &mut changes);
+        if let Some(&(ctx, kind)) = after.get(self.frames.len()) {
+            changes.push(Change::EnterFrame {
+                kind: kind.clone(),
+                contract: ctx.contract,
+                depth: ctx.depth,
+                code: ctx.code.clone(),
+            });
+        }
+        if let Some(write) = storage {
+            changes.push(Change::StorageWrite(write));
+        }
+
+        for change in &changes {
+            apply(&mut self.frames, &mut self.exited, change);
+        }
+        self.steps.push(Step {
+            pc,
+            depth,
+            opcode,
+            frames_after: after.len(),
+            changes,
+        });
+        self.cursor += 1;
+    }
+}
+
+/// 指令写入的内存范围 `(offset, size)`，由执行前栈上的参数决定
+///
+/// CALL 类指令的返回范围只有调用预编译合约时在这一步写入，否则在子帧返回时写入
+fn memory_write(opcode: u8, stack: &[U256]) -> Option<(usize, usize)> {
+    // 第 i 个参数，0 是栈顶
+    let arg = |i: usize| stack.len().checked_sub(i + 1).map(|i| stack[i]);
+    let (offset, size) = match opcode {
+        MSTORE => (arg(0)?, U256::from(32)),
+        MSTORE8 => (arg(0)?, U256::from(1)),
+        CALLDATACOPY | CODECOPY | RETURNDATACOPY | MCOPY => (arg(0)?, arg(2)?),
+        EXTCODECOPY => (arg(1)?, arg(3)?),
+        CALL | CALLCODE => (arg(5)?, arg(6)?),
+        DELEGATECALL | STATICCALL => (arg(4)?, arg(5)?),
+        _ => return None,
+    };
+    // 超出 usize 的范围无法扩展内存，指令会失败
+    Some((offset.try_into().ok()?, size.try_into().ok()?))
+}
+
+/// 子帧返回时写入父帧内存的范围，和 `Interpreter::return_to_parent` 一致
+fn return_write(child: &FrameKind, parent: &Context) -> Option<(usize, usize)> {
+    match *child {
+        FrameKind::Call {
+            ret_offset,
+            ret_size,
+            ..
+        } => Some((ret_offset, ret_size.min(parent.return_data.len()))),
+        _ => None,
+    }
+}
+
+/// 计算帧从 `old` 到 `new` 的修改，`pops` 是指令弹出的元素个数，`write` 是它写入的内存范围
+fn diff_frame(
+    old: &FrameView,
+    new: &Context,
+    pops: usize,
+    write: Option<(usize, usize)>,
+    changes: &mut Vec<Change>,
+) {
+    if old.pc != new.pc {
+        changes.push(Change::Pc {
+            old: old.pc,
+            new: new.pc,
+        });
+    }
+
+    // 指令只改动栈顶的 `pops` 个元素，执行失败时可能只弹出了一部分
+    let stack = new.stack.as_slice();
+    let base = (old.stack.len() - pops.min(old.stack.len())).min(stack.len());
+    changes.extend(old.stack[base..].iter().rev().map(|v| Change::StackPop(*v)));
+    changes.extend(stack[base..].iter().map(|v| Change::StackPush(*v)));
+
+    let memory = new.memory.data();
+    if old.memory.len() != memory.len() {
+        changes.push(Change::MemoryResize {
+            old: old.memory.len(),
+            new: memory.len(),
+        });
+    }
+    // 执行失败时内存可能没有扩展，只比较实际存在的部分，扩展出来的部分按 0 比较
+    let Some((offset, size)) = write else {
+        return;
+    };
+    let end = offset.saturating_add(size).min(memory.len());
+    if offset < end {
+        let old_bytes = read_range(&old.memory, offset, end - offset);
+        if old_bytes[..] != memory[offset..end] {
+            changes.push(Change::MemoryWrite {
+                offset,
+                old: old_bytes,
+                new: memory[offset..end].to_vec(),
+            });
+        }
+    }
+}
+
+fn apply(frames: &mut Vec<FrameView>, exited: &mut Vec<FrameView>, change: &Change) {
+    match change {
+        Change::EnterFrame {
+            kind,
+            contract,
+            depth,
+            code,
+        } => frames.push(FrameView {
+            kind: kind.clone(),
+            contract: *contract,
+            depth: *depth,
+            pc: 0,
+            code: code.clone(),
+            stack: Vec::new(),
+            memory: Vec::new(),
+        }),
+        Change::ExitFrame => exited.push(frames.pop().expect("frame stack is empty")),
+        Change::StorageWrite(_) => {}
+        _ => {
+            let frame = frames.last_mut().expect("frame stack is empty");
+            match change {
+                Change::Pc { new, .. } => frame.pc = *new,
+                Change::StackPop(_) => {
+                    frame.stack.pop();
+                }
+                Change::StackPush(value) => frame.stack.push(*value),
+                Change::MemoryResize { new, .. } => frame.memory.resize(*new, 0),
+                Change::MemoryWrite { offset, new, .. } => {
+                    frame.memory[*offset..*offset + new.len()].copy_from_slice(new)
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+fn undo(frames: &mut Vec<FrameView>, exited: &mut Vec<FrameView>, change: &Change) {
+    match change {
+        Change::EnterFrame { .. } => {
+            frames.pop();
+        }
+        Change::ExitFrame => frames.push(exited.pop().expect("no exited frame")),
+        Change::StorageWrite(_) => {}
+        _ => {
+            let frame = frames.last_mut().expect("frame stack is empty");
+            match change {
+                Change::Pc { old, .. } => frame.pc = *old,
+                Change::StackPop(value) => frame.stack.push(*value),
+                Change::StackPush(_) => {
+                    frame.stack.pop();
+                }
+                Change::MemoryResize { old, .. } => frame.memory.truncate(*old),
+                Change::MemoryWrite { offset, old, .. } => {
+                    frame.memory[*offset..*offset + old.len()].copy_from_slice(old)
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::BlockContext;
+    use crate::test_utils::deploy;
+    use crate::vm::Interpreter;
+    use alloy_primitives::{Bytes, U256};
+
+    // 写入存储后部署一个把 0x2a 写入内存并返回的合约，然后调用它
+    const CODE: &str = r#"
+        PUSH1 0x2a
+        PUSH1 1
+        SSTORE
+        PUSH19 0x69602a60005260206000f3600052600a6016f3
+        PUSH1 0
+        MSTORE
+        PUSH1 0x13
+        PUSH1 0x0d
+        PUSH1 0
+        CREATE
+        PUSH1 0x20
+        PUSH1 0x40
+        PUSH1 0
+        PUSH1 0
+        PUSH1 0
+        DUP6
+        GAS
+        CALL
+        STOP
+    "#;
+
+    // 写内存、调用 identity 预编译、MCOPY，最后 ADD 栈下溢
+    const MEMORY_CODE: &str = r#"
+        PUSH1 0x2a
+        PUSH1 0
+        MSTORE8
+        PUSH1 0x20
+        PUSH1 0x40
+        PUSH1 0x01
+        PUSH1 0
+        PUSH1 4
+        GAS
+        STATICCALL
+        PUSH1 1
+        PUSH1 0x40
+        PUSH1 0x60
+        MCOPY
+        ADD
+    "#;
+
+    type View = (usize, usize, Vec<U256>, Vec<u8>);
+
+    fn view(depth: usize, pc: usize, stack: &[U256], memory: &[u8]) -> View {
+        (depth, pc, stack.to_vec(), memory.to_vec())
+    }
+
+    /// 每一步之后执行日志中的帧和解释器一致，回退时每个位置都能还原
+    fn check_history(code: &str) {
+        let (state, caller, contract) = deploy(code);
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let mut exec = vm.start_call(caller, caller, contract, Bytes::new(), U256::ZERO);
+        exec.record_history();
+
+        let mut views = Vec::new();
+        loop {
+            let ctx = exec.context();
+            let expected = view(ctx.depth, ctx.pc, ctx.stack.as_slice(), &ctx.memory.data());
+            let history = exec.history().unwrap();
+            let frame = history.frame();
+            assert_eq!(
+                view(frame.depth, frame.pc, &frame.stack, &frame.memory),
+                expected
+            );
+            views.push((history.len(), history.frames().len(), expected));
+            if exec.is_finished() {
+                break;
+            }
+            exec.step();
+        }
+
+        let history = exec.history_mut().unwrap();
+        for (cursor, frames, expected) in views.into_iter().rev() {
+            history.seek(cursor);
+            assert_eq!(history.frames().len(), frames);
+            let frame = history.frame();
+            assert_eq!(
+                view(frame.depth, frame.pc, &frame.stack, &frame.memory),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_history_matches_execution() {
+        check_history(CODE);
+        check_history(MEMORY_CODE);
+    }
+
+    #[test]
+    fn test_history() {
+        let (state, caller, contract) = deploy(CODE);
+
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let mut exec = vm.start_call(caller, caller, contract, Bytes::new(), U256::ZERO);
+        exec.record_history();
+        while exec.step() {}
+
+        let final_stack = exec.context().stack.as_slice().to_vec();
+        let final_memory = exec.context().memory.data().to_vec();
+        let history = exec.history_mut().unwrap();
+        assert_eq!(history.frame().stack, final_stack);
+        assert_eq!(history.frame().memory, final_memory);
+
+        // 调用返回的数据写在 0x40，由子帧的 RETURN 写入
+        let step = history.last_memory_write(0x40, 32).unwrap();
+        history.seek(step);
+        assert_eq!(history.frames().len(), 2);
+        assert_eq!(history.frame().opcode(), Some(0xf3));
+        assert_eq!(history.frame().memory[31], 0x2a);
+
+        // 子帧中的写入不属于父帧
+        history.seek(history.len());
+        let step = history.last_memory_write(0, 32).unwrap();
+        assert_eq!(history.steps()[step].depth, 0);
+        assert_eq!(history.steps()[step].pc, 0x1b);
+
+        let step = history.last_storage_write(contract, U256::from(1)).unwrap();
+        assert_eq!(history.steps()[step].pc, 4);
+
+        // 回到开头，状态和执行前一致，再前进到最后和执行后一致
+        history.seek(0);
+        assert_eq!(history.frames().len(), 1);
+        assert!(history.frame().stack.is_empty());
+        assert!(history.frame().memory.is_empty());
+        assert_eq!(history.storage_writes().count(), 0);
+        while history.step_forward() {}
+        assert_eq!(history.frame().stack, final_stack);
+        assert_eq!(history.frame().memory, final_memory);
+        assert_eq!(history.storage_writes().count(), 1);
+    }
+}
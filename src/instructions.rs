@@ -3,12 +3,13 @@ use std::cmp::{min, Ordering};
 use crate::{
     context::{BlockContext, Context},
     error::EVMError,
+    gas,
     i256::{i256_cmp, i256_div, i256_mod},
-    opcode::JUMPDEST,
-    state::StateDB,
+    opcode::{INVALID, JUMPDEST},
+    state::{StateDB, KECCAK_EMPTY},
     u256,
 };
-use alloy_primitives::U256;
+use alloy_primitives::{Address, U256};
 use anyhow::Result;
 
 pub fn nop(
@@ -24,7 +25,7 @@ pub fn stop(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    return Err(EVMError::Stop);
+    Err(EVMError::Stop)
 }
 
 pub fn add(
@@ -32,8 +33,8 @@ pub fn add(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a.wrapping_add(b));
+    let (a, b) = ctx.stack.pop_top();
+    *b = a.wrapping_add(*b);
     Ok(())
 }
 
@@ -42,8 +43,8 @@ pub fn mul(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a.wrapping_mul(b));
+    let (a, b) = ctx.stack.pop_top();
+    *b = a.wrapping_mul(*b);
     Ok(())
 }
 
@@ -52,8 +53,8 @@ pub fn sub(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a.wrapping_sub(b));
+    let (a, b) = ctx.stack.pop_top();
+    *b = a.wrapping_sub(*b);
     Ok(())
 }
 
@@ -62,8 +63,8 @@ pub fn div(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a.wrapping_div(b));
+    let (a, b) = ctx.stack.pop_top();
+    *b = a.checked_div(*b).unwrap_or_default();
     Ok(())
 }
 
@@ -72,8 +73,8 @@ pub fn sign_div(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(i256_div(a, b));
+    let (a, b) = ctx.stack.pop_top();
+    *b = i256_div(a, *b);
     Ok(())
 }
 
@@ -82,8 +83,8 @@ pub fn modulo(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a.wrapping_rem(b));
+    let (a, b) = ctx.stack.pop_top();
+    *b = a.checked_rem(*b).unwrap_or_default();
     Ok(())
 }
 
@@ -92,8 +93,8 @@ pub fn sign_modulo(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(i256_mod(a, b));
+    let (a, b) = ctx.stack.pop_top();
+    *b = i256_mod(a, *b);
     Ok(())
 }
 
@@ -103,7 +104,7 @@ pub fn add_mod(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [a, b, c] = ctx.stack.pop_n::<3>();
-    ctx.stack.push(a.wrapping_add(b).wrapping_rem(c));
+    ctx.stack.push(a.add_mod(b, c));
     Ok(())
 }
 
@@ -113,7 +114,7 @@ pub fn mul_mod(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [a, b, c] = ctx.stack.pop_n::<3>();
-    ctx.stack.push(a.wrapping_mul(b).wrapping_rem(c));
+    ctx.stack.push(a.mul_mod(b, c));
     Ok(())
 }
 
@@ -122,8 +123,10 @@ pub fn exp(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a.pow(b));
+    let exponent = ctx.stack.as_slice()[ctx.stack.len() - 2];
+    ctx.use_gas(gas::EXP_BYTE * exponent.byte_len() as u64)?;
+    let (a, b) = ctx.stack.pop_top();
+    *b = a.pow(*b);
     Ok(())
 }
 
@@ -152,12 +155,8 @@ pub fn lt(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    if a < b {
-        ctx.stack.push(U256::from(1));
-    } else {
-        ctx.stack.push(U256::from(0));
-    }
+    let (a, b) = ctx.stack.pop_top();
+    *b = U256::from(a < *b);
     Ok(())
 }
 
@@ -166,12 +165,8 @@ pub fn gt(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    if a > b {
-        ctx.stack.push(U256::from(1));
-    } else {
-        ctx.stack.push(U256::from(0));
-    }
+    let (a, b) = ctx.stack.pop_top();
+    *b = U256::from(a > *b);
     Ok(())
 }
 
@@ -180,12 +175,8 @@ pub fn slt(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    match i256_cmp(&a, &b) {
-        Ordering::Less => ctx.stack.push(U256::from(1)),
-        Ordering::Greater => ctx.stack.push(U256::from(0)),
-        Ordering::Equal => ctx.stack.push(U256::from(0)),
-    }
+    let (a, b) = ctx.stack.pop_top();
+    *b = U256::from(i256_cmp(&a, b) == Ordering::Less);
     Ok(())
 }
 
@@ -194,12 +185,8 @@ pub fn sgt(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    match i256_cmp(&a, &b) {
-        Ordering::Less => ctx.stack.push(U256::from(0)),
-        Ordering::Greater => ctx.stack.push(U256::from(1)),
-        Ordering::Equal => ctx.stack.push(U256::from(0)),
-    }
+    let (a, b) = ctx.stack.pop_top();
+    *b = U256::from(i256_cmp(&a, b) == Ordering::Greater);
     Ok(())
 }
 
@@ -208,12 +195,8 @@ pub fn eq(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    if a == b {
-        ctx.stack.push(U256::from(1));
-    } else {
-        ctx.stack.push(U256::from(0));
-    }
+    let (a, b) = ctx.stack.pop_top();
+    *b = U256::from(a == *b);
     Ok(())
 }
 
@@ -222,12 +205,8 @@ pub fn is_zero(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let a = ctx.stack.pop();
-    if a == U256::from(0) {
-        ctx.stack.push(U256::from(1));
-    } else {
-        ctx.stack.push(U256::from(0));
-    }
+    let a = ctx.stack.top_mut();
+    *a = U256::from(a.is_zero());
     Ok(())
 }
 
@@ -236,8 +215,8 @@ pub fn and(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a & b);
+    let (a, b) = ctx.stack.pop_top();
+    *b &= a;
     Ok(())
 }
 
@@ -246,8 +225,8 @@ pub fn or(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a | b);
+    let (a, b) = ctx.stack.pop_top();
+    *b |= a;
     Ok(())
 }
 
@@ -256,8 +235,8 @@ pub fn xor(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a ^ b);
+    let (a, b) = ctx.stack.pop_top();
+    *b ^= a;
     Ok(())
 }
 
@@ -266,8 +245,8 @@ pub fn not(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let a = ctx.stack.pop();
-    ctx.stack.push(!a);
+    let a = ctx.stack.top_mut();
+    *a = !*a;
     Ok(())
 }
 
@@ -280,8 +259,8 @@ pub fn byte(
     if i >= U256::from(32) {
         ctx.stack.push(U256::from(0));
     } else {
-        let limb = x.as_limbs()[i.as_limbs()[0] as usize / 8];
-        let byte = (limb >> (i.as_limbs()[0] % 8 * 8)) & 0xff;
+        // 下标 0 是最高位字节
+        let byte = x.byte(31 - i.as_limbs()[0] as usize);
         ctx.stack.push(U256::from(byte));
     }
     Ok(())
@@ -292,8 +271,11 @@ pub fn shl(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a << b);
+    let (shift, value) = ctx.stack.pop_top();
+    *value = match shift < U256::from(256) {
+        true => *value << shift.as_limbs()[0] as usize,
+        false => U256::ZERO,
+    };
     Ok(())
 }
 
@@ -302,8 +284,11 @@ pub fn shr(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let [a, b] = ctx.stack.pop_n::<2>();
-    ctx.stack.push(a >> b);
+    let (shift, value) = ctx.stack.pop_top();
+    *value = match shift < U256::from(256) {
+        true => *value >> shift.as_limbs()[0] as usize,
+        false => U256::ZERO,
+    };
     Ok(())
 }
 
@@ -331,13 +316,9 @@ pub fn keccak256(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [offset, size] = ctx.stack.pop_n::<2>();
-    if size == U256::ZERO {
-        ctx.stack.push(U256::ZERO);
-        return Ok(());
-    }
-    let data = ctx
-        .memory
-        .read(u256::u256_to_usize(offset), u256::u256_to_usize(size));
+    let (offset, size) = ctx.expand_memory(offset, size)?;
+    ctx.use_gas(gas::KECCAK256_WORD * gas::words(size as u64))?;
+    let data = ctx.memory.read(offset, size);
     let hash = alloy_primitives::keccak256(data);
     ctx.stack.push(hash.into());
     Ok(())
@@ -357,9 +338,9 @@ pub fn balance(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let address = ctx.stack.pop();
-    ctx.stack
-        .push(state.get_balance(u256::u256_to_address(address)));
+    let address = u256::u256_to_address(ctx.stack.pop());
+    access_account(ctx, state, address)?;
+    ctx.stack.push(state.get_balance(address));
     Ok(())
 }
 
@@ -395,18 +376,14 @@ pub fn call_data_load(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    if ctx.call_data.is_empty() {
-        ctx.stack.push(U256::ZERO);
-        return Ok(());
-    }
-    let offset = ctx.stack.pop();
+    let offset = ctx.stack.top_mut();
+    let start_offset = u256::u256_to_usize(*offset);
     let mut loaded = [0u8; 32];
-    let start_offset = min(u256::u256_to_usize(offset), ctx.call_data.len() - 1);
-    let copy_size = min(32usize, ctx.call_data.len() - start_offset);
-    for i in 0..copy_size {
-        loaded[i] = ctx.call_data[start_offset + i];
+    if start_offset < ctx.call_data.len() {
+        let copy_size = min(32usize, ctx.call_data.len() - start_offset);
+        loaded[..copy_size].copy_from_slice(&ctx.call_data[start_offset..start_offset + copy_size]);
     }
-    ctx.stack.push(U256::from_be_slice(&loaded));
+    *offset = U256::from_be_bytes(loaded);
     Ok(())
 }
 
@@ -425,25 +402,8 @@ pub fn call_data_copy(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [dst_offset, offset, size] = ctx.stack.pop_n::<3>();
-
-    let copy_size = min(
-        u256::u256_to_usize(size),
-        ctx.call_data.len() - u256::u256_to_usize(offset),
-    );
-    let start_offset = u256::u256_to_usize(offset);
-    if start_offset >= ctx.call_data.len() {
-        ctx.memory.fill(
-            u256::u256_to_usize(dst_offset),
-            0,
-            u256::u256_to_usize(size),
-        );
-    } else {
-        ctx.memory.write(
-            u256::u256_to_usize(dst_offset),
-            &ctx.call_data[start_offset..start_offset + copy_size],
-        );
-    }
-    Ok(())
+    let call_data = ctx.call_data.clone();
+    copy_to_memory(ctx, dst_offset, &call_data, offset, size)
 }
 
 pub fn code_size(
@@ -461,24 +421,8 @@ pub fn code_copy(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [dst_offset, offset, size] = ctx.stack.pop_n::<3>();
-    let copy_size = min(
-        u256::u256_to_usize(size),
-        ctx.code.len() - u256::u256_to_usize(offset),
-    );
-    let start_offset = u256::u256_to_usize(offset);
-    if start_offset >= ctx.code.len() {
-        ctx.memory.fill(
-            u256::u256_to_usize(dst_offset),
-            0,
-            u256::u256_to_usize(size),
-        );
-    } else {
-        ctx.memory.write(
-            u256::u256_to_usize(dst_offset),
-            &ctx.code[start_offset..start_offset + copy_size],
-        );
-    }
-    Ok(())
+    let code = ctx.code.clone();
+    copy_to_memory(ctx, dst_offset, &code, offset, size)
 }
 
 pub fn gas_price(
@@ -495,10 +439,9 @@ pub fn ext_code_size(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let address = ctx.stack.pop();
-    ctx.stack.push(U256::from(
-        state.get_code_size(u256::u256_to_address(address)),
-    ));
+    let address = u256::u256_to_address(ctx.stack.pop());
+    access_account(ctx, state, address)?;
+    ctx.stack.push(U256::from(state.get_code_size(address)));
     Ok(())
 }
 
@@ -508,23 +451,10 @@ pub fn ext_code_copy(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [address, dst_offset, offset, size] = ctx.stack.pop_n::<4>();
-
-    let code = state.get_code(u256::u256_to_address(address));
-    let copy_size = min(u256::u256_to_usize(size), code.len());
-    let start_offset = u256::u256_to_usize(offset);
-    if start_offset >= code.len() {
-        ctx.memory.fill(
-            u256::u256_to_usize(dst_offset),
-            0,
-            u256::u256_to_usize(size),
-        );
-    } else {
-        ctx.memory.write(
-            u256::u256_to_usize(dst_offset),
-            &code[start_offset..start_offset + copy_size],
-        );
-    }
-    Ok(())
+    let address = u256::u256_to_address(address);
+    access_account(ctx, state, address)?;
+    let code = state.get_code(address);
+    copy_to_memory(ctx, dst_offset, &code, offset, size)
 }
 
 pub fn return_data_size(
@@ -542,25 +472,13 @@ pub fn return_data_copy(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [dst_offset, offset, size] = ctx.stack.pop_n::<3>();
-
-    let copy_size = min(
-        u256::u256_to_usize(size),
-        ctx.return_data.len() - u256::u256_to_usize(offset),
-    );
-    let start_offset = u256::u256_to_usize(offset);
-    if start_offset >= ctx.return_data.len() {
-        ctx.memory.fill(
-            u256::u256_to_usize(dst_offset),
-            0,
-            u256::u256_to_usize(size),
-        );
-    } else {
-        ctx.memory.write(
-            u256::u256_to_usize(dst_offset),
-            &ctx.return_data[start_offset..start_offset + copy_size],
-        );
+    // 和其他复制指令不同，越界读取返回数据是错误 (EIP-211)
+    match offset.checked_add(size) {
+        Some(end) if end <= U256::from(ctx.return_data.len()) => {}
+        _ => return Err(EVMError::ReturnDataOutOfBounds),
     }
-    Ok(())
+    let return_data = ctx.return_data.clone();
+    copy_to_memory(ctx, dst_offset, &return_data, offset, size)
 }
 
 pub fn ext_code_hash(
@@ -568,9 +486,17 @@ pub fn ext_code_hash(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    let address = ctx.stack.pop();
-    ctx.stack
-        .push(state.get_code_hash(u256::u256_to_address(address)).into());
+    let address = u256::u256_to_address(ctx.stack.pop());
+    access_account(ctx, state, address)?;
+    // 不存在的账户和空账户为 0，没有代码的账户为空代码的哈希
+    let hash = match state.is_empty(address) {
+        true => U256::ZERO,
+        false => match state.get_code_hash(address) {
+            hash if hash.is_zero() => KECCAK_EMPTY.into(),
+            hash => hash,
+        },
+    };
+    ctx.stack.push(hash);
     Ok(())
 }
 
@@ -661,7 +587,12 @@ pub fn blob_hash(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    ctx.stack.push(blk_ctx.blob_hash);
+    let index = ctx.stack.top_mut();
+    *index = blk_ctx
+        .blob_hashes
+        .get(u256::u256_to_usize(*index))
+        .copied()
+        .unwrap_or_default();
     Ok(())
 }
 
@@ -689,8 +620,8 @@ pub fn mload(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let offset = ctx.stack.pop();
-    ctx.stack
-        .push(ctx.memory.read32(u256::u256_to_usize(offset)));
+    let (offset, _) = ctx.expand_memory(offset, U256::from(32))?;
+    ctx.stack.push(ctx.memory.read32(offset));
     Ok(())
 }
 
@@ -700,7 +631,8 @@ pub fn mstore(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [offset, value] = ctx.stack.pop_n::<2>();
-    ctx.memory.write32(u256::u256_to_usize(offset), value);
+    let (offset, _) = ctx.expand_memory(offset, U256::from(32))?;
+    ctx.memory.write32(offset, value);
     Ok(())
 }
 
@@ -710,8 +642,8 @@ pub fn mstore8(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [offset, value] = ctx.stack.pop_n::<2>();
-    ctx.memory
-        .write8(u256::u256_to_usize(offset), value.as_limbs()[0] as u8);
+    let (offset, _) = ctx.expand_memory(offset, U256::from(1))?;
+    ctx.memory.write8(offset, value.as_limbs()[0] as u8);
     Ok(())
 }
 
@@ -721,6 +653,9 @@ pub fn sload(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let key = ctx.stack.pop();
+    if state.access_slot(ctx.contract, key) {
+        ctx.use_gas(gas::COLD_SLOAD - gas::WARM_STORAGE_READ)?;
+    }
     ctx.stack.push(state.get_state(ctx.contract, key));
     Ok(())
 }
@@ -730,7 +665,49 @@ pub fn sstore(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
+    if ctx.is_static {
+        return Err(EVMError::WriteProtection);
+    }
+    if ctx.gas <= gas::SSTORE_SENTRY {
+        return Err(EVMError::OutOfGas);
+    }
     let [key, value] = ctx.stack.pop_n::<2>();
+    let cold = state.access_slot(ctx.contract, key);
+    let original = state.get_committed_state(ctx.contract, key);
+    let current = state.get_state(ctx.contract, key);
+
+    // EIP-2200 的计费和 EIP-3529 的退款，指令表中的静态 gas 为 0
+    let mut cost = match current == value || original != current {
+        true => gas::WARM_STORAGE_READ,
+        false if original.is_zero() => gas::SSTORE_SET,
+        false => gas::SSTORE_RESET,
+    };
+    if cold {
+        cost += gas::COLD_SLOAD;
+    }
+    ctx.use_gas(cost)?;
+
+    if current != value {
+        if original == current {
+            if !original.is_zero() && value.is_zero() {
+                state.add_refund(gas::SSTORE_CLEARS_REFUND);
+            }
+        } else {
+            if !original.is_zero() {
+                if current.is_zero() {
+                    state.sub_refund(gas::SSTORE_CLEARS_REFUND);
+                } else if value.is_zero() {
+                    state.add_refund(gas::SSTORE_CLEARS_REFUND);
+                }
+            }
+            if original == value {
+                match original.is_zero() {
+                    true => state.add_refund(gas::SSTORE_SET - gas::WARM_STORAGE_READ),
+                    false => state.add_refund(gas::SSTORE_RESET - gas::WARM_STORAGE_READ),
+                }
+            }
+        }
+    }
     state.set_state(ctx.contract, key, value);
     Ok(())
 }
@@ -741,12 +718,7 @@ pub fn jump(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let counter = ctx.stack.pop();
-    ctx.pc = u256::u256_to_usize(counter);
-    let next_code = ctx.code[ctx.pc + 1];
-    if next_code != JUMPDEST {
-        return Err(EVMError::InvalidJumpDestination);
-    }
-    Ok(())
+    jump_to(ctx, counter)
 }
 
 pub fn jumpi(
@@ -756,8 +728,51 @@ pub fn jumpi(
 ) -> Result<(), EVMError> {
     let [counter, condition] = ctx.stack.pop_n::<2>();
     if !condition.is_zero() {
-        ctx.pc = u256::u256_to_usize(counter);
+        return jump_to(ctx, counter);
+    }
+    Ok(())
+}
+
+/// 访问账户，冷账户额外扣除 gas (EIP-2929)
+pub(crate) fn access_account(
+    ctx: &mut Context,
+    state: &mut Box<dyn StateDB>,
+    address: Address,
+) -> Result<(), EVMError> {
+    if state.access_address(address) {
+        ctx.use_gas(gas::COLD_ACCOUNT_ACCESS - gas::WARM_STORAGE_READ)?;
+    }
+    Ok(())
+}
+
+/// 把 `data[offset..offset + size]` 复制到内存，超出 data 的部分填 0
+fn copy_to_memory(
+    ctx: &mut Context,
+    dst_offset: U256,
+    data: &[u8],
+    offset: U256,
+    size: U256,
+) -> Result<(), EVMError> {
+    let (dst_offset, size) = ctx.expand_memory(dst_offset, size)?;
+    ctx.use_gas(gas::COPY_WORD * gas::words(size as u64))?;
+    if size == 0 {
+        return Ok(());
+    }
+    let start = u256::u256_to_usize(offset).min(data.len());
+    let copy_size = size.min(data.len() - start);
+    ctx.memory
+        .write(dst_offset, &data[start..start + copy_size]);
+    ctx.memory.fill(dst_offset + copy_size, 0, size - copy_size);
+    Ok(())
+}
+
+/// 跳转目标必须是 JUMPDEST，跳转后解释器不再自增 pc
+fn jump_to(ctx: &mut Context, counter: U256) -> Result<(), EVMError> {
+    let dest = u256::u256_to_usize(counter);
+    if ctx.code.get(dest) != Some(&JUMPDEST) {
+        return Err(EVMError::InvalidJumpDestination);
     }
+    ctx.pc = dest;
     Ok(())
 }
 
@@ -784,8 +799,7 @@ pub fn gas(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    // TODO implement gas
-    ctx.stack.push(U256::ZERO);
+    ctx.stack.push(U256::from(ctx.gas));
     Ok(())
 }
 
@@ -814,6 +828,9 @@ pub fn tstore(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
+    if ctx.is_static {
+        return Err(EVMError::WriteProtection);
+    }
     let [key, value] = ctx.stack.pop_n::<2>();
     state.set_transition_state(ctx.contract, key, value);
     Ok(())
@@ -825,11 +842,10 @@ pub fn mcopy(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [dst_offset, offset, size] = ctx.stack.pop_n::<3>();
-    ctx.memory.copy(
-        u256::u256_to_usize(dst_offset),
-        u256::u256_to_usize(offset),
-        u256::u256_to_usize(size),
-    );
+    let (offset, size) = ctx.expand_memory(offset, size)?;
+    let (dst_offset, _) = ctx.expand_memory(dst_offset, U256::from(size))?;
+    ctx.use_gas(gas::COPY_WORD * gas::words(size as u64))?;
+    ctx.memory.copy(dst_offset, offset, size);
     Ok(())
 }
 
@@ -875,15 +891,18 @@ pub fn log<const N: usize>(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
+    if ctx.is_static {
+        return Err(EVMError::WriteProtection);
+    }
     let [offset, size] = ctx.stack.pop_n::<2>();
     let mut topics = Vec::new();
     for _ in 0..N {
         topics.push(ctx.stack.pop());
     }
 
-    let data = ctx
-        .memory
-        .read(u256::u256_to_usize(offset), u256::u256_to_usize(size));
+    let (offset, size) = ctx.expand_memory(offset, size)?;
+    ctx.use_gas(gas::LOG_DATA * size as u64)?;
+    let data = ctx.memory.read(offset, size);
 
     state.add_log(ctx.contract, topics, data);
     Ok(())
@@ -895,9 +914,9 @@ pub fn ret(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [offset, size] = ctx.stack.pop_n::<2>();
-    ctx.return_data = ctx
-        .memory
-        .read(u256::u256_to_usize(offset), u256::u256_to_usize(size));
+    let (offset, size) = ctx.expand_memory(offset, size)?;
+    ctx.output = ctx.memory.read(offset, size).into();
+    ctx.return_data = ctx.output.clone();
     Err(EVMError::Stop)
 }
 
@@ -907,10 +926,9 @@ pub fn revert(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
     let [offset, size] = ctx.stack.pop_n::<2>();
-    let err_data = ctx
-        .memory
-        .read(u256::u256_to_usize(offset), u256::u256_to_usize(size));
-    ctx.return_data = err_data;
+    let (offset, size) = ctx.expand_memory(offset, size)?;
+    ctx.output = ctx.memory.read(offset, size).into();
+    ctx.return_data = ctx.output.clone();
     Err(EVMError::Revert)
 }
 
@@ -919,5 +937,5 @@ pub fn invalid(
     state: &mut Box<dyn StateDB>,
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError> {
-    Err(EVMError::Revert)
+    Err(EVMError::InvalidOpcode(INVALID))
 }
@@ -0,0 +1,39 @@
+#![allow(unused)]
+
+pub mod asm;
+pub mod asm_fmt;
+pub mod asm_lint;
+pub mod block;
+pub mod blocktest;
+pub mod breakpoint;
+pub mod chain;
+pub mod cli;
+pub mod context;
+pub mod dev_accounts;
+pub mod disasm;
+pub mod error;
+pub mod execution;
+pub mod fork;
+pub mod fork_state;
+pub mod gas;
+pub mod genesis;
+pub mod gui;
+pub mod history;
+pub mod i256;
+pub mod instructions;
+pub mod mem;
+pub mod node;
+pub mod opcode;
+pub mod opcode_table;
+pub mod precompile;
+pub mod proof;
+pub mod receipt;
+pub mod stack;
+pub mod state;
+pub mod statetest;
+#[cfg(test)]
+mod test_utils;
+pub mod transaction;
+pub mod trie;
+pub mod u256;
+pub mod vm;
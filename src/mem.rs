@@ -1,123 +1,170 @@
 use alloy_primitives::U256;
+use std::cell::{Ref, RefCell};
 use std::cmp::max;
+use std::rc::Rc;
 
 const MEMORY_SIZE: usize = 1024;
 
+/// 一个交易内所有调用帧共用的内存缓冲区
+///
+/// 每个调用帧从父帧当前内存的末尾 (checkpoint) 开始使用缓冲区，
+/// 帧结束 (Memory 被 drop) 时把缓冲区截断回 checkpoint，
+/// 所以同一时刻只有最内层的帧可以扩展内存
 pub struct Memory {
-    pub memory: Vec<u8>,
+    buffer: Rc<RefCell<Vec<u8>>>,
+    checkpoint: usize,
+    len: usize,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Memory {
+    fn drop(&mut self) {
+        self.buffer.borrow_mut().truncate(self.checkpoint);
+    }
 }
 
 impl Memory {
     pub fn new() -> Self {
-        let mut mem = Memory {
-            memory: Vec::with_capacity(MEMORY_SIZE),
-        };
-        mem
+        Memory {
+            buffer: Rc::new(RefCell::new(Vec::with_capacity(MEMORY_SIZE))),
+            checkpoint: 0,
+            len: 0,
+        }
+    }
+
+    /// 为子调用帧创建内存，和当前帧共用同一个缓冲区
+    pub fn new_frame(&self) -> Self {
+        Memory {
+            buffer: self.buffer.clone(),
+            checkpoint: self.checkpoint + self.len,
+            len: 0,
+        }
     }
 
     pub fn len(&self) -> usize {
-        self.memory.len()
+        self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 所有调用帧一共使用的内存大小
+    pub fn total_len(&self) -> usize {
+        self.buffer.borrow().len()
+    }
+
+    /// 当前帧的内存内容
+    pub fn data(&self) -> Ref<'_, [u8]> {
+        Ref::map(self.buffer.borrow(), |buffer| {
+            &buffer[self.checkpoint..self.checkpoint + self.len]
+        })
+    }
+
+    /// 内存按 32 字节的字扩展
     fn ensure_capacity(&mut self, offset: usize, size: usize) {
-        if offset + size > self.memory.len() {
-            self.memory.resize(offset + size, 0);
+        if size > 0 && offset + size > self.len {
+            let mut buffer = self.buffer.borrow_mut();
+            debug_assert_eq!(
+                buffer.len(),
+                self.checkpoint + self.len,
+                "only the innermost frame can expand memory"
+            );
+            self.len = (offset + size).div_ceil(32) * 32;
+            buffer.resize(self.checkpoint + self.len, 0);
         }
     }
 
+    /// 把内存扩展到至少包含 `[offset, offset + size)`
+    pub fn expand(&mut self, offset: usize, size: usize) {
+        self.ensure_capacity(offset, size);
+    }
+
+    fn with_slice<R>(&mut self, offset: usize, size: usize, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        self.ensure_capacity(offset, size);
+        let start = self.checkpoint + offset;
+        f(&mut self.buffer.borrow_mut()[start..start + size])
+    }
+
     pub fn write32(&mut self, offset: usize, value: U256) {
-        self.ensure_capacity(offset, 32);
-        self.memory[offset..offset + 32].copy_from_slice(value.to_be_bytes_vec().as_slice());
+        self.with_slice(offset, 32, |slice| {
+            slice.copy_from_slice(&value.to_be_bytes::<32>())
+        });
     }
 
     pub fn write8(&mut self, offset: usize, value: u8) {
-        self.ensure_capacity(offset, 1);
-        self.memory[offset] = value;
+        self.with_slice(offset, 1, |slice| slice[0] = value);
     }
 
     pub fn write(&mut self, offset: usize, value: &[u8]) {
-        if value.len() > 0 {
-            self.ensure_capacity(offset, value.len());
-            self.memory[offset..offset + value.len()].copy_from_slice(value);
+        if !value.is_empty() {
+            self.with_slice(offset, value.len(), |slice| slice.copy_from_slice(value));
         }
     }
 
     pub fn write_with_size(&mut self, offset: usize, size: usize, value: &[u8]) {
-        self.ensure_capacity(offset, size);
-        self.memory[offset..offset + size].copy_from_slice(&value[..size]);
-
-        if size > value.len() {
-            for i in offset + value.len()..offset + size {
-                self.memory[i] = 0;
-            }
-        }
+        let copy_size = size.min(value.len());
+        self.with_slice(offset, size, |slice| {
+            slice[..copy_size].copy_from_slice(&value[..copy_size]);
+            slice[copy_size..].fill(0);
+        });
     }
 
     pub fn fill(&mut self, offset: usize, value: u8, size: usize) {
-        self.ensure_capacity(offset, size);
-        self.memory[offset..offset + size].fill(value);
+        self.with_slice(offset, size, |slice| slice.fill(value));
     }
 
     pub fn read(&mut self, offset: usize, size: usize) -> Vec<u8> {
         if size == 0 {
             return vec![];
         }
-        self.ensure_capacity(offset, size);
-        self.memory[offset..offset + size].to_vec()
+        self.with_slice(offset, size, |slice| slice.to_vec())
     }
 
     pub fn read32(&mut self, offset: usize) -> U256 {
-        self.ensure_capacity(offset, 32);
-        U256::from_be_slice(&self.memory[offset..offset + 32])
+        self.with_slice(offset, 32, |slice| U256::from_be_slice(slice))
     }
 
     pub fn copy(&mut self, dst_offset: usize, src_offset: usize, size: usize) {
+        if size == 0 {
+            return;
+        }
         self.ensure_capacity(max(src_offset, dst_offset), size);
-        let (dst_slice, src_slice) = self.memory.split_at_mut(dst_offset);
-        dst_slice[dst_offset..dst_offset + size]
-            .copy_from_slice(&src_slice[src_offset..src_offset + size]);
+        let start = self.checkpoint;
+        self.buffer.borrow_mut()[start..].copy_within(src_offset..src_offset + size, dst_offset);
     }
+}
 
-    pub fn print_memory(&self) {
-        // 打印表头
-        println!("{}", "-".repeat(16 * 3 + 7 + 19));
-        print!("Offset  ");
-        for i in 0..16 {
-            print!("{:02x} ", i);
-        }
-        print!("  ASCII");
-        println!();
-
-        // 打印分隔线
-        println!("{}", "-".repeat(16 * 3 + 7 + 19));
-
-        // 按每行 16 字节打印内存内容
-        for (i, chunk) in self.memory.chunks(16).enumerate() {
-            // 打印左侧的内存地址偏移量
-            print!("{:06x}  ", i * 16);
-
-            // 打印每个字节的内容
-            for byte in chunk {
-                print!("{:02x} ", byte);
-            }
-
-            // 如果当前行不足 16 字节，用空格补齐
-            if chunk.len() < 16 {
-                print!("{}", "   ".repeat(16 - chunk.len()));
-            }
-
-            // 打印 ASCII 列
-            print!("  ");
-            for byte in chunk {
-                if byte.is_ascii_graphic() || *byte == b' ' {
-                    print!("{}", *byte as char);
-                } else {
-                    print!(".");
-                }
-            }
-
-            println!();
-        }
-        println!("{}", "-".repeat(16 * 3 + 7 + 19));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_share_buffer() {
+        let mut memory = Memory::new();
+        memory.write32(0, U256::from(1));
+
+        let mut frame = memory.new_frame();
+        frame.write8(0, 0xff);
+        assert_eq!(frame.len(), 32);
+        assert_eq!(memory.total_len(), 64);
+        assert_eq!(frame.read(0, 1), vec![0xff]);
+        drop(frame);
+
+        assert_eq!(memory.total_len(), 32);
+        assert_eq!(memory.read32(0), U256::from(1));
+    }
+
+    #[test]
+    fn test_copy() {
+        let mut memory = Memory::new();
+        memory.write(0, &[1, 2, 3, 4]);
+        memory.copy(2, 0, 4);
+        assert_eq!(memory.read(0, 6), vec![1, 2, 1, 2, 3, 4]);
     }
 }
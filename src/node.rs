@@ -0,0 +1,817 @@
+//! 本地开发节点：在 `LocalChain` 上提供以太坊 JSON-RPC，通过 HTTP 访问
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::block::Block;
+use crate::chain::LocalChain;
+use crate::context::BlockContext;
+use crate::error::EVMError;
+use crate::receipt::{Log, Receipt};
+use crate::transaction::{ExecutionResult, Message, SignedTransaction};
+
+/// 和 anvil、hardhat 相同的默认 chain id
+pub const DEFAULT_CHAIN_ID: u64 = 31337;
+/// 请求体的最大长度
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+/// 没有请求时检查定时出块的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    /// REVERT 返回的数据
+    data: Option<Bytes>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    fn invalid_params(message: impl ToString) -> Self {
+        Self::new(-32602, message)
+    }
+
+    fn server(message: impl ToString) -> Self {
+        Self::new(-32000, message)
+    }
+
+    /// 执行失败的调用，REVERT 带上返回的数据
+    fn execution(error: EVMError, output: Bytes) -> Self {
+        match error {
+            EVMError::Revert => RpcError {
+                code: 3,
+                message: "execution reverted".to_string(),
+                data: Some(output),
+            },
+            error => Self::server(error),
+        }
+    }
+}
+
+impl From<EVMError> for RpcError {
+    fn from(error: EVMError) -> Self {
+        Self::server(error)
+    }
+}
+
+/// eth_call 和 eth_estimateGas 的参数，省略的字段使用默认值
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct CallRequest {
+    from: Option<Address>,
+    to: Option<Address>,
+    gas: Option<U64>,
+    gas_price: Option<U256>,
+    max_fee_per_gas: Option<U256>,
+    max_priority_fee_per_gas: Option<U256>,
+    value: Option<U256>,
+    /// `input` 和 `data` 都可以，同时存在时使用 `input`
+    input: Option<Bytes>,
+    data: Option<Bytes>,
+}
+
+/// 地址和 topic 的过滤条件可以是单个值或者列表
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T: PartialEq> OneOrMany<T> {
+    fn contains(&self, value: &T) -> bool {
+        match self {
+            OneOrMany::One(one) => one == value,
+            OneOrMany::Many(many) => many.contains(value),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct LogFilter {
+    from_block: Option<Value>,
+    to_block: Option<Value>,
+    block_hash: Option<B256>,
+    address: Option<OneOrMany<Address>>,
+    /// 每个位置为 null 时匹配任意 topic
+    topics: Vec<Option<OneOrMany<B256>>>,
+}
+
+pub struct Node {
+    pub chain: LocalChain,
+    /// 收到交易后立即出块
+    pub automine: bool,
+    /// 定时出块的间隔，和 `automine` 可以同时打开
+    pub block_time: Option<Duration>,
+}
+
+impl Node {
+    pub fn new(chain: LocalChain) -> Self {
+        Node {
+            chain,
+            automine: true,
+            block_time: None,
+        }
+    }
+
+    /// 以当前时间出块，不早于父区块时间加 1 秒
+    pub fn mine(&mut self) -> Result<&Block, EVMError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let timestamp = now.max(self.chain.head().timestamp + 1);
+        self.chain.mine_block_at(timestamp)
+    }
+
+    /// 在 `listener` 上逐个处理 HTTP 连接，打开定时出块时在请求之间出块
+    pub fn serve(&mut self, listener: TcpListener) -> io::Result<()> {
+        listener.set_nonblocking(true)?;
+        let mut last_block = Instant::now();
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    // 单个连接出错不影响其它请求
+                    if let Err(e) = self.handle_connection(stream) {
+                        eprintln!("connection error: {}", e);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => return Err(e),
+            }
+            if let Some(block_time) = self.block_time {
+                if last_block.elapsed() >= block_time {
+                    if let Err(e) = self.mine() {
+                        eprintln!("mine block: {}", e);
+                    }
+                    last_block = Instant::now();
+                }
+            }
+        }
+    }
+
+    /// 读取一个 HTTP 请求并返回响应，响应后关闭连接
+    pub fn handle_connection(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        let mut writer = &stream;
+        match request_line.split_whitespace().next() {
+            // 浏览器跨域请求的预检
+            Some("OPTIONS") => write_response(&mut writer, "204 No Content", ""),
+            Some("POST") if content_length > MAX_BODY_SIZE => {
+                write_response(&mut writer, "413 Payload Too Large", "")
+            }
+            Some("POST") => {
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body)?;
+                let response = match serde_json::from_slice::<Value>(&body) {
+                    Ok(request) => self.handle(&request),
+                    Err(e) => response(Value::Null, Err(RpcError::new(-32700, e))),
+                };
+                write_response(&mut writer, "200 OK", &response.to_string())
+            }
+            _ => write_response(&mut writer, "405 Method Not Allowed", ""),
+        }
+    }
+
+    /// 处理一个 JSON-RPC 请求或者批量请求
+    pub fn handle(&mut self, request: &Value) -> Value {
+        match request {
+            Value::Array(requests) if !requests.is_empty() => {
+                Value::Array(requests.iter().map(|r| self.handle_one(r)).collect())
+            }
+            request => self.handle_one(request),
+        }
+    }
+
+    fn handle_one(&mut self, request: &Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str);
+        let result = match (method, request.get("params")) {
+            (Some(method), None) => self.dispatch(method, &[]),
+            (Some(method), Some(Value::Array(params))) => self.dispatch(method, params),
+            _ => Err(RpcError::new(-32600, "invalid request")),
+        };
+        response(id, result)
+    }
+
+    fn dispatch(&mut self, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+        let head = self.chain.head().number;
+        match method {
+            "eth_chainId" => Ok(quantity(self.chain.chain_id())),
+            "eth_blockNumber" => Ok(quantity(head)),
+            "eth_gasPrice" => Ok(quantity(self.chain.head().next_base_fee())),
+            "eth_getBalance" => {
+                let address: Address = param(params, 0)?;
+                self.check_block(params.get(1))?;
+                Ok(json!(self.chain.state().get_balance(address)))
+            }
+            "eth_getTransactionCount" => {
+                let address: Address = param(params, 0)?;
+                self.check_block(params.get(1))?;
+                Ok(quantity(self.chain.state().get_nonce(address)))
+            }
+            "eth_getCode" => {
+                let address: Address = param(params, 0)?;
+                self.check_block(params.get(1))?;
+                Ok(json!(self.chain.state().get_code(address)))
+            }
+            "eth_getStorageAt" => {
+                let address: Address = param(params, 0)?;
+                let slot: U256 = param(params, 1)?;
+                self.check_block(params.get(2))?;
+                let value = self.chain.state().get_state(address, slot);
+                Ok(json!(B256::from(value)))
+            }
+            "eth_call" => self.eth_call(params),
+            "eth_estimateGas" => self.estimate_gas(params),
+            "eth_sendRawTransaction" => self.send_raw_transaction(params),
+            "eth_getTransactionReceipt" => {
+                let hash: B256 = param(params, 0)?;
+                let Some((number, index)) = self.chain.find_transaction(hash) else {
+                    return Ok(Value::Null);
+                };
+                let block = self.chain.block(number).unwrap();
+                let receipt = &self.chain.receipts(number).unwrap()[index];
+                Ok(receipt_json(block, receipt))
+            }
+            "eth_getLogs" => self.get_logs(params),
+            "evm_mine" => {
+                self.mine()?;
+                Ok(json!("0x0"))
+            }
+            "evm_setAutomine" => {
+                self.automine = param(params, 0)?;
+                Ok(json!(true))
+            }
+            "evm_setIntervalMining" => {
+                // 0 关闭定时出块
+                let seconds: u64 = param(params, 0)?;
+                self.block_time = (seconds > 0).then(|| Duration::from_secs(seconds));
+                Ok(json!(true))
+            }
+            _ => Err(RpcError::new(
+                -32601,
+                format!("method {} not found", method),
+            )),
+        }
+    }
+
+    fn eth_call(&mut self, params: &[Value]) -> Result<Value, RpcError> {
+        let request: CallRequest = param(params, 0)?;
+        self.check_block(params.get(1))?;
+        let (msg, mut blk_ctx) = self.call_message(&request);
+        let result = self.simulate(&msg, &mut blk_ctx)?;
+        match result.result {
+            Ok(()) => Ok(json!(result.output)),
+            Err(e) => Err(RpcError::execution(e, result.output)),
+        }
+    }
+
+    /// 二分查找调用成功需要的最小 gas limit
+    fn estimate_gas(&mut self, params: &[Value]) -> Result<Value, RpcError> {
+        let request: CallRequest = param(params, 0)?;
+        self.check_block(params.get(1))?;
+        let (mut msg, mut blk_ctx) = self.call_message(&request);
+        let result = self.simulate(&msg, &mut blk_ctx)?;
+        if let Err(e) = result.result {
+            return Err(RpcError::execution(e, result.output));
+        }
+
+        // gas_used 已经扣除了退款，比它少 1 一定不够
+        let (mut low, mut high) = (result.gas_used - 1, msg.gas_limit);
+        while low + 1 < high {
+            msg.gas_limit = low + (high - low) / 2;
+            match self.simulate(&msg, &mut blk_ctx) {
+                Ok(result) if result.is_success() => high = msg.gas_limit,
+                _ => low = msg.gas_limit,
+            }
+        }
+        Ok(quantity(high))
+    }
+
+    fn send_raw_transaction(&mut self, params: &[Value]) -> Result<Value, RpcError> {
+        let raw: Bytes = param(params, 0)?;
+        let tx = SignedTransaction::decode(&raw)?;
+        let mut blk_ctx = self.chain.pending_block_context();
+        let mut msg = tx.to_message(&blk_ctx)?;
+        // 队列中同一发送者的交易还没有执行，新交易的 nonce 要接在它们之后，
+        // 余额要够支付它们和新交易最多花费的总和
+        let queued: Vec<_> = self
+            .chain
+            .pending_transactions()
+            .filter(|pending| pending.recover_sender().ok() == Some(msg.caller))
+            .filter_map(|pending| pending.to_message(&blk_ctx).ok())
+            .collect();
+        let nonce = self.chain.state().get_nonce(msg.caller) + queued.len() as u64;
+        if msg.nonce != nonce {
+            return Err(RpcError::server(format!(
+                "nonce mismatch: expected {nonce}, got {}",
+                msg.nonce
+            )));
+        }
+        let cost = queued
+            .iter()
+            .chain([&msg])
+            .try_fold(U256::ZERO, |sum, msg| sum.checked_add(msg.max_cost()?));
+        if cost.is_none_or(|cost| cost > self.chain.state().get_balance(msg.caller)) {
+            return Err(RpcError::server(
+                "insufficient funds for queued transactions",
+            ));
+        }
+        // 在最新状态上检查费用，无效的交易直接返回错误
+        msg.nonce -= queued.len() as u64;
+        self.simulate(&msg, &mut blk_ctx)?;
+        let hash = self.chain.add_transaction(tx);
+        if self.automine {
+            self.mine()?;
+        }
+        Ok(json!(hash))
+    }
+
+    fn get_logs(&self, params: &[Value]) -> Result<Value, RpcError> {
+        let filter: LogFilter = param(params, 0)?;
+        let latest = json!("latest");
+        let (from, to) = match filter.block_hash {
+            Some(hash) => {
+                let block = self
+                    .chain
+                    .block_by_hash(hash)
+                    .ok_or_else(|| RpcError::server("unknown block"))?;
+                (block.header.number, block.header.number)
+            }
+            None => (
+                self.block_number(filter.from_block.as_ref().unwrap_or(&latest))?,
+                self.block_number(filter.to_block.as_ref().unwrap_or(&latest))?,
+            ),
+        };
+
+        let mut logs = Vec::new();
+        for number in from..=to.min(self.chain.head().number) {
+            let block = self.chain.block(number).unwrap();
+            for receipt in self.chain.receipts(number).unwrap() {
+                for log in &receipt.logs {
+                    let address = filter
+                        .address
+                        .as_ref()
+                        .is_none_or(|address| address.contains(&log.address));
+                    let topics = filter.topics.iter().enumerate().all(|(i, topics)| {
+                        let Some(topics) = topics else {
+                            return true;
+                        };
+                        log.topics
+                            .get(i)
+                            .is_some_and(|&topic| topics.contains(&topic.into()))
+                    });
+                    if address && topics {
+                        logs.push(log_json(block, log));
+                    }
+                }
+            }
+        }
+        Ok(Value::Array(logs))
+    }
+
+    /// 调用的消息和下一个区块的上下文，gas price 为 0 时不检查 base fee
+    fn call_message(&self, request: &CallRequest) -> (Message, BlockContext) {
+        let mut blk_ctx = self.chain.pending_block_context();
+        let caller = request.from.unwrap_or_default();
+        let gas_price = request
+            .gas_price
+            .or(request.max_fee_per_gas)
+            .unwrap_or_default();
+        if gas_price.is_zero() {
+            blk_ctx.base_fee = U256::ZERO;
+        }
+        let msg = Message {
+            caller,
+            to: request.to,
+            value: request.value.unwrap_or_default(),
+            data: request
+                .input
+                .clone()
+                .or_else(|| request.data.clone())
+                .unwrap_or_default(),
+            nonce: self.chain.state().get_nonce(caller),
+            gas_limit: request
+                .gas
+                .map_or(blk_ctx.block_gas_limit.to(), |gas| gas.to()),
+            gas_price,
+            max_priority_fee_per_gas: request
+                .max_fee_per_gas
+                .filter(|fee| !fee.is_zero())
+                .map(|_| request.max_priority_fee_per_gas.unwrap_or_default()),
+            ..Default::default()
+        };
+        (msg, blk_ctx)
+    }
+
+    fn simulate(
+        &mut self,
+        msg: &Message,
+        blk_ctx: &mut BlockContext,
+    ) -> Result<ExecutionResult, RpcError> {
+        blk_ctx.gas_price = msg.effective_gas_price(blk_ctx.base_fee);
+        blk_ctx.blob_hashes = msg.blob_hashes.clone();
+        Ok(self.chain.call(msg, blk_ctx)?)
+    }
+
+    /// 只保存最新的状态，查询其它区块的状态时返回错误
+    fn check_block(&self, block: Option<&Value>) -> Result<(), RpcError> {
+        let Some(block) = block else {
+            return Ok(());
+        };
+        match self.block_number(block)? == self.chain.head().number {
+            true => Ok(()),
+            false => Err(RpcError::server("historical state is not available")),
+        }
+    }
+
+    fn block_number(&self, block: &Value) -> Result<u64, RpcError> {
+        match block.as_str() {
+            Some("earliest") => Ok(0),
+            Some("latest" | "pending" | "safe" | "finalized") => Ok(self.chain.head().number),
+            _ => serde_json::from_value::<U64>(block.clone())
+                .map(|number| number.to())
+                .map_err(RpcError::invalid_params),
+        }
+    }
+}
+
+fn param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, RpcError> {
+    let value = params
+        .get(index)
+        .ok_or_else(|| RpcError::invalid_params(format!("missing param {}", index)))?;
+    serde_json::from_value(value.clone()).map_err(RpcError::invalid_params)
+}
+
+fn quantity(n: u64) -> Value {
+    json!(format!("{:#x}", n))
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => {
+            let mut error = json!({ "code": e.code, "message": e.message });
+            if let Some(data) = e.data {
+                error["data"] = json!(data);
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        }
+    }
+}
+
+fn receipt_json(block: &Block, receipt: &Receipt) -> Value {
+    let tx = &block.transactions[receipt.transaction_index as usize];
+    let from = tx.recover_sender().unwrap_or_default();
+    let base_fee = U256::from(block.header.base_fee_per_gas.unwrap_or_default());
+    json!({
+        "transactionHash": tx.hash(),
+        "transactionIndex": quantity(receipt.transaction_index),
+        "blockHash": block.hash(),
+        "blockNumber": quantity(block.header.number),
+        "from": from,
+        "to": tx.to,
+        "cumulativeGasUsed": quantity(receipt.cumulative_gas_used),
+        "gasUsed": quantity(receipt.gas_used),
+        "effectiveGasPrice": tx.message(from).effective_gas_price(base_fee),
+        "contractAddress": receipt.contract_address,
+        "logs": receipt.logs.iter().map(|log| log_json(block, log)).collect::<Vec<_>>(),
+        "logsBloom": receipt.logs_bloom,
+        "type": quantity(receipt.tx_type as u64),
+        "status": quantity(receipt.success as u64),
+    })
+}
+
+fn log_json(block: &Block, log: &Log) -> Value {
+    let tx = &block.transactions[log.transaction_index as usize];
+    json!({
+        "address": log.address,
+        "topics": log.topics.iter().map(|&topic| B256::from(topic)).collect::<Vec<_>>(),
+        "data": log.data,
+        "blockNumber": quantity(block.header.number),
+        "blockHash": block.hash(),
+        "transactionHash": tx.hash(),
+        "transactionIndex": quantity(log.transaction_index),
+        "logIndex": quantity(log.log_index),
+        "removed": false,
+    })
+}
+
+fn write_response(out: &mut impl Write, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: *\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev_accounts::{DevAccount, DEFAULT_BALANCE};
+    use crate::fork::Fork;
+    use crate::test_utils::{dev_account, genesis};
+    use crate::transaction::EIP1559_TX_TYPE;
+    use alloy_primitives::hex;
+
+    const CONTRACT: Address = Address::repeat_byte(0xcc);
+    const REVERTER: Address = Address::repeat_byte(0xdd);
+
+    fn node() -> (Node, DevAccount) {
+        let account = dev_account();
+        let state = genesis(
+            std::slice::from_ref(&account),
+            &[
+                // SSTORE(0, x); LOG1(topic x); 返回 SLOAD(0)，x 是 calldata 的第一个字
+                (
+                    CONTRACT,
+                    &hex!("6000358060005560006000a160005460005260206000f3"),
+                ),
+                // REVERT 返回 32 字节的 1
+                (REVERTER, &hex!("600160005260206000fd")),
+            ],
+        );
+        let chain = LocalChain::new(state, DEFAULT_CHAIN_ID, Fork::Cancun);
+        (Node::new(chain), account)
+    }
+
+    fn call(node: &mut Node, method: &str, params: Value) -> Value {
+        node.handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+    }
+
+    fn result(node: &mut Node, method: &str, params: Value) -> Value {
+        let response = call(node, method, params);
+        assert!(response["error"].is_null(), "{}", response);
+        response["result"].clone()
+    }
+
+    fn transaction(account: &DevAccount, nonce: u64, gas_limit: u64, data: Bytes) -> Value {
+        let mut tx = SignedTransaction::new(EIP1559_TX_TYPE, DEFAULT_CHAIN_ID);
+        tx.nonce = nonce;
+        tx.gas_price = U256::from(10_000_000_000u64);
+        tx.max_priority_fee_per_gas = Some(U256::from(1));
+        tx.gas_limit = gas_limit;
+        tx.to = Some(CONTRACT);
+        tx.data = data;
+        json!([Bytes::from(account.sign_transaction(tx).encode())])
+    }
+
+    #[test]
+    fn test_rpc() {
+        let (mut node, account) = node();
+        assert_eq!(result(&mut node, "eth_chainId", json!([])), "0x7a69");
+        assert_eq!(result(&mut node, "eth_blockNumber", json!([])), "0x0");
+        assert_eq!(
+            result(
+                &mut node,
+                "eth_getBalance",
+                json!([account.address, "latest"])
+            ),
+            json!(DEFAULT_BALANCE)
+        );
+        assert_eq!(
+            result(&mut node, "eth_getCode", json!([REVERTER])),
+            "0x600160005260206000fd"
+        );
+        assert_eq!(
+            call(&mut node, "eth_getBalance", json!([account.address, "0x5"]))["error"]["code"],
+            -32000
+        );
+
+        // eth_call 不修改状态
+        let x = B256::with_last_byte(7);
+        let request = json!({ "from": account.address, "to": CONTRACT, "data": x });
+        assert_eq!(result(&mut node, "eth_call", json!([request])), json!(x));
+        assert_eq!(
+            result(
+                &mut node,
+                "eth_getStorageAt",
+                json!([CONTRACT, "0x0", "latest"])
+            ),
+            json!(B256::ZERO)
+        );
+        let error = call(&mut node, "eth_call", json!([{ "to": REVERTER }]))["error"].clone();
+        assert_eq!(error["code"], 3);
+        assert_eq!(error["data"], json!(B256::with_last_byte(1)));
+
+        // 估算的 gas 正好够用
+        let gas = result(&mut node, "eth_estimateGas", json!([request]));
+        let gas = u64::from_str_radix(gas.as_str().unwrap().trim_start_matches("0x"), 16).unwrap();
+        let data = Bytes::from(x.to_vec());
+        let failed = call(
+            &mut node,
+            "eth_sendRawTransaction",
+            transaction(&account, 0, gas - 1, data.clone()),
+        );
+        let failed: B256 = serde_json::from_value(failed["result"].clone()).unwrap();
+        let hash = result(
+            &mut node,
+            "eth_sendRawTransaction",
+            transaction(&account, 1, gas, data.clone()),
+        );
+        assert_eq!(result(&mut node, "eth_blockNumber", json!([])), "0x2");
+        let receipt = result(&mut node, "eth_getTransactionReceipt", json!([failed]));
+        assert_eq!(receipt["status"], "0x0");
+        let receipt = result(&mut node, "eth_getTransactionReceipt", json!([hash]));
+        assert_eq!(receipt["status"], "0x1");
+        assert_eq!(receipt["blockNumber"], "0x2");
+        assert_eq!(receipt["from"], json!(account.address));
+        assert_eq!(receipt["logs"][0]["topics"], json!([x]));
+        assert_eq!(
+            result(&mut node, "eth_getStorageAt", json!([CONTRACT, "0x0"])),
+            json!(x)
+        );
+
+        // nonce 已经使用过
+        let error = call(
+            &mut node,
+            "eth_sendRawTransaction",
+            transaction(&account, 1, gas, data.clone()),
+        );
+        assert_eq!(error["error"]["code"], -32000);
+
+        // 关闭 automine 后交易等到 evm_mine 才打包
+        result(&mut node, "evm_setAutomine", json!([false]));
+        let hash = result(
+            &mut node,
+            "eth_sendRawTransaction",
+            transaction(
+                &account,
+                2,
+                gas,
+                Bytes::from(B256::with_last_byte(8).to_vec()),
+            ),
+        );
+        assert!(result(&mut node, "eth_getTransactionReceipt", json!([hash])).is_null());
+        // 不出块也要检查交易，nonce 接在队列中的交易之后
+        let error = call(
+            &mut node,
+            "eth_sendRawTransaction",
+            transaction(&account, 2, gas, data.clone()),
+        );
+        assert_eq!(error["error"]["code"], -32000);
+        let error = call(
+            &mut node,
+            "eth_sendRawTransaction",
+            transaction(&account, 3, 1_000, data.clone()),
+        );
+        assert_eq!(error["error"]["code"], -32000);
+        assert_eq!(node.chain.pending_transactions().count(), 1);
+        let queued = result(
+            &mut node,
+            "eth_sendRawTransaction",
+            transaction(&account, 3, gas, data.clone()),
+        );
+        result(&mut node, "evm_mine", json!([]));
+        assert_eq!(
+            result(&mut node, "eth_getTransactionReceipt", json!([queued]))["status"],
+            "0x1"
+        );
+        assert_eq!(
+            result(&mut node, "eth_getTransactionReceipt", json!([hash]))["logs"][0]["logIndex"],
+            "0x0"
+        );
+        assert_eq!(
+            result(
+                &mut node,
+                "eth_getTransactionCount",
+                json!([account.address])
+            ),
+            "0x4"
+        );
+
+        let logs = |node: &mut Node, filter: Value| {
+            result(node, "eth_getLogs", json!([filter]))
+                .as_array()
+                .unwrap()
+                .len()
+        };
+        assert_eq!(logs(&mut node, json!({ "fromBlock": "earliest" })), 3);
+        assert_eq!(logs(&mut node, json!({})), 2);
+        assert_eq!(
+            logs(
+                &mut node,
+                json!({ "fromBlock": "0x0", "address": [CONTRACT], "topics": [x] })
+            ),
+            2
+        );
+        assert_eq!(
+            logs(
+                &mut node,
+                json!({ "fromBlock": "0x0", "address": REVERTER, "topics": [null] })
+            ),
+            0
+        );
+
+        assert_eq!(
+            call(&mut node, "eth_foo", json!([]))["error"]["code"],
+            -32601
+        );
+        let batch = node.handle(&json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "eth_chainId" },
+            { "jsonrpc": "2.0", "id": 2, "method": "eth_blockNumber" },
+        ]));
+        assert_eq!(batch[1]["id"], 2);
+        assert_eq!(batch[1]["result"], "0x3");
+    }
+
+    #[test]
+    fn test_queued_balance() {
+        let (mut node, account) = node();
+        result(&mut node, "evm_setAutomine", json!([false]));
+        let send = |node: &mut Node, nonce: u64, value: U256| {
+            let mut tx = SignedTransaction::new(EIP1559_TX_TYPE, DEFAULT_CHAIN_ID);
+            tx.nonce = nonce;
+            tx.gas_price = U256::from(10_000_000_000u64);
+            tx.max_priority_fee_per_gas = Some(U256::from(1));
+            tx.gas_limit = 100_000;
+            tx.to = Some(CONTRACT);
+            tx.value = value;
+            let raw = Bytes::from(account.sign_transaction(tx).encode());
+            call(node, "eth_sendRawTransaction", json!([raw]))
+        };
+
+        // 每笔单独都付得起，加上队列中的第一笔就超出余额
+        let half = DEFAULT_BALANCE / U256::from(2);
+        assert!(send(&mut node, 0, half)["error"].is_null());
+        let error = send(&mut node, 1, half);
+        assert_eq!(error["error"]["code"], -32000);
+        assert_eq!(node.chain.pending_transactions().count(), 1);
+
+        result(&mut node, "evm_mine", json!([]));
+        assert!(node.chain.dropped_transactions().is_empty());
+        assert_eq!(node.chain.state().get_balance(CONTRACT), half);
+    }
+
+    #[test]
+    fn test_http() {
+        let (mut node, _) = node();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || {
+            let body = r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#;
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(
+                stream,
+                "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let (stream, _) = listener.accept().unwrap();
+        node.handle_connection(stream).unwrap();
+
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["result"], "0x7a69");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::fork::Fork::{self, *};
 use crate::opcode::*;
 use crate::{
     context::{BlockContext, Context},
@@ -15,193 +16,669 @@ pub type InstFn = fn(
     blk_ctx: &BlockContext,
 ) -> Result<(), EVMError>;
 
+/// 以 opcode 为下标的指令表，未启用的指令为 None
+pub type JumpTable = [Option<InstFn>; 256];
+
+/// 以 opcode 为下标的 (inputs, outputs)，解释器据此在执行前一次性检查栈高度
+pub type StackTable = [(usize, usize); 256];
+
+/// 以 opcode 为下标的静态 gas，解释器在执行指令前扣除
+pub type GasTable = [u64; 256];
+
+/// 指令的元数据，反汇编、gas 计算、静态分析和硬分叉判断共用
+#[derive(Clone, Copy)]
+pub struct OpcodeInfo {
+    pub opcode: u8,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// 从栈上弹出的元素个数
+    pub inputs: usize,
+    /// 压入栈的元素个数
+    pub outputs: usize,
+    /// 紧跟在 opcode 之后的立即数字节数
+    pub immediate_size: usize,
+    /// 静态 gas，不包括内存扩展、冷热访问等动态部分
+    pub static_gas: u64,
+    /// 执行后不会顺序执行下一条指令 (STOP/RETURN/REVERT/INVALID/SELFDESTRUCT/JUMP)
+    pub terminates: bool,
+    /// 可能修改 pc (JUMP/JUMPI)
+    pub jumps: bool,
+    /// 引入该指令的硬分叉
+    pub fork: Fork,
+    pub inst_fn: InstFn,
+}
+
+impl OpcodeInfo {
+    #[allow(clippy::too_many_arguments)]
+    const fn new(
+        opcode: u8,
+        name: &'static str,
+        description: &'static str,
+        inputs: usize,
+        outputs: usize,
+        static_gas: u64,
+        fork: Fork,
+        inst_fn: InstFn,
+    ) -> Self {
+        let immediate_size = match opcode {
+            PUSH1..=PUSH32 => (opcode - PUSH1 + 1) as usize,
+            _ => 0,
+        };
+        OpcodeInfo {
+            opcode,
+            name,
+            description,
+            inputs,
+            outputs,
+            immediate_size,
+            static_gas,
+            terminates: matches!(
+                opcode,
+                STOP | RETURN | REVERT | INVALID | SELFDESTRUCT | JUMP
+            ),
+            jumps: matches!(opcode, JUMP | JUMPI),
+            fork,
+            inst_fn,
+        }
+    }
+
+    /// 指令在给定硬分叉中是否可用
+    pub fn is_enabled(&self, fork: Fork) -> bool {
+        fork >= self.fork
+    }
+
+    /// 是否结束一个基本块
+    pub fn ends_block(&self) -> bool {
+        self.terminates || self.jumps
+    }
+
+    /// 执行该指令后栈高度的变化
+    pub fn stack_delta(&self) -> isize {
+        self.outputs as isize - self.inputs as isize
+    }
+
+    /// 指令所属的类别，按 opcode 的区段划分
+    pub fn category(&self) -> &'static str {
+        match self.opcode {
+            0x00..=0x0b => "arithmetic",
+            0x10..=0x1d => "bitwise",
+            0x20 => "keccak",
+            0x30..=0x3f => "environment",
+            0x40..=0x4a => "block",
+            0x50..=0x5e => "memory/storage/flow",
+            PUSH0..=PUSH32 => "push",
+            DUP1..=DUP16 => "dup",
+            SWAP1..=SWAP16 => "swap",
+            LOG0..=LOG4 => "log",
+            _ => "system",
+        }
+    }
+}
+
 macro_rules! inst {
-    ($opcode:expr, $name:expr, $desc:expr, $func:expr) => {
-        ($opcode, $name, $desc, $func as InstFn)
+    ($opcode:expr, $name:expr, $desc:expr, $inputs:expr, $outputs:expr, $gas:expr, $fork:expr, $func:expr) => {
+        OpcodeInfo::new(
+            $opcode,
+            $name,
+            $desc,
+            $inputs,
+            $outputs,
+            $gas,
+            $fork,
+            $func as InstFn,
+        )
     };
 }
 
-pub static OPCODE_TABLE: Lazy<HashMap<u8, (&str, &str, InstFn)>> = Lazy::new(|| {
+pub static OPCODE_TABLE: Lazy<HashMap<u8, OpcodeInfo>> = Lazy::new(|| {
     let mut opcode_table = HashMap::new();
     let instructions = [
-        inst!(STOP, "STOP", "Halts execution", stop),
-        inst!(ADD, "ADD", "Adds two numbers", add),
-        inst!(MUL, "MUL", "Multiplies two numbers", mul),
-        inst!(SUB, "SUB", "Subtracts two numbers", sub),
-        inst!(DIV, "DIV", "Divides two numbers", div),
-        inst!(SDIV, "SDIV", "Divides two numbers", sign_div),
-        inst!(MOD, "MOD", "Modulo two numbers", modulo),
-        inst!(SMOD, "SMOD", "Modulo two numbers", sign_modulo),
-        inst!(ADDMOD, "ADDMOD", "Add modulo two numbers", add_mod),
-        inst!(MULMOD, "MULMOD", "Multiply modulo two numbers", mul_mod),
-        inst!(EXP, "EXP", "Exponentiation", exp),
-        inst!(SIGNEXTEND, "SIGNEXTEND", "Sign extend", sign_extend),
-        inst!(LT, "LT", "Less than", lt),
-        inst!(GT, "GT", "Greater than", gt),
-        inst!(SLT, "SLT", "Signed less than", slt),
-        inst!(SGT, "SGT", "Signed greater than", sgt),
-        inst!(EQ, "EQ", "Equal", eq),
-        inst!(ISZERO, "ISZERO", "Is zero", is_zero),
-        inst!(AND, "AND", "Bitwise AND", and),
-        inst!(OR, "OR", "Bitwise OR", or),
-        inst!(XOR, "XOR", "Bitwise XOR", xor),
-        inst!(NOT, "NOT", "Bitwise NOT", not),
-        inst!(BYTE, "BYTE", "Get byte", byte),
-        inst!(SHL, "SHL", "Shift left", shl),
-        inst!(SHR, "SHR", "Shift right", shr),
-        inst!(SAR, "SAR", "Shift right arithmetic", sar),
-        inst!(KECCAK256, "KECCAK256", "Keccak256", keccak256),
-        inst!(ADDRESS, "ADDRESS", "Address", address),
-        inst!(BALANCE, "BALANCE", "Balance", balance),
-        inst!(ORIGIN, "ORIGIN", "Origin", origin),
-        inst!(CALLER, "CALLER", "Caller", caller),
-        inst!(CALLVALUE, "CALLVALUE", "Call value", call_value),
+        inst!(STOP, "STOP", "Halts execution", 0, 0, 0, Frontier, stop),
+        inst!(ADD, "ADD", "Addition operation", 2, 1, 3, Frontier, add),
+        inst!(MUL, "MUL", "Multiplication operation", 2, 1, 5, Frontier, mul),
+        inst!(SUB, "SUB", "Subtraction operation", 2, 1, 3, Frontier, sub),
+        inst!(DIV, "DIV", "Integer division operation", 2, 1, 5, Frontier, div),
+        inst!(
+            SDIV,
+            "SDIV",
+            "Signed integer division operation (truncated)",
+            2,
+            1,
+            5,
+            Frontier,
+            sign_div
+        ),
+        inst!(MOD, "MOD", "Modulo remainder operation", 2, 1, 5, Frontier, modulo),
+        inst!(SMOD, "SMOD", "Signed modulo remainder operation", 2, 1, 5, Frontier, sign_modulo),
+        inst!(ADDMOD, "ADDMOD", "Modulo addition operation", 3, 1, 8, Frontier, add_mod),
+        inst!(MULMOD, "MULMOD", "Modulo multiplication operation", 3, 1, 8, Frontier, mul_mod),
+        inst!(EXP, "EXP", "Exponential operation", 2, 1, 10, Frontier, exp),
+        inst!(
+            SIGNEXTEND,
+            "SIGNEXTEND",
+            "Extend length of two's complement signed integer",
+            2,
+            1,
+            5,
+            Frontier,
+            sign_extend
+        ),
+        inst!(LT, "LT", "Less-than comparison", 2, 1, 3, Frontier, lt),
+        inst!(GT, "GT", "Greater-than comparison", 2, 1, 3, Frontier, gt),
+        inst!(SLT, "SLT", "Signed less-than comparison", 2, 1, 3, Frontier, slt),
+        inst!(SGT, "SGT", "Signed greater-than comparison", 2, 1, 3, Frontier, sgt),
+        inst!(EQ, "EQ", "Equality comparison", 2, 1, 3, Frontier, eq),
+        inst!(ISZERO, "ISZERO", "Is-zero comparison", 1, 1, 3, Frontier, is_zero),
+        inst!(AND, "AND", "Bitwise AND operation", 2, 1, 3, Frontier, and),
+        inst!(OR, "OR", "Bitwise OR operation", 2, 1, 3, Frontier, or),
+        inst!(XOR, "XOR", "Bitwise XOR operation", 2, 1, 3, Frontier, xor),
+        inst!(NOT, "NOT", "Bitwise NOT operation", 1, 1, 3, Frontier, not),
+        inst!(BYTE, "BYTE", "Retrieve single byte from word", 2, 1, 3, Frontier, byte),
+        inst!(SHL, "SHL", "Left shift operation", 2, 1, 3, Constantinople, shl),
+        inst!(SHR, "SHR", "Logical right shift operation", 2, 1, 3, Constantinople, shr),
+        inst!(
+            SAR,
+            "SAR",
+            "Arithmetic (signed) right shift operation",
+            2,
+            1,
+            3,
+            Constantinople,
+            sar
+        ),
+        inst!(KECCAK256, "KECCAK256", "Compute Keccak-256 hash", 2, 1, 30, Frontier, keccak256),
+        inst!(
+            ADDRESS,
+            "ADDRESS",
+            "Get address of currently executing account",
+            0,
+            1,
+            2,
+            Frontier,
+            address
+        ),
+        inst!(BALANCE, "BALANCE", "Get balance of the given account", 1, 1, 100, Frontier, balance),
+        inst!(ORIGIN, "ORIGIN", "Get execution origination address", 0, 1, 2, Frontier, origin),
+        inst!(CALLER, "CALLER", "Get caller address", 0, 1, 2, Frontier, caller),
+        inst!(
+            CALLVALUE,
+            "CALLVALUE",
+            "Get deposited value by the instruction/transaction responsible for this execution",
+            0,
+            1,
+            2,
+            Frontier,
+            call_value
+        ),
         inst!(
             CALLDATALOAD,
             "CALLDATALOAD",
-            "Call data load",
+            "Get input data of current environment",
+            1,
+            1,
+            3,
+            Frontier,
             call_data_load
         ),
         inst!(
             CALLDATASIZE,
             "CALLDATASIZE",
-            "Call data size",
+            "Get size of input data in current environment",
+            0,
+            1,
+            2,
+            Frontier,
             call_data_size
         ),
         inst!(
             CALLDATACOPY,
             "CALLDATACOPY",
-            "Call data copy",
+            "Copy input data in current environment to memory",
+            3,
+            0,
+            3,
+            Frontier,
             call_data_copy
         ),
-        inst!(CODESIZE, "CODESIZE", "Code size", code_size),
-        inst!(CODECOPY, "CODECOPY", "Code copy", code_copy),
-        inst!(EXTCODESIZE, "EXTCODESIZE", "Ext code size", ext_code_size),
-        inst!(EXTCODECOPY, "EXTCODECOPY", "Ext code copy", ext_code_copy),
+        inst!(
+            CODESIZE,
+            "CODESIZE",
+            "Get size of code running in current environment",
+            0,
+            1,
+            2,
+            Frontier,
+            code_size
+        ),
+        inst!(
+            CODECOPY,
+            "CODECOPY",
+            "Copy code running in current environment to memory",
+            3,
+            0,
+            3,
+            Frontier,
+            code_copy
+        ),
+        inst!(
+            EXTCODESIZE,
+            "EXTCODESIZE",
+            "Get size of an account's code",
+            1,
+            1,
+            100,
+            Frontier,
+            ext_code_size
+        ),
+        inst!(
+            EXTCODECOPY,
+            "EXTCODECOPY",
+            "Copy an account's code to memory",
+            4,
+            0,
+            100,
+            Frontier,
+            ext_code_copy
+        ),
         inst!(
             RETURNDATASIZE,
             "RETURNDATASIZE",
-            "Return data size",
+            "Get size of output data from the previous call",
+            0,
+            1,
+            2,
+            Byzantium,
             return_data_size
         ),
         inst!(
             RETURNDATACOPY,
             "RETURNDATACOPY",
-            "Return data copy",
+            "Copy output data from the previous call to memory",
+            3,
+            0,
+            3,
+            Byzantium,
             return_data_copy
         ),
-        inst!(EXTCODEHASH, "EXTCODEHASH", "Ext code hash", ext_code_hash),
-        inst!(BLOCKHASH, "BLOCKHASH", "Block hash", block_hash),
-        inst!(COINBASE, "COINBASE", "Coinbase", coinbase),
-        inst!(TIMESTAMP, "TIMESTAMP", "Timestamp", timestamp),
-        inst!(NUMBER, "NUMBER", "Number", block_number),
-        inst!(CHAINID, "CHAINID", "Chain ID", chain_id),
-        inst!(DIFFICULTY, "DIFFICULTY", "Difficulty", difficulty),
-        inst!(GAS, "GAS", "Gas", gas),
-        inst!(GASLIMIT, "GASLIMIT", "Gas limit", gas_limit),
-        inst!(GASPRICE, "GASPRICE", "Gas price", gas_price),
-        inst!(SELFBALANCE, "SELFBALANCE", "Self balance", self_balance),
-        inst!(BASEFEE, "BASEFEE", "Base fee", base_fee),
-        inst!(BLOBHASH, "BLOBHASH", "Blob hash", blob_hash),
-        inst!(BLOBHASHFEE, "BLOBHASHFEE", "Blob hash fee", blob_hash_fee),
-        inst!(POP, "POP", "Pop", pop),
-        inst!(MLOAD, "MLOAD", "Memory load", mload),
-        inst!(MSTORE, "MSTORE", "Memory store", mstore),
-        inst!(MSTORE8, "MSTORE8", "Memory store 8", mstore8),
-        inst!(SLOAD, "SLOAD", "Storage load", sload),
-        inst!(SSTORE, "SSTORE", "Storage store", sstore),
-        inst!(JUMP, "JUMP", "Jump", jump),
-        inst!(JUMPI, "JUMPI", "Jump if", jumpi),
-        inst!(PC, "PC", "Program counter", pc),
-        inst!(MSIZE, "MSIZE", "Memory size", msize),
-        inst!(JUMPDEST, "JUMPDEST", "Jump destination", jump_dest),
-        inst!(TLOAD, "TLOAD", "Tload", tload),
-        inst!(TSTORE, "TSTORE", "Tstore", tstore),
-        inst!(MCOPY, "MCOPY", "Memory copy", mcopy),
-        inst!(PUSH0, "PUSH0", "Push 0", push0),
-        inst!(PUSH1, "PUSH1", "Push 1", push::<1>),
-        inst!(PUSH2, "PUSH2", "Push 2", push::<2>),
-        inst!(PUSH3, "PUSH3", "Push 3", push::<3>),
-        inst!(PUSH4, "PUSH4", "Push 4", push::<4>),
-        inst!(PUSH5, "PUSH5", "Push 5", push::<5>),
-        inst!(PUSH6, "PUSH6", "Push 6", push::<6>),
-        inst!(PUSH7, "PUSH7", "Push 7", push::<7>),
-        inst!(PUSH8, "PUSH8", "Push 8", push::<8>),
-        inst!(PUSH9, "PUSH9", "Push 9", push::<9>),
-        inst!(PUSH10, "PUSH10", "Push 10", push::<10>),
-        inst!(PUSH11, "PUSH11", "Push 11", push::<11>),
-        inst!(PUSH12, "PUSH12", "Push 12", push::<12>),
-        inst!(PUSH13, "PUSH13", "Push 13", push::<13>),
-        inst!(PUSH14, "PUSH14", "Push 14", push::<14>),
-        inst!(PUSH15, "PUSH15", "Push 15", push::<15>),
-        inst!(PUSH16, "PUSH16", "Push 16", push::<16>),
-        inst!(PUSH17, "PUSH17", "Push 17", push::<17>),
-        inst!(PUSH18, "PUSH18", "Push 18", push::<18>),
-        inst!(PUSH19, "PUSH19", "Push 19", push::<19>),
-        inst!(PUSH20, "PUSH20", "Push 20", push::<20>),
-        inst!(PUSH21, "PUSH21", "Push 21", push::<21>),
-        inst!(PUSH22, "PUSH22", "Push 22", push::<22>),
-        inst!(PUSH23, "PUSH23", "Push 23", push::<23>),
-        inst!(PUSH24, "PUSH24", "Push 24", push::<24>),
-        inst!(PUSH25, "PUSH25", "Push 25", push::<25>),
-        inst!(PUSH26, "PUSH26", "Push 26", push::<26>),
-        inst!(PUSH27, "PUSH27", "Push 27", push::<27>),
-        inst!(PUSH28, "PUSH28", "Push 28", push::<28>),
-        inst!(PUSH29, "PUSH29", "Push 29", push::<29>),
-        inst!(PUSH30, "PUSH30", "Push 30", push::<30>),
-        inst!(PUSH31, "PUSH31", "Push 31", push::<31>),
-        inst!(PUSH32, "PUSH32", "Push 32", push::<32>),
-        inst!(DUP1, "DUP1", "Duplicate top stack item", dup::<1>),
-        inst!(DUP2, "DUP2", "Duplicate top stack item", dup::<2>),
-        inst!(DUP3, "DUP3", "Duplicate top stack item", dup::<3>),
-        inst!(DUP4, "DUP4", "Duplicate top stack item", dup::<4>),
-        inst!(DUP5, "DUP5", "Duplicate top stack item", dup::<5>),
-        inst!(DUP6, "DUP6", "Duplicate top stack item", dup::<6>),
-        inst!(DUP7, "DUP7", "Duplicate top stack item", dup::<7>),
-        inst!(DUP8, "DUP8", "Duplicate top stack item", dup::<8>),
-        inst!(DUP9, "DUP9", "Duplicate top stack item", dup::<9>),
-        inst!(DUP10, "DUP10", "Duplicate top stack item", dup::<10>),
-        inst!(DUP11, "DUP11", "Duplicate top stack item", dup::<11>),
-        inst!(DUP12, "DUP12", "Duplicate top stack item", dup::<12>),
-        inst!(DUP13, "DUP13", "Duplicate top stack item", dup::<13>),
-        inst!(DUP14, "DUP14", "Duplicate top stack item", dup::<14>),
-        inst!(DUP15, "DUP15", "Duplicate top stack item", dup::<15>),
-        inst!(DUP16, "DUP16", "Duplicate top stack item", dup::<16>),
-        inst!(SWAP1, "SWAP1", "Swap top stack item", swap::<1>),
-        inst!(SWAP2, "SWAP2", "Swap top stack item", swap::<2>),
-        inst!(SWAP3, "SWAP3", "Swap top stack item", swap::<3>),
-        inst!(SWAP4, "SWAP4", "Swap top stack item", swap::<4>),
-        inst!(SWAP5, "SWAP5", "Swap top stack item", swap::<5>),
-        inst!(SWAP6, "SWAP6", "Swap top stack item", swap::<6>),
-        inst!(SWAP7, "SWAP7", "Swap top stack item", swap::<7>),
-        inst!(SWAP8, "SWAP8", "Swap top stack item", swap::<8>),
-        inst!(SWAP9, "SWAP9", "Swap top stack item", swap::<9>),
-        inst!(SWAP10, "SWAP10", "Swap top stack item", swap::<10>),
-        inst!(SWAP11, "SWAP11", "Swap top stack item", swap::<11>),
-        inst!(SWAP12, "SWAP12", "Swap top stack item", swap::<12>),
-        inst!(SWAP13, "SWAP13", "Swap top stack item", swap::<13>),
-        inst!(SWAP14, "SWAP14", "Swap top stack item", swap::<14>),
-        inst!(SWAP15, "SWAP15", "Swap top stack item", swap::<15>),
-        inst!(SWAP16, "SWAP16", "Swap top stack item", swap::<16>),
-        inst!(LOG0, "LOG0", "Log", log::<0>),
-        inst!(LOG1, "LOG1", "Log", log::<1>),
-        inst!(LOG2, "LOG2", "Log", log::<2>),
-        inst!(LOG3, "LOG3", "Log", log::<3>),
-        inst!(LOG4, "LOG4", "Log", log::<4>),
-        inst!(CREATE, "CREATE", "Create contract", nop),
-        inst!(CALL, "CALL", "Call", nop),
-        inst!(CALLCODE, "CALLCODE", "Call code", nop),
-        inst!(RETURN, "RETURN", "Return", ret),
-        inst!(DELEGATECALL, "DELEGATECALL", "Delegate call", nop),
-        inst!(CREATE2, "CREATE2", "Create contract 2", nop),
-        inst!(STATICCALL, "STATICCALL", "Static call", nop),
-        inst!(REVERT, "REVERT", "Revert", revert),
-        inst!(INVALID, "INVALID", "Invalid", invalid),
-        inst!(SELFDESTRUCT, "SELFDESTRUCT", "Self destruct", nop),
+        inst!(
+            EXTCODEHASH,
+            "EXTCODEHASH",
+            "Get hash of an account's code",
+            1,
+            1,
+            100,
+            Constantinople,
+            ext_code_hash
+        ),
+        inst!(
+            BLOCKHASH,
+            "BLOCKHASH",
+            "Get the hash of one of the 256 most recent complete blocks",
+            1,
+            1,
+            20,
+            Frontier,
+            block_hash
+        ),
+        inst!(
+            COINBASE,
+            "COINBASE",
+            "Get the block's beneficiary address",
+            0,
+            1,
+            2,
+            Frontier,
+            coinbase
+        ),
+        inst!(TIMESTAMP, "TIMESTAMP", "Get the block's timestamp", 0, 1, 2, Frontier, timestamp),
+        inst!(NUMBER, "NUMBER", "Get the block's number", 0, 1, 2, Frontier, block_number),
+        inst!(CHAINID, "CHAINID", "Get the chain ID", 0, 1, 2, Istanbul, chain_id),
+        inst!(
+            DIFFICULTY,
+            "DIFFICULTY",
+            "Get the block's difficulty (PREVRANDAO since Paris)",
+            0,
+            1,
+            2,
+            Frontier,
+            difficulty
+        ),
+        inst!(GAS, "GAS", "Get the amount of available gas", 0, 1, 2, Frontier, gas),
+        inst!(GASLIMIT, "GASLIMIT", "Get the block's gas limit", 0, 1, 2, Frontier, gas_limit),
+        inst!(
+            GASPRICE,
+            "GASPRICE",
+            "Get price of gas in current environment",
+            0,
+            1,
+            2,
+            Frontier,
+            gas_price
+        ),
+        inst!(
+            SELFBALANCE,
+            "SELFBALANCE",
+            "Get balance of currently executing account",
+            0,
+            1,
+            5,
+            Istanbul,
+            self_balance
+        ),
+        inst!(BASEFEE, "BASEFEE", "Get the base fee", 0, 1, 2, London, base_fee),
+        inst!(BLOBHASH, "BLOBHASH", "Get versioned hashes", 1, 1, 3, Cancun, blob_hash),
+        inst!(
+            BLOBHASHFEE,
+            "BLOBHASHFEE",
+            "Get the current blob base fee",
+            0,
+            1,
+            2,
+            Cancun,
+            blob_hash_fee
+        ),
+        inst!(POP, "POP", "Remove item from stack", 1, 0, 2, Frontier, pop),
+        inst!(MLOAD, "MLOAD", "Load word from memory", 1, 1, 3, Frontier, mload),
+        inst!(MSTORE, "MSTORE", "Save word to memory", 2, 0, 3, Frontier, mstore),
+        inst!(MSTORE8, "MSTORE8", "Save byte to memory", 2, 0, 3, Frontier, mstore8),
+        inst!(SLOAD, "SLOAD", "Load word from storage", 1, 1, 100, Frontier, sload),
+        inst!(SSTORE, "SSTORE", "Save word to storage", 2, 0, 100, Frontier, sstore),
+        inst!(JUMP, "JUMP", "Alter the program counter", 1, 0, 8, Frontier, jump),
+        inst!(JUMPI, "JUMPI", "Conditionally alter the program counter", 2, 0, 10, Frontier, jumpi),
+        inst!(
+            PC,
+            "PC",
+            "Get the value of the program counter prior to the increment",
+            0,
+            1,
+            2,
+            Frontier,
+            pc
+        ),
+        inst!(MSIZE, "MSIZE", "Get the size of active memory in bytes", 0, 1, 2, Frontier, msize),
+        inst!(
+            JUMPDEST,
+            "JUMPDEST",
+            "Mark a valid destination for jumps",
+            0,
+            0,
+            1,
+            Frontier,
+            jump_dest
+        ),
+        inst!(TLOAD, "TLOAD", "Load word from transient storage", 1, 1, 100, Cancun, tload),
+        inst!(TSTORE, "TSTORE", "Save word to transient storage", 2, 0, 100, Cancun, tstore),
+        inst!(MCOPY, "MCOPY", "Copy memory areas", 3, 0, 3, Cancun, mcopy),
+        inst!(PUSH0, "PUSH0", "Place value 0 on stack", 0, 1, 2, Shanghai, push0),
+        inst!(PUSH1, "PUSH1", "Place 1 byte item on stack", 0, 1, 3, Frontier, push::<1>),
+        inst!(PUSH2, "PUSH2", "Place 2 byte item on stack", 0, 1, 3, Frontier, push::<2>),
+        inst!(PUSH3, "PUSH3", "Place 3 byte item on stack", 0, 1, 3, Frontier, push::<3>),
+        inst!(PUSH4, "PUSH4", "Place 4 byte item on stack", 0, 1, 3, Frontier, push::<4>),
+        inst!(PUSH5, "PUSH5", "Place 5 byte item on stack", 0, 1, 3, Frontier, push::<5>),
+        inst!(PUSH6, "PUSH6", "Place 6 byte item on stack", 0, 1, 3, Frontier, push::<6>),
+        inst!(PUSH7, "PUSH7", "Place 7 byte item on stack", 0, 1, 3, Frontier, push::<7>),
+        inst!(PUSH8, "PUSH8", "Place 8 byte item on stack", 0, 1, 3, Frontier, push::<8>),
+        inst!(PUSH9, "PUSH9", "Place 9 byte item on stack", 0, 1, 3, Frontier, push::<9>),
+        inst!(PUSH10, "PUSH10", "Place 10 byte item on stack", 0, 1, 3, Frontier, push::<10>),
+        inst!(PUSH11, "PUSH11", "Place 11 byte item on stack", 0, 1, 3, Frontier, push::<11>),
+        inst!(PUSH12, "PUSH12", "Place 12 byte item on stack", 0, 1, 3, Frontier, push::<12>),
+        inst!(PUSH13, "PUSH13", "Place 13 byte item on stack", 0, 1, 3, Frontier, push::<13>),
+        inst!(PUSH14, "PUSH14", "Place 14 byte item on stack", 0, 1, 3, Frontier, push::<14>),
+        inst!(PUSH15, "PUSH15", "Place 15 byte item on stack", 0, 1, 3, Frontier, push::<15>),
+        inst!(PUSH16, "PUSH16", "Place 16 byte item on stack", 0, 1, 3, Frontier, push::<16>),
+        inst!(PUSH17, "PUSH17", "Place 17 byte item on stack", 0, 1, 3, Frontier, push::<17>),
+        inst!(PUSH18, "PUSH18", "Place 18 byte item on stack", 0, 1, 3, Frontier, push::<18>),
+        inst!(PUSH19, "PUSH19", "Place 19 byte item on stack", 0, 1, 3, Frontier, push::<19>),
+        inst!(PUSH20, "PUSH20", "Place 20 byte item on stack", 0, 1, 3, Frontier, push::<20>),
+        inst!(PUSH21, "PUSH21", "Place 21 byte item on stack", 0, 1, 3, Frontier, push::<21>),
+        inst!(PUSH22, "PUSH22", "Place 22 byte item on stack", 0, 1, 3, Frontier, push::<22>),
+        inst!(PUSH23, "PUSH23", "Place 23 byte item on stack", 0, 1, 3, Frontier, push::<23>),
+        inst!(PUSH24, "PUSH24", "Place 24 byte item on stack", 0, 1, 3, Frontier, push::<24>),
+        inst!(PUSH25, "PUSH25", "Place 25 byte item on stack", 0, 1, 3, Frontier, push::<25>),
+        inst!(PUSH26, "PUSH26", "Place 26 byte item on stack", 0, 1, 3, Frontier, push::<26>),
+        inst!(PUSH27, "PUSH27", "Place 27 byte item on stack", 0, 1, 3, Frontier, push::<27>),
+        inst!(PUSH28, "PUSH28", "Place 28 byte item on stack", 0, 1, 3, Frontier, push::<28>),
+        inst!(PUSH29, "PUSH29", "Place 29 byte item on stack", 0, 1, 3, Frontier, push::<29>),
+        inst!(PUSH30, "PUSH30", "Place 30 byte item on stack", 0, 1, 3, Frontier, push::<30>),
+        inst!(PUSH31, "PUSH31", "Place 31 byte item on stack", 0, 1, 3, Frontier, push::<31>),
+        inst!(PUSH32, "PUSH32", "Place 32 byte item on stack", 0, 1, 3, Frontier, push::<32>),
+        inst!(DUP1, "DUP1", "Duplicate 1st stack item", 1, 2, 3, Frontier, dup::<1>),
+        inst!(DUP2, "DUP2", "Duplicate 2nd stack item", 2, 3, 3, Frontier, dup::<2>),
+        inst!(DUP3, "DUP3", "Duplicate 3rd stack item", 3, 4, 3, Frontier, dup::<3>),
+        inst!(DUP4, "DUP4", "Duplicate 4th stack item", 4, 5, 3, Frontier, dup::<4>),
+        inst!(DUP5, "DUP5", "Duplicate 5th stack item", 5, 6, 3, Frontier, dup::<5>),
+        inst!(DUP6, "DUP6", "Duplicate 6th stack item", 6, 7, 3, Frontier, dup::<6>),
+        inst!(DUP7, "DUP7", "Duplicate 7th stack item", 7, 8, 3, Frontier, dup::<7>),
+        inst!(DUP8, "DUP8", "Duplicate 8th stack item", 8, 9, 3, Frontier, dup::<8>),
+        inst!(DUP9, "DUP9", "Duplicate 9th stack item", 9, 10, 3, Frontier, dup::<9>),
+        inst!(DUP10, "DUP10", "Duplicate 10th stack item", 10, 11, 3, Frontier, dup::<10>),
+        inst!(DUP11, "DUP11", "Duplicate 11th stack item", 11, 12, 3, Frontier, dup::<11>),
+        inst!(DUP12, "DUP12", "Duplicate 12th stack item", 12, 13, 3, Frontier, dup::<12>),
+        inst!(DUP13, "DUP13", "Duplicate 13th stack item", 13, 14, 3, Frontier, dup::<13>),
+        inst!(DUP14, "DUP14", "Duplicate 14th stack item", 14, 15, 3, Frontier, dup::<14>),
+        inst!(DUP15, "DUP15", "Duplicate 15th stack item", 15, 16, 3, Frontier, dup::<15>),
+        inst!(DUP16, "DUP16", "Duplicate 16th stack item", 16, 17, 3, Frontier, dup::<16>),
+        inst!(SWAP1, "SWAP1", "Exchange 1st and 2nd stack items", 2, 2, 3, Frontier, swap::<1>),
+        inst!(SWAP2, "SWAP2", "Exchange 1st and 3rd stack items", 3, 3, 3, Frontier, swap::<2>),
+        inst!(SWAP3, "SWAP3", "Exchange 1st and 4th stack items", 4, 4, 3, Frontier, swap::<3>),
+        inst!(SWAP4, "SWAP4", "Exchange 1st and 5th stack items", 5, 5, 3, Frontier, swap::<4>),
+        inst!(SWAP5, "SWAP5", "Exchange 1st and 6th stack items", 6, 6, 3, Frontier, swap::<5>),
+        inst!(SWAP6, "SWAP6", "Exchange 1st and 7th stack items", 7, 7, 3, Frontier, swap::<6>),
+        inst!(SWAP7, "SWAP7", "Exchange 1st and 8th stack items", 8, 8, 3, Frontier, swap::<7>),
+        inst!(SWAP8, "SWAP8", "Exchange 1st and 9th stack items", 9, 9, 3, Frontier, swap::<8>),
+        inst!(SWAP9, "SWAP9", "Exchange 1st and 10th stack items", 10, 10, 3, Frontier, swap::<9>),
+        inst!(
+            SWAP10,
+            "SWAP10",
+            "Exchange 1st and 11th stack items",
+            11,
+            11,
+            3,
+            Frontier,
+            swap::<10>
+        ),
+        inst!(
+            SWAP11,
+            "SWAP11",
+            "Exchange 1st and 12th stack items",
+            12,
+            12,
+            3,
+            Frontier,
+            swap::<11>
+        ),
+        inst!(
+            SWAP12,
+            "SWAP12",
+            "Exchange 1st and 13th stack items",
+            13,
+            13,
+            3,
+            Frontier,
+            swap::<12>
+        ),
+        inst!(
+            SWAP13,
+            "SWAP13",
+            "Exchange 1st and 14th stack items",
+            14,
+            14,
+            3,
+            Frontier,
+            swap::<13>
+        ),
+        inst!(
+            SWAP14,
+            "SWAP14",
+            "Exchange 1st and 15th stack items",
+            15,
+            15,
+            3,
+            Frontier,
+            swap::<14>
+        ),
+        inst!(
+            SWAP15,
+            "SWAP15",
+            "Exchange 1st and 16th stack items",
+            16,
+            16,
+            3,
+            Frontier,
+            swap::<15>
+        ),
+        inst!(
+            SWAP16,
+            "SWAP16",
+            "Exchange 1st and 17th stack items",
+            17,
+            17,
+            3,
+            Frontier,
+            swap::<16>
+        ),
+        inst!(LOG0, "LOG0", "Append log record with 0 topics", 2, 0, 375, Frontier, log::<0>),
+        inst!(LOG1, "LOG1", "Append log record with 1 topic", 3, 0, 750, Frontier, log::<1>),
+        inst!(LOG2, "LOG2", "Append log record with 2 topics", 4, 0, 1125, Frontier, log::<2>),
+        inst!(LOG3, "LOG3", "Append log record with 3 topics", 5, 0, 1500, Frontier, log::<3>),
+        inst!(LOG4, "LOG4", "Append log record with 4 topics", 6, 0, 1875, Frontier, log::<4>),
+        inst!(
+            CREATE,
+            "CREATE",
+            "Create a new account with associated code",
+            3,
+            1,
+            32000,
+            Frontier,
+            nop
+        ),
+        inst!(CALL, "CALL", "Message-call into an account", 7, 1, 100, Frontier, nop),
+        inst!(
+            CALLCODE,
+            "CALLCODE",
+            "Message-call into this account with an alternative account's code",
+            7,
+            1,
+            100,
+            Frontier,
+            nop
+        ),
+        inst!(RETURN, "RETURN", "Halt execution returning output data", 2, 0, 0, Frontier, ret),
+        inst!(
+            DELEGATECALL,
+            "DELEGATECALL",
+            "Message-call into this account with an alternative account's code, persisting the current values for sender and value",
+            6,
+            1,
+            100,
+            Homestead,
+            nop
+        ),
+        inst!(
+            CREATE2,
+            "CREATE2",
+            "Create a new account with associated code at a predictable address",
+            4,
+            1,
+            32000,
+            Constantinople,
+            nop
+        ),
+        inst!(
+            STATICCALL,
+            "STATICCALL",
+            "Static message-call into an account",
+            6,
+            1,
+            100,
+            Byzantium,
+            nop
+        ),
+        inst!(
+            REVERT,
+            "REVERT",
+            "Halt execution reverting state changes but returning data and remaining gas",
+            2,
+            0,
+            0,
+            Byzantium,
+            revert
+        ),
+        inst!(INVALID, "INVALID", "Designated invalid instruction", 0, 0, 0, Frontier, invalid),
+        inst!(
+            SELFDESTRUCT,
+            "SELFDESTRUCT",
+            "Halt execution and register account for later deletion",
+            1,
+            0,
+            5000,
+            Frontier,
+            nop
+        ),
+
     ];
 
-    for &(opcode, name, description, function) in &instructions {
-        opcode_table.insert(opcode, (name, description, function));
+    for info in instructions {
+        opcode_table.insert(info.opcode, info);
     }
     opcode_table
 });
+
+/// 构建给定硬分叉下的指令表
+pub fn make_jump_table(fork: Fork) -> JumpTable {
+    let mut jump_table: JumpTable = [None; 256];
+    for info in OPCODE_TABLE.values() {
+        if info.is_enabled(fork) {
+            jump_table[info.opcode as usize] = Some(info.inst_fn);
+        }
+    }
+    jump_table
+}
+
+/// SSTORE 的 gas 完全由指令自己计算，因为剩余 gas 不足 2300 时要在扣费前失败
+pub fn make_gas_table() -> GasTable {
+    let mut gas_table: GasTable = [0; 256];
+    for info in OPCODE_TABLE.values() {
+        gas_table[info.opcode as usize] = info.static_gas;
+    }
+    gas_table[SSTORE as usize] = 0;
+    gas_table
+}
+
+pub fn make_stack_table() -> StackTable {
+    let mut stack_table: StackTable = [(0, 0); 256];
+    for info in OPCODE_TABLE.values() {
+        stack_table[info.opcode as usize] = (info.inputs, info.outputs);
+    }
+    stack_table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcode_info() {
+        let push32 = OPCODE_TABLE[&PUSH32];
+        assert_eq!(push32.immediate_size, 32);
+        assert_eq!(push32.stack_delta(), 1);
+
+        let swap16 = OPCODE_TABLE[&SWAP16];
+        assert_eq!((swap16.inputs, swap16.outputs), (17, 17));
+
+        assert!(OPCODE_TABLE[&JUMP].terminates && OPCODE_TABLE[&JUMP].jumps);
+        assert!(!OPCODE_TABLE[&JUMPI].terminates && OPCODE_TABLE[&JUMPI].ends_block());
+
+        assert!(!OPCODE_TABLE[&PUSH0].is_enabled(Fork::London));
+        assert!(OPCODE_TABLE[&PUSH0].is_enabled(Fork::Shanghai));
+    }
+
+    #[test]
+    fn test_make_jump_table() {
+        let london = make_jump_table(Fork::London);
+        assert!(london[ADD as usize].is_some());
+        assert!(london[PUSH0 as usize].is_none());
+        assert!(london[0x0c].is_none());
+        assert!(make_jump_table(Fork::Shanghai)[PUSH0 as usize].is_some());
+    }
+}
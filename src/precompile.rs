@@ -0,0 +1,382 @@
+//! 预编译合约，地址 0x01 到 0x0a
+//!
+//! 0x0a (KZG point evaluation) 需要 trusted setup，这里没有实现，调用总是失败
+
+use std::cmp::max;
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use bn::Group;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha2::Digest;
+
+use crate::{error::EVMError, fork::Fork, gas};
+
+/// 给定硬分叉中的预编译合约地址
+pub fn addresses(fork: Fork) -> impl Iterator<Item = Address> {
+    let count = if fork >= Fork::Cancun { 10 } else { 9 };
+    (1..=count).map(Address::with_last_byte)
+}
+
+pub fn is_precompile(address: Address, fork: Fork) -> bool {
+    address[..19].iter().all(|&b| b == 0) && addresses(fork).any(|a| a == address)
+}
+
+/// 执行预编译合约，返回消耗的 gas 和输出
+pub fn run(address: Address, input: &[u8], gas_limit: u64) -> Result<(u64, Bytes), EVMError> {
+    let words = gas::words(input.len() as u64);
+    let (cost, output) = match address[19] {
+        1 => (3000, ec_recover(input)),
+        2 => (60 + 12 * words, sha2::Sha256::digest(input).to_vec()),
+        3 => (600 + 120 * words, ripemd160(input)),
+        4 => (15 + 3 * words, input.to_vec()),
+        5 => return modexp(input, gas_limit),
+        6 => (150, bn_add(input)?),
+        7 => (6000, bn_mul(input)?),
+        8 => {
+            let cost = 45000 + 34000 * (input.len() / 192) as u64;
+            if cost > gas_limit {
+                return Err(EVMError::OutOfGas);
+            }
+            (cost, bn_pairing(input)?)
+        }
+        9 => return blake2f(input, gas_limit),
+        _ => return Err(EVMError::PrecompileFailure),
+    };
+    if cost > gas_limit {
+        return Err(EVMError::OutOfGas);
+    }
+    Ok((cost, output.into()))
+}
+
+/// 从签名恢复地址，签名无效时返回 None
+pub fn ecrecover(sig: &[u8; 64], mut recid: u8, msg: &B256) -> Option<Address> {
+    let mut sig = Signature::from_slice(sig).ok()?;
+    // ecrecover 接受 s 在高半区的签名
+    if let Some(normalized) = sig.normalize_s() {
+        sig = normalized;
+        recid ^= 1;
+    }
+    let recid = RecoveryId::from_byte(recid)?;
+    let key = VerifyingKey::recover_from_prehash(msg.as_slice(), &sig, recid).ok()?;
+    let hash = keccak256(&key.to_encoded_point(false).as_bytes()[1..]);
+    Some(Address::from_slice(&hash[12..]))
+}
+
+/// 输入不足的部分补 0
+fn right_pad<const N: usize>(input: &[u8]) -> [u8; N] {
+    let mut padded = [0u8; N];
+    let len = input.len().min(N);
+    padded[..len].copy_from_slice(&input[..len]);
+    padded
+}
+
+fn ec_recover(input: &[u8]) -> Vec<u8> {
+    let input = right_pad::<128>(input);
+    // v 必须是 27 或 28，否则返回空输出
+    if !(input[32..63].iter().all(|&b| b == 0) && matches!(input[63], 27 | 28)) {
+        return Vec::new();
+    }
+    let msg = B256::from_slice(&input[..32]);
+    let sig: [u8; 64] = input[64..].try_into().unwrap();
+    match ecrecover(&sig, input[63] - 27, &msg) {
+        Some(address) => address.into_word().to_vec(),
+        None => Vec::new(),
+    }
+}
+
+fn ripemd160(input: &[u8]) -> Vec<u8> {
+    let mut output = vec![0u8; 12];
+    output.extend_from_slice(&ripemd::Ripemd160::digest(input));
+    output
+}
+
+/// EIP-198，gas 按 EIP-2565 计算
+fn modexp(input: &[u8], gas_limit: u64) -> Result<(u64, Bytes), EVMError> {
+    let read_len = |offset: usize| {
+        let len = U256::from_be_bytes(right_pad::<32>(input.get(offset..).unwrap_or_default()));
+        usize::try_from(len).map_err(|_| EVMError::PrecompileFailure)
+    };
+    let (base_len, exp_len, mod_len) = (read_len(0)?, read_len(32)?, read_len(64)?);
+    if base_len == 0 && mod_len == 0 {
+        return match gas_limit < 200 {
+            true => Err(EVMError::OutOfGas),
+            false => Ok((200, Bytes::new())),
+        };
+    }
+    let data = input.get(96..).unwrap_or_default();
+
+    // 指数的前 32 字节决定迭代次数
+    let exp_head_len = exp_len.min(32);
+    let mut exp_head = [0u8; 32];
+    for (i, byte) in exp_head[32 - exp_head_len..].iter_mut().enumerate() {
+        *byte = data.get(base_len.saturating_add(i)).copied().unwrap_or(0);
+    }
+    let exp_head = U256::from_be_bytes(exp_head);
+    let iterations = match exp_len <= 32 {
+        true if exp_head.is_zero() => 0,
+        true => exp_head.bit_len() as u64 - 1,
+        false => (8u64.saturating_mul(exp_len as u64 - 32))
+            .saturating_add(max(1, exp_head.bit_len() as u64) - 1),
+    };
+    // EIP-2565 按 8 字节为一个字计算
+    let words = U256::from((max(base_len, mod_len) as u64).div_ceil(8));
+    let cost = words * words * U256::from(max(iterations, 1)) / U256::from(3);
+    let cost = max(200, cost.saturating_to::<u64>());
+    if cost > gas_limit {
+        return Err(EVMError::OutOfGas);
+    }
+
+    let read = |offset: usize, len: usize| {
+        let mut buf = vec![0u8; len];
+        if let Some(src) = data.get(offset..) {
+            let n = src.len().min(len);
+            buf[..n].copy_from_slice(&src[..n]);
+        }
+        buf
+    };
+    let base = read(0, base_len);
+    let exponent = read(base_len, exp_len);
+    let modulus = read(base_len + exp_len, mod_len);
+    let result = aurora_engine_modexp::modexp(&base, &exponent, &modulus);
+
+    let mut output = vec![0u8; mod_len];
+    let n = result.len().min(mod_len);
+    output[mod_len - n..].copy_from_slice(&result[result.len() - n..]);
+    Ok((cost, output.into()))
+}
+
+fn read_fq(input: &[u8]) -> Result<bn::Fq, EVMError> {
+    bn::Fq::from_slice(input).map_err(|_| EVMError::PrecompileFailure)
+}
+
+/// 读取 G1 点，(0, 0) 表示无穷远点
+fn read_g1(input: &[u8]) -> Result<bn::G1, EVMError> {
+    let (x, y) = (read_fq(&input[..32])?, read_fq(&input[32..64])?);
+    if x.is_zero() && y.is_zero() {
+        return Ok(bn::G1::zero());
+    }
+    bn::AffineG1::new(x, y)
+        .map(Into::into)
+        .map_err(|_| EVMError::PrecompileFailure)
+}
+
+fn encode_g1(point: bn::G1) -> Vec<u8> {
+    let mut output = vec![0u8; 64];
+    if let Some(point) = bn::AffineG1::from_jacobian(point) {
+        point.x().to_big_endian(&mut output[..32]).unwrap();
+        point.y().to_big_endian(&mut output[32..]).unwrap();
+    }
+    output
+}
+
+fn bn_add(input: &[u8]) -> Result<Vec<u8>, EVMError> {
+    let input = right_pad::<128>(input);
+    Ok(encode_g1(read_g1(&input[..64])? + read_g1(&input[64..])?))
+}
+
+fn bn_mul(input: &[u8]) -> Result<Vec<u8>, EVMError> {
+    let input = right_pad::<96>(input);
+    let scalar = bn::Fr::from_slice(&input[64..]).map_err(|_| EVMError::PrecompileFailure)?;
+    Ok(encode_g1(read_g1(&input[..64])? * scalar))
+}
+
+fn bn_pairing(input: &[u8]) -> Result<Vec<u8>, EVMError> {
+    if !input.len().is_multiple_of(192) {
+        return Err(EVMError::PrecompileFailure);
+    }
+    let mut product = bn::Gt::one();
+    for chunk in input.chunks(192) {
+        let a = read_g1(&chunk[..64])?;
+        // G2 点的坐标先虚部后实部
+        let bx = bn::Fq2::new(read_fq(&chunk[96..128])?, read_fq(&chunk[64..96])?);
+        let by = bn::Fq2::new(read_fq(&chunk[160..192])?, read_fq(&chunk[128..160])?);
+        let b = match bx.is_zero() && by.is_zero() {
+            true => bn::G2::zero(),
+            false => bn::AffineG2::new(bx, by)
+                .map_err(|_| EVMError::PrecompileFailure)?
+                .into(),
+        };
+        product = product * bn::pairing(a, b);
+    }
+    Ok(U256::from(product == bn::Gt::one())
+        .to_be_bytes::<32>()
+        .to_vec())
+}
+
+/// EIP-152，输入为 rounds(4) h(64) m(128) t(16) f(1)
+fn blake2f(input: &[u8], gas_limit: u64) -> Result<(u64, Bytes), EVMError> {
+    if input.len() != 213 || input[212] > 1 {
+        return Err(EVMError::PrecompileFailure);
+    }
+    let rounds = u32::from_be_bytes(input[..4].try_into().unwrap());
+    if rounds as u64 > gas_limit {
+        return Err(EVMError::OutOfGas);
+    }
+    let word = |i: usize| u64::from_le_bytes(input[i..i + 8].try_into().unwrap());
+    let mut h: [u64; 8] = std::array::from_fn(|i| word(4 + i * 8));
+    let m: [u64; 16] = std::array::from_fn(|i| word(68 + i * 8));
+    let t = [word(196), word(204)];
+    blake2_compress(rounds as usize, &mut h, &m, t, input[212] == 1);
+    let output: Vec<u8> = h.iter().flat_map(|w| w.to_le_bytes()).collect();
+    Ok((rounds as u64, output.into()))
+}
+
+const BLAKE2B_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+fn blake2_compress(rounds: usize, h: &mut [u64; 8], m: &[u64; 16], t: [u64; 2], last: bool) {
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&BLAKE2B_IV);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if last {
+        v[14] = !v[14];
+    }
+
+    let mut g = |a: usize, b: usize, c: usize, d: usize, x: u64, y: u64| {
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+        v[d] = (v[d] ^ v[a]).rotate_right(32);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(24);
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(63);
+    };
+    for round in 0..rounds {
+        let s = &SIGMA[round % 10];
+        g(0, 4, 8, 12, m[s[0]], m[s[1]]);
+        g(1, 5, 9, 13, m[s[2]], m[s[3]]);
+        g(2, 6, 10, 14, m[s[4]], m[s[5]]);
+        g(3, 7, 11, 15, m[s[6]], m[s[7]]);
+        g(0, 5, 10, 15, m[s[8]], m[s[9]]);
+        g(1, 6, 11, 12, m[s[10]], m[s[11]]);
+        g(2, 7, 8, 13, m[s[12]], m[s[13]]);
+        g(3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(address: u8, input: &str) -> String {
+        let input = hex::decode(input).unwrap();
+        let (_, output) = run(Address::with_last_byte(address), &input, u64::MAX).unwrap();
+        hex::encode(output)
+    }
+
+    #[test]
+    fn test_precompiles() {
+        assert_eq!(
+            call(2, ""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            call(3, ""),
+            "0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31"
+        );
+        assert_eq!(call(4, "0102"), "0102");
+        // 3 ** 5 % 7 = 5
+        let modexp = format!("{:064x}{:064x}{:064x}030507", 1, 1, 1);
+        assert_eq!(call(5, &modexp), "05");
+        // EIP-2565 的 eip_example1：3 ** (p - 1) % p = 1，p 为 secp256k1 的域
+        let modexp = format!(
+            "{:064x}{:064x}{:064x}03{}{}",
+            1,
+            32,
+            32,
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e",
+            "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f"
+        );
+        let (gas, output) = run(
+            Address::with_last_byte(5),
+            &hex::decode(modexp).unwrap(),
+            u64::MAX,
+        )
+        .unwrap();
+        assert_eq!((gas, hex::encode(output)), (1360, format!("{:064x}", 1)));
+        // 1024 字节的底数和模数，指数为 2：128 个 8 字节的字，128 * 128 / 3 = 5461
+        let modexp = format!(
+            "{:064x}{:064x}{:064x}{}02{}",
+            1024,
+            1,
+            1024,
+            "00".repeat(1024),
+            "00".repeat(1024)
+        );
+        let (gas, _) = run(
+            Address::with_last_byte(5),
+            &hex::decode(modexp).unwrap(),
+            u64::MAX,
+        )
+        .unwrap();
+        assert_eq!(gas, 5461);
+        // G1 生成元 (1, 2) 加上自己等于乘以 2
+        let g = format!("{:064x}{:064x}", 1, 2);
+        assert_eq!(
+            call(6, &format!("{}{}", g, g)),
+            call(7, &format!("{}{:064x}", g, 2))
+        );
+        assert_eq!(call(8, ""), format!("{:064x}", 1));
+    }
+
+    #[test]
+    fn test_ecrecover() {
+        // 私钥为 1 的地址签名的消息
+        let key = k256::ecdsa::SigningKey::from_slice(&U256::from(1).to_be_bytes::<32>()).unwrap();
+        let msg = keccak256(b"hello");
+        let (sig, recid) = key.sign_prehash_recoverable(msg.as_slice()).unwrap();
+        let mut input = msg.to_vec();
+        input.extend_from_slice(&U256::from(27 + recid.to_byte()).to_be_bytes::<32>());
+        input.extend_from_slice(&sig.to_bytes());
+        assert_eq!(
+            call(1, &hex::encode(input)),
+            "0000000000000000000000007e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+        // v 不是 27/28 时输出为空
+        assert_eq!(call(1, ""), "");
+    }
+
+    #[test]
+    fn test_blake2f() {
+        // EIP-152 的测试向量 5，即 BLAKE2b-512("abc")
+        let input = format!(
+            "0000000c{}{:0<256}{}{}01",
+            "48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b",
+            "616263",
+            "0300000000000000",
+            "0000000000000000"
+        );
+        assert_eq!(
+            call(9, &input),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! `eth_getProof` 格式的账户和存储证明
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::error::EVMError;
+use crate::genesis::quantity;
+use crate::state::KECCAK_EMPTY;
+use crate::trie::{encode_account, storage_key, verify_proof, Trie, EMPTY_ROOT};
+
+/// `eth_getProof` 的返回值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: Address,
+    pub balance: U256,
+    pub code_hash: B256,
+    #[serde(with = "quantity")]
+    pub nonce: u64,
+    pub storage_hash: B256,
+    /// 状态树中从根到账户的节点
+    pub account_proof: Vec<Bytes>,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageProof {
+    pub key: U256,
+    pub value: U256,
+    /// 存储树中从根到槽位的节点
+    pub proof: Vec<Bytes>,
+}
+
+impl StorageProof {
+    /// 由账户的存储树生成证明
+    pub fn new(trie: Option<&Trie>, key: U256, value: U256) -> Self {
+        let proof = trie.map_or_else(Vec::new, |trie| trie.proof(storage_key(key).as_slice()));
+        StorageProof { key, value, proof }
+    }
+}
+
+/// 不存在的账户证明为空值，字段为默认值
+pub fn verify_account_proof(state_root: B256, proof: &AccountProof) -> Result<(), EVMError> {
+    let value = verify_proof(
+        state_root,
+        keccak256(proof.address).as_slice(),
+        &proof.account_proof,
+    )?;
+    let expected = encode_account(
+        proof.nonce,
+        proof.balance,
+        proof.storage_hash,
+        proof.code_hash,
+    );
+    let empty = proof.nonce == 0
+        && proof.balance.is_zero()
+        && proof.storage_hash == EMPTY_ROOT
+        && proof.code_hash == KECCAK_EMPTY;
+    match value {
+        Some(value) if value == expected => {}
+        None if empty => {}
+        _ => return Err(EVMError::InvalidProof(format!("account {}", proof.address))),
+    }
+
+    for storage in &proof.storage_proof {
+        let value = verify_proof(
+            proof.storage_hash,
+            storage_key(storage.key).as_slice(),
+            &storage.proof,
+        )?;
+        let expected = (!storage.value.is_zero()).then(|| alloy_rlp::encode(storage.value));
+        if value != expected {
+            return Err(EVMError::InvalidProof(format!(
+                "storage slot {:#x}",
+                storage.key
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{InMemoryStateDB, StateDB};
+    use serde_json::Value;
+
+    #[test]
+    fn test_account_proof() {
+        let a = Address::repeat_byte(1);
+        let mut state = InMemoryStateDB::new();
+        state.set_code(a, Bytes::from_static(&[0x60, 0x00]));
+        state.add_balance(a, U256::from(100));
+        for slot in 0..20u64 {
+            state.set_state(a, U256::from(slot), U256::from(slot * 3));
+        }
+        for i in 2..40u8 {
+            state.add_balance(Address::repeat_byte(i), U256::from(i));
+        }
+        state.commit();
+        let root = state.state_root();
+
+        let slots = [U256::from(1), U256::ZERO, U256::from(19), U256::from(1000)];
+        let proof = state.get_proof(a, &slots).unwrap();
+        assert_eq!(proof.balance, U256::from(100));
+        assert_eq!(proof.code_hash, keccak256([0x60, 0x00]));
+        assert_eq!(proof.storage_proof[2].value, U256::from(57));
+        verify_account_proof(root, &proof).unwrap();
+
+        // 不存在的账户
+        let missing = state.get_proof(Address::repeat_byte(0xff), &slots).unwrap();
+        assert_eq!(missing.storage_hash, EMPTY_ROOT);
+        verify_account_proof(root, &missing).unwrap();
+
+        let mut forged = proof.clone();
+        forged.balance += U256::from(1);
+        assert!(verify_account_proof(root, &forged).is_err());
+        let mut forged = proof.clone();
+        forged.storage_proof[0].value = U256::from(4);
+        assert!(verify_account_proof(root, &forged).is_err());
+
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json["nonce"], Value::from("0x0"));
+        assert_eq!(json["balance"], Value::from("0x64"));
+        assert!(json["accountProof"].is_array());
+        assert_eq!(json["storageProof"][2]["value"], Value::from("0x39"));
+        let decoded: AccountProof = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, proof);
+    }
+}
@@ -0,0 +1,198 @@
+//! 交易回执、日志布隆过滤器和回执树根
+
+use alloy_primitives::{Address, Bloom, BloomInput, Bytes, B256, U256};
+use alloy_rlp::Encodable;
+
+use crate::block::ordered_trie_root;
+use crate::transaction::{ExecutionResult, LEGACY_TX_TYPE};
+use crate::trie::encode_list;
+
+/// 区块中的一条日志
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<U256>,
+    pub data: Bytes,
+    pub transaction_index: u64,
+    /// 在整个区块中的下标
+    pub log_index: u64,
+}
+
+impl Log {
+    /// 共识编码 [address, topics, data]，不包括下标
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.address.encode(&mut payload);
+        let topics: Vec<B256> = self.topics.iter().map(|&topic| topic.into()).collect();
+        topics.encode(&mut payload);
+        self.data.encode(&mut payload);
+        out.extend(encode_list(&payload));
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Receipt {
+    pub tx_type: u8,
+    /// 最外层调用是否成功 (EIP-658)
+    pub success: bool,
+    /// 区块中到这笔交易为止消耗的 gas
+    pub cumulative_gas_used: u64,
+    pub gas_used: u64,
+    pub transaction_index: u64,
+    pub contract_address: Option<Address>,
+    pub logs: Vec<Log>,
+    pub logs_bloom: Bloom,
+}
+
+impl Receipt {
+    /// 由交易的执行结果生成回执，`first_log_index` 是区块中前面交易的日志数
+    pub fn new(
+        tx_type: u8,
+        result: &ExecutionResult,
+        cumulative_gas_used: u64,
+        transaction_index: u64,
+        first_log_index: u64,
+    ) -> Self {
+        let logs: Vec<Log> = result
+            .logs
+            .iter()
+            .zip(first_log_index..)
+            .map(|((address, topics, data), log_index)| Log {
+                address: *address,
+                topics: topics.clone(),
+                data: data.clone().into(),
+                transaction_index,
+                log_index,
+            })
+            .collect();
+        Receipt {
+            tx_type,
+            success: result.is_success(),
+            cumulative_gas_used,
+            gas_used: result.gas_used,
+            transaction_index,
+            contract_address: result.contract_address,
+            logs_bloom: logs_bloom(&logs),
+            logs,
+        }
+    }
+
+    /// 共识编码，typed 交易为 `type || rlp([status, cumulative_gas_used, bloom, logs])`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        self.success.encode(&mut payload);
+        self.cumulative_gas_used.encode(&mut payload);
+        self.logs_bloom.encode(&mut payload);
+        let mut logs = Vec::new();
+        for log in &self.logs {
+            log.encode(&mut logs);
+        }
+        payload.extend(encode_list(&logs));
+
+        let mut out = Vec::with_capacity(payload.len() + 4);
+        if self.tx_type != LEGACY_TX_TYPE {
+            out.push(self.tx_type);
+        }
+        out.extend(encode_list(&payload));
+        out
+    }
+}
+
+/// 日志的 2048 位布隆过滤器，包含每条日志的地址和所有 topic
+pub fn logs_bloom<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Bloom {
+    let mut bloom = Bloom::ZERO;
+    for log in logs {
+        bloom.accrue(BloomInput::Raw(log.address.as_slice()));
+        for topic in &log.topics {
+            bloom.accrue(BloomInput::Raw(&topic.to_be_bytes::<32>()));
+        }
+    }
+    bloom
+}
+
+pub fn receipts_root(receipts: &[Receipt]) -> B256 {
+    ordered_trie_root(receipts.iter().map(Receipt::encode))
+}
+
+/// 区块的布隆过滤器是所有回执的并集
+pub fn block_bloom(receipts: &[Receipt]) -> Bloom {
+    receipts
+        .iter()
+        .fold(Bloom::ZERO, |bloom, receipt| bloom | receipt.logs_bloom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::BlockContext;
+    use crate::state::{InMemoryStateDB, StateDB};
+    use crate::transaction::{Message, EIP1559_TX_TYPE};
+    use crate::vm::Interpreter;
+    use alloy_primitives::hex;
+
+    #[test]
+    fn test_receipts() {
+        let logger = Address::repeat_byte(0xaa);
+        let contract = Address::repeat_byte(0xbb);
+        let caller = Address::repeat_byte(1);
+        let mut state = InMemoryStateDB::new();
+        state.create_object(caller);
+        // LOG1(topic 0xaa) 然后 REVERT
+        state.create_object(logger);
+        state.set_code(logger, hex!("60aa60006000a160006000fd").into());
+        // LOG1(topic 0xbb) 然后 CALL logger，logger 的日志随回滚丢弃
+        let mut code = hex!("60bb60006000a16000600060006000600073").to_vec();
+        code.extend_from_slice(logger.as_slice());
+        code.extend_from_slice(&hex!("5af15000"));
+        state.create_object(contract);
+        state.set_code(contract, code.into());
+        state.commit();
+
+        let mut blk_ctx = BlockContext::new();
+        blk_ctx.block_gas_limit = U256::from(30_000_000);
+        let mut state: Box<dyn StateDB> = Box::new(state);
+        let mut receipts = Vec::new();
+        let (mut gas_used, mut log_index) = (0, 0);
+        for nonce in 0..2 {
+            let msg = Message {
+                caller,
+                to: Some(contract),
+                nonce,
+                gas_limit: 100_000,
+                ..Default::default()
+            };
+            let mut vm = Interpreter::new(state, &blk_ctx);
+            let result = vm.transact(&msg).unwrap();
+            state = vm.into_state();
+            gas_used += result.gas_used;
+            let receipt = Receipt::new(EIP1559_TX_TYPE, &result, gas_used, nonce, log_index);
+            log_index += receipt.logs.len() as u64;
+            receipts.push(receipt);
+        }
+
+        for (i, receipt) in receipts.iter().enumerate() {
+            assert!(receipt.success);
+            assert_eq!(receipt.logs.len(), 1);
+            let log = &receipt.logs[0];
+            assert_eq!(log.address, contract);
+            assert_eq!(log.topics, vec![U256::from(0xbb)]);
+            assert_eq!((log.transaction_index, log.log_index), (i as u64, i as u64));
+            assert!(receipt
+                .logs_bloom
+                .contains_input(BloomInput::Raw(contract.as_slice())));
+            assert!(!receipt
+                .logs_bloom
+                .contains_input(BloomInput::Raw(logger.as_slice())));
+            assert_eq!(receipt.encode()[0], EIP1559_TX_TYPE);
+        }
+        assert_eq!(receipts[1].cumulative_gas_used, gas_used);
+        assert_eq!(block_bloom(&receipts), receipts[0].logs_bloom);
+
+        let legacy = Receipt {
+            tx_type: LEGACY_TX_TYPE,
+            ..receipts[0].clone()
+        };
+        assert_eq!(legacy.encode(), receipts[0].encode()[1..]);
+        assert_ne!(receipts_root(&receipts), receipts_root(&receipts[..1]));
+    }
+}
@@ -1,61 +1,103 @@
 use alloy_primitives::U256;
 
+use crate::error::EVMError;
+
+/// EVM 栈的最大深度
+pub const STACK_LIMIT: usize = 1024;
+
+/// 固定 1024 个槽位的栈，创建后不再分配内存
+///
+/// 解释器在执行每条指令前调用 `check` 一次性检查栈高度，
+/// 之后的 push/pop/dup/swap 不再做 Option 判断，只剩数组下标检查
 pub struct Stack {
-    pub stack: Vec<U256>,
+    pub stack: Box<[U256; STACK_LIMIT]>,
+    len: usize,
+}
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Stack {
     pub fn new() -> Self {
         Stack {
-            stack: Vec::with_capacity(1024),
+            stack: Box::new([U256::ZERO; STACK_LIMIT]),
+            len: 0,
+        }
+    }
+
+    /// 检查栈上至少有 `inputs` 个元素，并且执行后不会超过 `STACK_LIMIT`
+    #[inline]
+    pub fn check(&self, inputs: usize, outputs: usize) -> Result<(), EVMError> {
+        if self.len < inputs {
+            return Err(EVMError::StackUnderflow);
+        }
+        if self.len - inputs + outputs > STACK_LIMIT {
+            return Err(EVMError::StackOverflow);
         }
+        Ok(())
     }
 
+    #[inline]
     pub fn push(&mut self, value: U256) {
-        self.stack.push(value);
+        self.stack[self.len] = value;
+        self.len += 1;
     }
 
+    #[inline]
     pub fn pop(&mut self) -> U256 {
-        self.stack.pop().unwrap()
+        self.len -= 1;
+        self.stack[self.len]
     }
 
+    /// 依次弹出 N 个元素，第一个元素是原来的栈顶
+    #[inline]
     pub fn pop_n<const N: usize>(&mut self) -> [U256; N] {
-        let mut values: Vec<_> = (0..N).map(|_| self.pop()).collect();
-        let result: [_; N] = values
-            .try_into()
-            .expect("Expected the correct number of elements");
-        result
+        let top = self.len;
+        self.len -= N;
+        std::array::from_fn(|i| self.stack[top - 1 - i])
     }
 
-    pub fn len(&self) -> usize {
-        self.stack.len()
+    /// 弹出栈顶，并返回新的栈顶的可变引用，二元运算直接把结果写回
+    #[inline]
+    pub fn pop_top(&mut self) -> (U256, &mut U256) {
+        self.len -= 1;
+        let value = self.stack[self.len];
+        (value, &mut self.stack[self.len - 1])
     }
 
-    pub fn peek(&self) -> U256 {
-        self.stack[self.stack.len() - 1].clone()
+    #[inline]
+    pub fn top_mut(&mut self) -> &mut U256 {
+        &mut self.stack[self.len - 1]
     }
 
-    pub fn dup(&mut self, n: usize) {
-        self.push(self.stack[self.stack.len() - n].clone())
+    pub fn len(&self) -> usize {
+        self.len
     }
 
-    pub fn swap(&mut self, n: usize) {
-        let len = self.stack.len();
-        let tmp = self.stack[len - 1].clone();
-        self.stack[len - 1] = self.stack[len - n - 1].clone();
-        self.stack[len - n - 1] = tmp;
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 
-    pub fn print_stack(&self) {
-        println!("{:<10} {:<64}", "Index", "Value");
-        println!("{:-<10} {:-<64}", "", "");
+    /// 从栈底到栈顶的有效元素
+    pub fn as_slice(&self) -> &[U256] {
+        &self.stack[..self.len]
+    }
 
-        let len = self.stack.len();
-        let start = if len > 16 { len - 16 } else { 0 };
+    pub fn peek(&self) -> U256 {
+        self.stack[self.len - 1]
+    }
 
-        for i in (start..len).rev() {
-            println!("{:<10} {:<64x}", len - i, self.stack[i]);
-        }
+    #[inline]
+    pub fn dup(&mut self, n: usize) {
+        self.push(self.stack[self.len - n])
+    }
+
+    #[inline]
+    pub fn swap(&mut self, n: usize) {
+        self.stack.swap(self.len - 1, self.len - n - 1);
     }
 }
 
@@ -82,4 +124,29 @@ mod tests {
         assert_eq!(stack.stack[0], U256::from(1u64));
         assert_eq!(stack.stack[1], U256::from(1u64));
     }
+
+    #[test]
+    fn test_pop_n_and_pop_top() {
+        let mut stack = Stack::new();
+        for i in 1..=4u64 {
+            stack.push(U256::from(i));
+        }
+        assert_eq!(stack.pop_n::<2>(), [U256::from(4u64), U256::from(3u64)]);
+
+        let (a, b) = stack.pop_top();
+        *b += a;
+        assert_eq!(stack.as_slice(), &[U256::from(3u64)]);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut stack = Stack::new();
+        stack.push(U256::ZERO);
+        assert!(stack.check(1, 1).is_ok());
+        assert!(matches!(stack.check(2, 1), Err(EVMError::StackUnderflow)));
+        for _ in 1..STACK_LIMIT {
+            stack.push(U256::ZERO);
+        }
+        assert!(matches!(stack.check(0, 1), Err(EVMError::StackOverflow)));
+    }
 }
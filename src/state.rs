@@ -1,14 +1,21 @@
-use alloy_primitives::{keccak256, Address, U256};
+use alloy_primitives::{b256, keccak256, Address, Bytes, B256, U256};
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::error::EVMError;
+use crate::genesis::{GenesisAccount, GenesisAlloc};
+use crate::proof::{AccountProof, StorageProof};
+use crate::trie::{encode_account, storage_key, Trie, EMPTY_ROOT};
+
+/// 空代码的 keccak256
+pub const KECCAK_EMPTY: B256 =
+    b256!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
 
 pub trait StateDB {
     // account
     fn create_object(&mut self, address: Address);
-    fn create_contract(&mut self, caller: Address, code: Vec<u8>) -> Address;
-    fn set_code(&mut self, cotnract: Address, code: Vec<u8>);
+    fn create_contract(&mut self, caller: Address, code: Bytes) -> Address;
+    fn set_code(&mut self, cotnract: Address, code: Bytes);
 
     // balance
     fn transfer(&mut self, from: Address, to: Address, value: U256) -> Result<(), EVMError>;
@@ -20,25 +27,90 @@ pub trait StateDB {
     fn get_nonce(&self, address: Address) -> u64;
     fn set_nonce(&mut self, address: Address, nonce: u64);
 
-    // code
-    fn get_code(&self, address: Address) -> Vec<u8>;
+    // code，Bytes 的 clone 只增加引用计数，调用帧和 state 共享同一份代码
+    fn get_code(&self, address: Address) -> Bytes;
     fn get_code_hash(&self, address: Address) -> U256;
     fn get_code_size(&self, address: Address) -> usize;
     fn exists(&self, address: Address) -> bool;
 
+    /// EIP-161 的空账户：nonce 和余额为 0 并且没有代码
+    fn is_empty(&self, address: Address) -> bool {
+        self.get_nonce(address) == 0
+            && self.get_balance(address).is_zero()
+            && self.get_code_size(address) == 0
+    }
+
     // storage
     fn get_state(&self, address: Address, slot: U256) -> U256;
     fn set_state(&mut self, address: Address, slot: U256, value: U256);
+    /// 当前交易开始前的值，SSTORE 据此计算 gas 和退款
+    fn get_committed_state(&self, address: Address, slot: U256) -> U256;
 
     fn get_transition_state(&self, address: Address, slot: U256) -> U256;
     fn set_transition_state(&mut self, address: Address, slot: U256, value: U256);
 
+    // access list (EIP-2929)，返回访问前是否是冷的
+    fn access_address(&mut self, address: Address) -> bool;
+    fn access_slot(&mut self, address: Address, slot: U256) -> bool;
+
+    // gas refund
+    fn add_refund(&mut self, gas: u64);
+    fn sub_refund(&mut self, gas: u64);
+    fn get_refund(&self) -> u64;
+
+    // selfdestruct，账户在交易结束时删除
+    fn selfdestruct(&mut self, address: Address);
+    /// 账户是否在当前交易中由 `create_object` 创建
+    fn is_created(&self, address: Address) -> bool;
+
     // state transaction
+    /// 返回当前修改的位置，`revert_to_snapshot` 撤销之后的所有修改
+    fn snapshot(&mut self) -> usize;
+    fn revert_to_snapshot(&mut self, snapshot: usize);
+    /// 开始新交易，清空访问列表、退款、临时存储和日志
     fn prepare(&mut self);
+    /// 结束交易，删除自毁的账户和被修改过的空账户，然后提交
+    fn finalize(&mut self);
     fn commit(&mut self);
 
     // log
     fn add_log(&mut self, address: Address, topics: Vec<U256>, data: Vec<u8>);
+    /// 当前交易的日志，被回滚的调用产生的日志已经丢弃
+    fn logs(&self) -> &[(Address, Vec<U256>, Vec<u8>)];
+
+    // dump，包括还没有提交的修改
+    fn dump(&self) -> GenesisAlloc;
+    /// 最近一次 `commit` 之后的状态根
+    fn state_root(&self) -> B256;
+    /// 账户和 `slots` 的 Merkle 证明，基于最近一次 `commit` 之后的状态
+    fn get_proof(&self, address: Address, slots: &[U256]) -> Result<AccountProof, EVMError>;
+
+    /// 取出读取外部状态时的错误，出错后本交易的执行结果不可信
+    fn take_error(&mut self) -> Option<EVMError>;
+}
+
+/// 撤销一次修改所需的旧值，`None` 表示修改前没有这一项
+enum JournalEntry {
+    Object {
+        address: Address,
+        prev: Option<StateObject>,
+    },
+    Storage {
+        address: Address,
+        slot: U256,
+        prev: Option<U256>,
+    },
+    TransitionStorage {
+        address: Address,
+        slot: U256,
+        prev: Option<U256>,
+    },
+    AccessAddress(Address),
+    AccessSlot(Address, U256),
+    Refund(u64),
+    Log,
+    Created(Address),
+    Destructed(Address),
 }
 
 pub struct InMemoryStateDB {
@@ -49,6 +121,24 @@ pub struct InMemoryStateDB {
     dirty_objects: HashMap<Address, StateObject>,
     transition_storage: HashMap<(Address, U256), U256>,
     logs: Vec<(Address, Vec<U256>, Vec<u8>)>,
+
+    journal: Vec<JournalEntry>,
+    accessed_addresses: HashSet<Address>,
+    accessed_slots: HashSet<(Address, U256)>,
+    refund: u64,
+    created: HashSet<Address>,
+    destructed: HashSet<Address>,
+
+    /// 已提交的账户和存储对应的树，`commit` 时只更新被修改的部分
+    account_trie: Trie,
+    storage_tries: HashMap<Address, Trie>,
+    state_root: B256,
+}
+
+impl Default for InMemoryStateDB {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl InMemoryStateDB {
@@ -60,60 +150,132 @@ impl InMemoryStateDB {
             dirty_objects: HashMap::new(),
             transition_storage: HashMap::new(),
             logs: Vec::new(),
+            journal: Vec::new(),
+            accessed_addresses: HashSet::new(),
+            accessed_slots: HashSet::new(),
+            refund: 0,
+            created: HashSet::new(),
+            destructed: HashSet::new(),
+            account_trie: Trie::new(),
+            storage_tries: HashMap::new(),
+            state_root: EMPTY_ROOT,
         }
     }
 }
 
 impl InMemoryStateDB {
-    fn get_object(&self, address: &Address) -> Option<StateObject> {
-        match self.dirty_objects.get(address) {
-            Some(account) => Some(account.clone()),
-            None => self.objects.get(address).cloned(),
-        }
+    /// 直接加入已提交的账户，不记录修改也不更新状态根，用于按需从其它来源加载状态
+    pub(crate) fn load_account(&mut self, account: StateObject) {
+        self.objects.insert(account.address, account);
     }
 
-    fn get_object_mut(&mut self, address: &Address) -> Option<&mut StateObject> {
-        match self.dirty_objects.get_mut(address) {
+    /// 和 `load_account` 一样加入已提交的存储
+    pub(crate) fn load_storage(&mut self, address: Address, slot: U256, value: U256) {
+        self.storage.insert((address, slot), value);
+    }
+
+    fn get_object(&self, address: &Address) -> Option<&StateObject> {
+        match self.dirty_objects.get(address) {
             Some(account) => Some(account),
-            None => self.objects.get_mut(address),
+            None => self.objects.get(address),
         }
     }
 
-    fn get_object_mut_or_create(&mut self, address: &Address) -> &mut StateObject {
-        match self.get_object(address) {
-            Some(account) => self.get_object_mut(address).unwrap(),
+    /// 修改前先把账户复制到 dirty_objects 并记录旧值
+    fn get_object_mut(&mut self, address: &Address) -> Option<&mut StateObject> {
+        let prev = match self.dirty_objects.get(address) {
+            Some(account) => Some(account.clone()),
             None => {
-                let account = StateObject::new_with_address(address.clone());
-                self.set_account(address.clone(), account);
-                self.get_object_mut(address).unwrap()
+                let account = self.objects.get(address)?.clone();
+                self.dirty_objects.insert(*address, account);
+                None
             }
+        };
+        self.journal.push(JournalEntry::Object {
+            address: *address,
+            prev,
+        });
+        self.dirty_objects.get_mut(address)
+    }
+
+    fn get_object_mut_or_create(&mut self, address: &Address) -> &mut StateObject {
+        if self.get_object(address).is_none() {
+            self.set_account(*address, StateObject::new_with_address(*address));
         }
+        self.get_object_mut(address).unwrap()
     }
 
     fn set_account(&mut self, address: Address, account: StateObject) {
-        self.dirty_objects.insert(address, account);
+        let prev = self.dirty_objects.insert(address, account);
+        self.journal.push(JournalEntry::Object { address, prev });
+    }
+
+    fn delete_account(&mut self, address: &Address) {
+        self.dirty_objects.remove(address);
+        self.objects.remove(address);
+        self.storage.retain(|(a, _), _| a != address);
+        self.dirty_storage.retain(|(a, _), _| a != address);
+        self.account_trie.remove(keccak256(address).as_slice());
+        self.storage_tries.remove(address);
+    }
+
+    /// 把已提交的修改写入树并重新计算状态根
+    fn update_tries(
+        &mut self,
+        storage: &HashMap<(Address, U256), U256>,
+        mut accounts: HashSet<Address>,
+    ) {
+        for (&(address, slot), value) in storage {
+            let trie = self.storage_tries.entry(address).or_default();
+            let key = storage_key(slot);
+            match value.is_zero() {
+                true => trie.remove(key.as_slice()),
+                false => trie.insert(key.as_slice(), alloy_rlp::encode(value)),
+            }
+            accounts.insert(address);
+        }
+        for address in accounts {
+            let Some(account) = self.objects.get(&address) else {
+                continue;
+            };
+            let storage_root = self
+                .storage_tries
+                .get(&address)
+                .map_or(EMPTY_ROOT, |trie| trie.root());
+            let code_hash = match account.code.is_empty() {
+                true => KECCAK_EMPTY,
+                false => B256::from(account.code_hash),
+            };
+            self.account_trie.insert(
+                keccak256(address).as_slice(),
+                encode_account(account.nonce, account.balance, storage_root, code_hash),
+            );
+        }
+        self.state_root = self.account_trie.root();
     }
 }
 
 impl StateDB for InMemoryStateDB {
     fn create_object(&mut self, address: Address) {
         self.set_account(address, StateObject::new_with_address(address));
+        if self.created.insert(address) {
+            self.journal.push(JournalEntry::Created(address));
+        }
     }
 
-    fn create_contract(&mut self, caller: Address, code: Vec<u8>) -> Address {
-        let account = self.get_object(&caller).unwrap();
-        let nonce = account.nonce;
+    fn create_contract(&mut self, caller: Address, code: Bytes) -> Address {
+        let nonce = self.get_nonce(caller);
         let contract_address = caller.create(nonce);
+        self.set_nonce(caller, nonce + 1);
 
-        let mut contract = StateObject::new();
-        contract.address = contract_address;
-        contract.code = code;
-
-        self.set_account(contract_address, contract);
+        self.set_account(
+            contract_address,
+            StateObject::new_with_code(contract_address, code),
+        );
         contract_address
     }
 
-    fn set_code(&mut self, cotnract: Address, code: Vec<u8>) {
+    fn set_code(&mut self, cotnract: Address, code: Bytes) {
         match self.get_object_mut(&cotnract) {
             Some(account) => {
                 account.code_hash = keccak256(&code).into();
@@ -132,22 +294,16 @@ impl StateDB for InMemoryStateDB {
     }
 
     fn sub_balance(&mut self, address: Address, value: U256) -> Result<U256, EVMError> {
+        if self.get_balance(address) < value {
+            return Err(EVMError::InsufficientBalance);
+        }
         match self.get_object_mut(&address) {
             Some(account) => {
                 let balance = account.balance;
-                if balance < value {
-                    return Err(EVMError::InsufficientBalance);
-                }
                 account.balance -= value;
                 Ok(balance)
             }
-            None => {
-                if value.is_zero() {
-                    Ok(U256::ZERO)
-                } else {
-                    Err(EVMError::InsufficientBalance)
-                }
-            }
+            None => Ok(U256::ZERO),
         }
     }
 
@@ -176,10 +332,10 @@ impl StateDB for InMemoryStateDB {
         self.get_object_mut_or_create(&address).nonce = nonce;
     }
 
-    fn get_code(&self, address: Address) -> Vec<u8> {
+    fn get_code(&self, address: Address) -> Bytes {
         match self.get_object(&address) {
             Some(account) => account.code.clone(),
-            None => Vec::new(),
+            None => Bytes::new(),
         }
     }
 
@@ -203,49 +359,240 @@ impl StateDB for InMemoryStateDB {
 
     fn get_state(&self, address: Address, slot: U256) -> U256 {
         match self.dirty_storage.get(&(address, slot)) {
-            Some(value) => value.clone(),
-            None => match self.storage.get(&(address, slot)) {
-                Some(value) => value.clone(),
-                None => U256::ZERO,
-            },
+            Some(value) => *value,
+            None => self.get_committed_state(address, slot),
         }
     }
 
     fn set_state(&mut self, address: Address, slot: U256, value: U256) {
-        self.dirty_storage.insert((address, slot), value);
+        let prev = self.dirty_storage.insert((address, slot), value);
+        self.journal.push(JournalEntry::Storage {
+            address,
+            slot,
+            prev,
+        });
+    }
+
+    fn get_committed_state(&self, address: Address, slot: U256) -> U256 {
+        match self.storage.get(&(address, slot)) {
+            Some(value) => *value,
+            None => U256::ZERO,
+        }
+    }
+
+    fn access_address(&mut self, address: Address) -> bool {
+        let cold = self.accessed_addresses.insert(address);
+        if cold {
+            self.journal.push(JournalEntry::AccessAddress(address));
+        }
+        cold
+    }
+
+    fn access_slot(&mut self, address: Address, slot: U256) -> bool {
+        let cold = self.accessed_slots.insert((address, slot));
+        if cold {
+            self.journal.push(JournalEntry::AccessSlot(address, slot));
+        }
+        cold
+    }
+
+    fn add_refund(&mut self, gas: u64) {
+        self.journal.push(JournalEntry::Refund(self.refund));
+        self.refund += gas;
+    }
+
+    fn sub_refund(&mut self, gas: u64) {
+        self.journal.push(JournalEntry::Refund(self.refund));
+        self.refund -= gas;
+    }
+
+    fn get_refund(&self) -> u64 {
+        self.refund
+    }
+
+    fn selfdestruct(&mut self, address: Address) {
+        if self.destructed.insert(address) {
+            self.journal.push(JournalEntry::Destructed(address));
+        }
+    }
+
+    fn is_created(&self, address: Address) -> bool {
+        self.created.contains(&address)
+    }
+
+    fn snapshot(&mut self) -> usize {
+        self.journal.len()
+    }
+
+    fn revert_to_snapshot(&mut self, snapshot: usize) {
+        for entry in self.journal.drain(snapshot..).rev() {
+            match entry {
+                JournalEntry::Object { address, prev } => {
+                    match prev {
+                        Some(account) => self.dirty_objects.insert(address, account),
+                        None => self.dirty_objects.remove(&address),
+                    };
+                }
+                JournalEntry::Storage {
+                    address,
+                    slot,
+                    prev,
+                } => {
+                    match prev {
+                        Some(value) => self.dirty_storage.insert((address, slot), value),
+                        None => self.dirty_storage.remove(&(address, slot)),
+                    };
+                }
+                JournalEntry::TransitionStorage {
+                    address,
+                    slot,
+                    prev,
+                } => {
+                    match prev {
+                        Some(value) => self.transition_storage.insert((address, slot), value),
+                        None => self.transition_storage.remove(&(address, slot)),
+                    };
+                }
+                JournalEntry::AccessAddress(address) => {
+                    self.accessed_addresses.remove(&address);
+                }
+                JournalEntry::AccessSlot(address, slot) => {
+                    self.accessed_slots.remove(&(address, slot));
+                }
+                JournalEntry::Refund(refund) => {
+                    self.refund = refund;
+                }
+                JournalEntry::Log => {
+                    self.logs.pop();
+                }
+                JournalEntry::Created(address) => {
+                    self.created.remove(&address);
+                }
+                JournalEntry::Destructed(address) => {
+                    self.destructed.remove(&address);
+                }
+            }
+        }
     }
 
     fn prepare(&mut self) {
-        self.dirty_storage.clear();
-        self.dirty_objects.clear();
+        self.journal.clear();
+        self.accessed_addresses.clear();
+        self.accessed_slots.clear();
+        self.refund = 0;
+        self.logs.clear();
+        self.created.clear();
+        self.destructed.clear();
         self.transition_storage.clear();
     }
 
+    fn finalize(&mut self) {
+        let deleted: Vec<_> = self
+            .dirty_objects
+            .iter()
+            .filter(|(address, account)| self.destructed.contains(*address) || account.is_empty())
+            .map(|(address, _)| *address)
+            .collect();
+        for address in deleted.iter().chain(&self.destructed.clone()) {
+            self.delete_account(address);
+        }
+        self.commit();
+    }
+
     fn commit(&mut self) {
-        for (slot, value) in self.dirty_storage.iter() {
-            self.storage.insert(slot.clone(), value.clone());
+        let storage = std::mem::take(&mut self.dirty_storage);
+        for (slot, value) in storage.iter() {
+            self.storage.insert(*slot, *value);
         }
-        self.dirty_storage.clear();
-        for (address, account) in self.dirty_objects.iter() {
-            self.objects.insert(address.clone(), account.clone());
+        let objects = std::mem::take(&mut self.dirty_objects);
+        let accounts = objects.keys().copied().collect();
+        for (address, account) in objects {
+            self.objects.insert(address, account);
         }
-        self.dirty_objects.clear();
+        self.update_tries(&storage, accounts);
         self.transition_storage.clear();
+        self.journal.clear();
     }
 
     fn add_log(&mut self, address: Address, topics: Vec<U256>, data: Vec<u8>) {
         self.logs.push((address, topics, data));
+        self.journal.push(JournalEntry::Log);
+    }
+
+    fn logs(&self) -> &[(Address, Vec<U256>, Vec<u8>)] {
+        &self.logs
     }
 
     fn get_transition_state(&self, address: Address, slot: U256) -> U256 {
         match self.transition_storage.get(&(address, slot)) {
-            Some(value) => value.clone(),
+            Some(value) => *value,
             None => U256::ZERO,
         }
     }
 
     fn set_transition_state(&mut self, address: Address, slot: U256, value: U256) {
-        self.transition_storage.insert((address, slot), value);
+        let prev = self.transition_storage.insert((address, slot), value);
+        self.journal.push(JournalEntry::TransitionStorage {
+            address,
+            slot,
+            prev,
+        });
+    }
+
+    fn state_root(&self) -> B256 {
+        self.state_root
+    }
+
+    fn get_proof(&self, address: Address, slots: &[U256]) -> Result<AccountProof, EVMError> {
+        let account = self.objects.get(&address);
+        let storage_trie = account.and(self.storage_tries.get(&address));
+        let code_hash = match account {
+            Some(account) if !account.code.is_empty() => B256::from(account.code_hash),
+            _ => KECCAK_EMPTY,
+        };
+        Ok(AccountProof {
+            address,
+            balance: account.map_or(U256::ZERO, |account| account.balance),
+            code_hash,
+            nonce: account.map_or(0, |account| account.nonce),
+            storage_hash: storage_trie.map_or(EMPTY_ROOT, |trie| trie.root()),
+            account_proof: self.account_trie.proof(keccak256(address).as_slice()),
+            storage_proof: slots
+                .iter()
+                .map(|&slot| {
+                    let value = self.get_committed_state(address, slot);
+                    StorageProof::new(storage_trie, slot, value)
+                })
+                .collect(),
+        })
+    }
+
+    fn dump(&self) -> GenesisAlloc {
+        let mut alloc = GenesisAlloc::new();
+        for (address, account) in self.objects.iter().chain(&self.dirty_objects) {
+            alloc.insert(
+                *address,
+                GenesisAccount {
+                    balance: account.balance,
+                    nonce: account.nonce,
+                    code: account.code.clone(),
+                    storage: Default::default(),
+                },
+            );
+        }
+        for (&(address, slot), &value) in self.storage.iter().chain(&self.dirty_storage) {
+            let storage = &mut alloc.entry(address).or_default().storage;
+            match value.is_zero() {
+                true => storage.remove(&slot),
+                false => storage.insert(slot, value),
+            };
+        }
+        alloc
+    }
+
+    /// 本地状态不会读取失败
+    fn take_error(&mut self) -> Option<EVMError> {
+        None
     }
 }
 
@@ -253,17 +600,23 @@ impl StateDB for InMemoryStateDB {
 pub struct StateObject {
     pub balance: U256,
     pub nonce: u64,
-    pub code: Vec<u8>,
+    pub code: Bytes,
     pub code_hash: U256,
     pub address: Address,
 }
 
+impl Default for StateObject {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StateObject {
     pub fn new() -> Self {
         StateObject {
             balance: U256::ZERO,
             nonce: 0,
-            code: Vec::new(),
+            code: Bytes::new(),
             code_hash: U256::ZERO,
             address: Address::ZERO,
         }
@@ -273,13 +626,13 @@ impl StateObject {
         StateObject {
             balance: U256::ZERO,
             nonce: 0,
-            code: Vec::new(),
+            code: Bytes::new(),
             code_hash: U256::ZERO,
             address,
         }
     }
 
-    pub fn new_with_code(address: Address, code: Vec<u8>) -> Self {
+    pub fn new_with_code(address: Address, code: Bytes) -> Self {
         let code_hash = keccak256(&code).into();
         StateObject {
             balance: U256::ZERO,
@@ -289,4 +642,85 @@ impl StateObject {
             address,
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revert_to_snapshot() {
+        let a = Address::repeat_byte(1);
+        let b = Address::repeat_byte(2);
+        let mut state = InMemoryStateDB::new();
+        state.add_balance(a, U256::from(10));
+        state.set_state(a, U256::ZERO, U256::from(1));
+        state.commit();
+
+        state.prepare();
+        let snapshot = state.snapshot();
+        state.transfer(a, b, U256::from(4)).unwrap();
+        state.set_state(a, U256::ZERO, U256::from(2));
+        state.access_address(b);
+        state.add_refund(100);
+        state.add_log(a, vec![], vec![]);
+        assert_eq!(state.get_committed_state(a, U256::ZERO), U256::from(1));
+
+        state.revert_to_snapshot(snapshot);
+        assert_eq!(state.get_balance(a), U256::from(10));
+        assert!(!state.exists(b));
+        assert_eq!(state.get_state(a, U256::ZERO), U256::from(1));
+        assert!(state.access_address(b));
+        assert_eq!(state.get_refund(), 0);
+        assert!(state.logs().is_empty());
+    }
+
+    #[test]
+    fn test_finalize() {
+        let a = Address::repeat_byte(1);
+        let b = Address::repeat_byte(2);
+        let c = Address::repeat_byte(3);
+        let mut state = InMemoryStateDB::new();
+        state.set_code(a, Bytes::from_static(&[0]));
+        state.set_state(a, U256::ZERO, U256::from(1));
+        state.create_object(c);
+        state.commit();
+
+        // 自毁的账户和被修改过的空账户在交易结束时删除，没有被修改的空账户保留
+        state.prepare();
+        state.selfdestruct(a);
+        state.add_balance(b, U256::ZERO);
+        state.finalize();
+        assert!(!state.exists(a));
+        assert_eq!(state.get_state(a, U256::ZERO), U256::ZERO);
+        assert!(!state.exists(b));
+        assert!(state.exists(c));
+    }
+
+    #[test]
+    fn test_state_root() {
+        let a = Address::repeat_byte(1);
+        let b = Address::repeat_byte(2);
+        let mut state = InMemoryStateDB::new();
+        assert_eq!(state.state_root(), EMPTY_ROOT);
+
+        state.set_code(a, Bytes::from_static(&[0]));
+        state.set_state(a, U256::ZERO, U256::from(1));
+        state.set_state(a, U256::from(1), U256::from(2));
+        state.add_balance(b, U256::from(10));
+        state.commit();
+        assert_eq!(state.state_root(), crate::trie::state_root(&state.dump()));
+
+        // 清空槽位和删除账户后只更新对应的路径
+        state.prepare();
+        state.set_state(a, U256::ZERO, U256::ZERO);
+        state.selfdestruct(b);
+        state.finalize();
+        assert_eq!(state.state_root(), crate::trie::state_root(&state.dump()));
+        assert_eq!(state.dump().len(), 1);
+    }
 }
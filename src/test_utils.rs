@@ -0,0 +1,58 @@
+//! 单元测试共用的辅助函数
+
+use std::net::TcpListener;
+use std::thread;
+
+use alloy_primitives::{Address, Bytes};
+
+use crate::asm::Assembler;
+use crate::chain::LocalChain;
+use crate::dev_accounts::{
+    accounts_from_mnemonic, prefund, DevAccount, DEFAULT_BALANCE, DEFAULT_MNEMONIC,
+};
+use crate::fork::Fork;
+use crate::node::Node;
+use crate::state::{InMemoryStateDB, StateDB};
+
+/// 汇编 `code` 并部署到新的状态中，返回状态、部署者和合约地址
+pub fn deploy(code: &str) -> (InMemoryStateDB, Address, Address) {
+    let code = Assembler::new().asm(code).unwrap();
+    let mut state = InMemoryStateDB::new();
+    let caller = Address::ZERO;
+    state.create_object(caller);
+    let contract = state.create_contract(caller, code.into());
+    state.commit();
+    (state, caller, contract)
+}
+
+/// 默认助记词的第一个开发账户
+pub fn dev_account() -> DevAccount {
+    accounts_from_mnemonic(DEFAULT_MNEMONIC, "", 1)
+        .unwrap()
+        .remove(0)
+}
+
+/// 给 `accounts` 充值默认余额并在指定地址放置合约代码，已经提交
+pub fn genesis(accounts: &[DevAccount], contracts: &[(Address, &[u8])]) -> InMemoryStateDB {
+    let mut state = InMemoryStateDB::new();
+    prefund(&mut state, accounts, DEFAULT_BALANCE);
+    for &(address, code) in contracts {
+        state.create_object(address);
+        state.set_code(address, Bytes::copy_from_slice(code));
+    }
+    state.commit();
+    state
+}
+
+/// 在后台线程启动只有创世区块的本地节点，返回它的 HTTP 地址
+///
+/// 节点不能跨线程传递，所以创世状态在线程里由 `genesis` 构造
+pub fn spawn_node(genesis: impl FnOnce() -> InMemoryStateDB + Send + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let mut node = Node::new(LocalChain::new(genesis(), 1, Fork::Cancun));
+        node.serve(listener).unwrap();
+    });
+    url
+}
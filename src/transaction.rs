@@ -0,0 +1,633 @@
+use alloy_primitives::{keccak256, uint, Address, Bytes, B256, U256};
+use alloy_rlp::{Decodable, Encodable, Header};
+
+use crate::{
+    context::BlockContext, error::EVMError, fork::Fork, gas, precompile, trie::encode_list,
+};
+
+/// 每个 blob 消耗的 blob gas (EIP-4844)
+pub const GAS_PER_BLOB: u64 = 1 << 17;
+pub const MAX_BLOBS_PER_BLOCK: usize = 6;
+
+pub const LEGACY_TX_TYPE: u8 = 0;
+pub const EIP2930_TX_TYPE: u8 = 1;
+pub const EIP1559_TX_TYPE: u8 = 2;
+pub const EIP4844_TX_TYPE: u8 = 3;
+pub const EIP7702_TX_TYPE: u8 = 4;
+
+/// EIP-7702 授权签名的前缀
+const AUTHORIZATION_MAGIC: u8 = 0x05;
+
+/// secp256k1 曲线阶的一半，交易签名的 s 不能超过它 (EIP-2)
+const SECP256K1N_HALF: U256 =
+    uint!(0x7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0_U256);
+
+/// 交易中执行所需的部分，签名和编码由调用方处理
+#[derive(Debug, Clone, Default)]
+pub struct Message {
+    pub caller: Address,
+    /// None 表示创建合约
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Bytes,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    /// legacy 交易的 gas price，EIP-1559 交易的 max fee per gas
+    pub gas_price: U256,
+    /// 只有 EIP-1559 之后的交易才有
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub access_list: Vec<(Address, Vec<U256>)>,
+    pub blob_hashes: Vec<U256>,
+    pub max_fee_per_blob_gas: Option<U256>,
+    /// EIP-7702 的授权，还不支持执行
+    pub authorization_list: Vec<Authorization>,
+}
+
+impl Message {
+    /// 执行前就要扣除的 gas：基础费用、calldata、access list 和初始化代码
+    pub fn intrinsic_gas(&self, fork: Fork) -> u64 {
+        let mut gas = match self.to {
+            Some(_) => gas::TX,
+            None => gas::TX_CREATE,
+        };
+        let zeros = self.data.iter().filter(|&&b| b == 0).count() as u64;
+        gas += zeros * gas::TX_DATA_ZERO + (self.data.len() as u64 - zeros) * gas::TX_DATA_NON_ZERO;
+        for (_, slots) in &self.access_list {
+            gas +=
+                gas::TX_ACCESS_LIST_ADDRESS + slots.len() as u64 * gas::TX_ACCESS_LIST_STORAGE_KEY;
+        }
+        if self.to.is_none() && fork >= Fork::Shanghai {
+            gas += gas::INITCODE_WORD * gas::words(self.data.len() as u64);
+        }
+        gas + self.authorization_list.len() as u64 * gas::TX_AUTHORIZATION
+    }
+
+    /// `Interpreter::run` 的参数 (origin, from, to, calldata, value)，创建合约时为 None
+    pub fn call_args(&self) -> Option<(Address, Address, Address, Bytes, U256)> {
+        let to = self.to?;
+        Some((self.caller, self.caller, to, self.data.clone(), self.value))
+    }
+
+    /// 实际支付的 gas 价格，EIP-1559 交易为 min(max fee, base fee + priority fee)
+    pub fn effective_gas_price(&self, base_fee: U256) -> U256 {
+        match self.max_priority_fee_per_gas {
+            Some(priority) => self.gas_price.min(base_fee.saturating_add(priority)),
+            None => self.gas_price,
+        }
+    }
+
+    pub fn blob_gas(&self) -> u64 {
+        GAS_PER_BLOB * self.blob_hashes.len() as u64
+    }
+
+    /// 最多花费的余额：gas limit * gas price + value + blob 费用，溢出时为 None
+    pub fn max_cost(&self) -> Option<U256> {
+        let blob_fee = U256::from(self.blob_gas()) * self.max_fee_per_blob_gas.unwrap_or_default();
+        U256::from(self.gas_limit)
+            .checked_mul(self.gas_price)?
+            .checked_add(self.value)?
+            .checked_add(blob_fee)
+    }
+}
+
+/// 交易执行的结果，交易无效时没有结果
+#[derive(Debug)]
+pub struct ExecutionResult {
+    /// 最外层调用的结果，REVERT 为 `Err(EVMError::Revert)`
+    pub result: Result<(), EVMError>,
+    /// 扣除退款后的 gas
+    pub gas_used: u64,
+    pub output: Bytes,
+    /// 被回滚的调用产生的日志已经丢弃
+    pub logs: Vec<(Address, Vec<U256>, Vec<u8>)>,
+    /// 创建合约成功时的合约地址
+    pub contract_address: Option<Address>,
+}
+
+impl ExecutionResult {
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// 已签名的交易，交易类型没有的字段为默认值
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignedTransaction {
+    pub tx_type: u8,
+    /// legacy 交易的 chain id 由 v 得到，EIP-155 之前的交易没有
+    pub chain_id: Option<u64>,
+    pub nonce: u64,
+    /// legacy 和 EIP-2930 交易的 gas price，之后的交易的 max fee per gas
+    pub gas_price: U256,
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub gas_limit: u64,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: Bytes,
+    pub access_list: Vec<(Address, Vec<U256>)>,
+    pub max_fee_per_blob_gas: Option<U256>,
+    pub blob_hashes: Vec<U256>,
+    pub authorization_list: Vec<Authorization>,
+    /// legacy 交易为 27/28 或者 chain_id * 2 + 35/36，typed 交易为 y parity
+    pub v: u64,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl SignedTransaction {
+    /// 指定类型的未签名交易，这个类型才有的费用字段为 `Some(0)`，其余字段为默认值
+    pub fn new(tx_type: u8, chain_id: u64) -> Self {
+        SignedTransaction {
+            tx_type,
+            chain_id: Some(chain_id),
+            max_priority_fee_per_gas: (tx_type >= EIP1559_TX_TYPE).then_some(U256::ZERO),
+            max_fee_per_blob_gas: (tx_type == EIP4844_TX_TYPE).then_some(U256::ZERO),
+            ..Default::default()
+        }
+    }
+
+    /// 解码交易，typed 交易的编码为 `type || rlp(fields)`
+    pub fn decode(raw: &[u8]) -> Result<Self, EVMError> {
+        let Some((&first, rest)) = raw.split_first() else {
+            return Err(EVMError::InvalidRlp("empty transaction".to_string()));
+        };
+        let mut tx = SignedTransaction::default();
+        let mut buf = match first {
+            0xc0..=0xff => raw,
+            EIP2930_TX_TYPE..=EIP7702_TX_TYPE => {
+                tx.tx_type = first;
+                rest
+            }
+            _ => {
+                return Err(EVMError::InvalidTransaction(format!(
+                    "unsupported transaction type {first}"
+                )))
+            }
+        };
+        let fields = &mut Header::decode_bytes(&mut buf, true)?;
+        if !buf.is_empty() {
+            return Err(EVMError::InvalidRlp(
+                "trailing bytes after transaction".to_string(),
+            ));
+        }
+
+        if tx.tx_type != LEGACY_TX_TYPE {
+            tx.chain_id = Some(u64::decode(fields)?);
+        }
+        tx.nonce = u64::decode(fields)?;
+        if tx.tx_type >= EIP1559_TX_TYPE {
+            tx.max_priority_fee_per_gas = Some(U256::decode(fields)?);
+        }
+        tx.gas_price = U256::decode(fields)?;
+        tx.gas_limit = u64::decode(fields)?;
+        tx.to = match Bytes::decode(fields)? {
+            to if to.is_empty() => None,
+            to if to.len() == 20 => Some(Address::from_slice(&to)),
+            _ => return Err(EVMError::InvalidRlp("invalid to address".to_string())),
+        };
+        tx.value = U256::decode(fields)?;
+        tx.data = Bytes::decode(fields)?;
+        if tx.tx_type != LEGACY_TX_TYPE {
+            tx.access_list = decode_access_list(fields)?;
+        }
+        if tx.tx_type == EIP4844_TX_TYPE {
+            tx.max_fee_per_blob_gas = Some(U256::decode(fields)?);
+            tx.blob_hashes = Vec::<B256>::decode(fields)?
+                .into_iter()
+                .map(Into::into)
+                .collect();
+        }
+        if tx.tx_type == EIP7702_TX_TYPE {
+            let mut list = Header::decode_bytes(fields, true)?;
+            while !list.is_empty() {
+                tx.authorization_list
+                    .push(Authorization::decode(&mut list)?);
+            }
+            if tx.to.is_none() || tx.authorization_list.is_empty() {
+                return Err(EVMError::InvalidTransaction(
+                    "set code transaction needs a target and authorizations".to_string(),
+                ));
+            }
+        }
+        tx.v = u64::decode(fields)?;
+        tx.r = U256::decode(fields)?;
+        tx.s = U256::decode(fields)?;
+        if !fields.is_empty() {
+            return Err(EVMError::InvalidRlp(
+                "too many transaction fields".to_string(),
+            ));
+        }
+
+        tx.chain_id = match (tx.tx_type, tx.v) {
+            (LEGACY_TX_TYPE, 27 | 28) => None,
+            (LEGACY_TX_TYPE, v) if v >= 35 => Some((v - 35) / 2),
+            (LEGACY_TX_TYPE, v) => {
+                return Err(EVMError::InvalidTransaction(format!("invalid v {v}")))
+            }
+            (_, 0 | 1) => tx.chain_id,
+            (_, v) => {
+                return Err(EVMError::InvalidTransaction(format!(
+                    "invalid y parity {v}"
+                )))
+            }
+        };
+        Ok(tx)
+    }
+
+    /// 交易的编码，也是交易树中的值
+    pub fn encode(&self) -> Vec<u8> {
+        self.encode_fields(true)
+    }
+
+    pub fn hash(&self) -> B256 {
+        keccak256(self.encode())
+    }
+
+    /// 被签名的哈希，legacy 交易按 EIP-155 包含 chain id
+    pub fn signature_hash(&self) -> B256 {
+        keccak256(self.encode_fields(false))
+    }
+
+    fn encode_fields(&self, signed: bool) -> Vec<u8> {
+        let mut payload = Vec::new();
+        if self.tx_type != LEGACY_TX_TYPE {
+            self.chain_id.unwrap_or_default().encode(&mut payload);
+        }
+        self.nonce.encode(&mut payload);
+        if self.tx_type >= EIP1559_TX_TYPE {
+            self.max_priority_fee_per_gas
+                .unwrap_or_default()
+                .encode(&mut payload);
+        }
+        self.gas_price.encode(&mut payload);
+        self.gas_limit.encode(&mut payload);
+        match self.to {
+            Some(to) => to.encode(&mut payload),
+            None => payload.push(alloy_rlp::EMPTY_STRING_CODE),
+        }
+        self.value.encode(&mut payload);
+        self.data.encode(&mut payload);
+        if self.tx_type != LEGACY_TX_TYPE {
+            encode_access_list(&self.access_list, &mut payload);
+        }
+        if self.tx_type == EIP4844_TX_TYPE {
+            self.max_fee_per_blob_gas
+                .unwrap_or_default()
+                .encode(&mut payload);
+            let hashes: Vec<B256> = self.blob_hashes.iter().map(|&hash| hash.into()).collect();
+            hashes.encode(&mut payload);
+        }
+        if self.tx_type == EIP7702_TX_TYPE {
+            let mut list = Vec::new();
+            for authorization in &self.authorization_list {
+                authorization.encode(&mut list);
+            }
+            payload.extend(encode_list(&list));
+        }
+        match (signed, self.tx_type, self.chain_id) {
+            (true, _, _) => {
+                self.v.encode(&mut payload);
+                self.r.encode(&mut payload);
+                self.s.encode(&mut payload);
+            }
+            (false, LEGACY_TX_TYPE, Some(chain_id)) => {
+                chain_id.encode(&mut payload);
+                0u8.encode(&mut payload);
+                0u8.encode(&mut payload);
+            }
+            (false, _, _) => {}
+        }
+
+        let mut out = Vec::with_capacity(payload.len() + 10);
+        if self.tx_type != LEGACY_TX_TYPE {
+            out.push(self.tx_type);
+        }
+        out.extend(encode_list(&payload));
+        out
+    }
+
+    /// 由签名恢复发送者
+    pub fn recover_sender(&self) -> Result<Address, EVMError> {
+        let recid = match (self.tx_type, self.chain_id) {
+            (LEGACY_TX_TYPE, None) => self.v.checked_sub(27),
+            (LEGACY_TX_TYPE, Some(chain_id)) => self.v.checked_sub(chain_id * 2 + 35),
+            _ => Some(self.v),
+        };
+        recover_signer(self.signature_hash(), recid, self.r, self.s)
+    }
+
+    /// 检查 chain id 并恢复发送者，得到在 `blk_ctx` 中执行的消息
+    ///
+    /// 还没有实现 EIP-7702 的代码委托，set code 交易只能编解码和签名，不能执行
+    pub fn to_message(&self, blk_ctx: &BlockContext) -> Result<Message, EVMError> {
+        if self.tx_type == EIP7702_TX_TYPE {
+            return Err(EVMError::SetCodeUnsupported);
+        }
+        if let Some(chain_id) = self.chain_id {
+            if U256::from(chain_id) != blk_ctx.chain_id {
+                return Err(EVMError::InvalidTransaction(format!(
+                    "chain id mismatch: expected {}, got {chain_id}",
+                    blk_ctx.chain_id
+                )));
+            }
+        }
+        Ok(self.message(self.recover_sender()?))
+    }
+
+    /// 以 `caller` 为发送者执行的消息
+    pub fn message(&self, caller: Address) -> Message {
+        Message {
+            caller,
+            to: self.to,
+            value: self.value,
+            data: self.data.clone(),
+            nonce: self.nonce,
+            gas_limit: self.gas_limit,
+            gas_price: self.gas_price,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            access_list: self.access_list.clone(),
+            blob_hashes: self.blob_hashes.clone(),
+            max_fee_per_blob_gas: self.max_fee_per_blob_gas,
+            authorization_list: self.authorization_list.clone(),
+        }
+    }
+}
+
+/// EIP-7702 的授权：authority 把自己的代码委托给 `address`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Authorization {
+    /// 0 表示在所有链上有效
+    pub chain_id: U256,
+    pub address: Address,
+    pub nonce: u64,
+    pub y_parity: u8,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl Authorization {
+    /// 被签名的哈希 keccak256(0x05 || rlp([chain_id, address, nonce]))
+    pub fn signature_hash(&self) -> B256 {
+        let mut payload = Vec::new();
+        self.chain_id.encode(&mut payload);
+        self.address.encode(&mut payload);
+        self.nonce.encode(&mut payload);
+        let mut out = vec![AUTHORIZATION_MAGIC];
+        out.extend(encode_list(&payload));
+        keccak256(out)
+    }
+
+    /// 签名授权的账户
+    pub fn recover_authority(&self) -> Result<Address, EVMError> {
+        recover_signer(
+            self.signature_hash(),
+            Some(self.y_parity as u64),
+            self.r,
+            self.s,
+        )
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        self.chain_id.encode(&mut payload);
+        self.address.encode(&mut payload);
+        self.nonce.encode(&mut payload);
+        self.y_parity.encode(&mut payload);
+        self.r.encode(&mut payload);
+        self.s.encode(&mut payload);
+        out.extend(encode_list(&payload));
+    }
+
+    fn decode(buf: &mut &[u8]) -> Result<Self, EVMError> {
+        let fields = &mut Header::decode_bytes(buf, true)?;
+        let authorization = Authorization {
+            chain_id: U256::decode(fields)?,
+            address: Address::decode(fields)?,
+            nonce: u64::decode(fields)?,
+            y_parity: u8::decode(fields)?,
+            r: U256::decode(fields)?,
+            s: U256::decode(fields)?,
+        };
+        if !fields.is_empty() {
+            return Err(EVMError::InvalidRlp("invalid authorization".to_string()));
+        }
+        Ok(authorization)
+    }
+}
+
+/// 从签名恢复地址，`recid` 必须是 0 或 1，s 必须在低半区 (EIP-2)
+fn recover_signer(hash: B256, recid: Option<u64>, r: U256, s: U256) -> Result<Address, EVMError> {
+    let invalid = || EVMError::InvalidTransaction("invalid signature".to_string());
+    let recid = recid.filter(|&recid| recid <= 1).ok_or_else(invalid)?;
+    if r.is_zero() || s.is_zero() || s > SECP256K1N_HALF {
+        return Err(invalid());
+    }
+    let mut sig = [0u8; 64];
+    sig[..32].copy_from_slice(&r.to_be_bytes::<32>());
+    sig[32..].copy_from_slice(&s.to_be_bytes::<32>());
+    precompile::ecrecover(&sig, recid as u8, &hash).ok_or_else(invalid)
+}
+
+/// access list 的编码 [[address, [storage_key, ...]], ...]
+fn encode_access_list(access_list: &[(Address, Vec<U256>)], out: &mut Vec<u8>) {
+    let mut payload = Vec::new();
+    for (address, slots) in access_list {
+        let mut item = Vec::new();
+        address.encode(&mut item);
+        let keys: Vec<B256> = slots.iter().map(|&slot| slot.into()).collect();
+        keys.encode(&mut item);
+        payload.extend(encode_list(&item));
+    }
+    out.extend(encode_list(&payload));
+}
+
+fn decode_access_list(buf: &mut &[u8]) -> Result<Vec<(Address, Vec<U256>)>, EVMError> {
+    let mut list = Header::decode_bytes(buf, true)?;
+    let mut access_list = Vec::new();
+    while !list.is_empty() {
+        let item = &mut Header::decode_bytes(&mut list, true)?;
+        let address = Address::decode(item)?;
+        let keys = Vec::<B256>::decode(item)?;
+        if !item.is_empty() {
+            return Err(EVMError::InvalidRlp("invalid access list item".to_string()));
+        }
+        access_list.push((address, keys.into_iter().map(Into::into).collect()));
+    }
+    Ok(access_list)
+}
+
+/// `fake_exponential` (EIP-4844)，用来从 excess blob gas 计算 blob base fee
+pub fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> U256 {
+    let (factor, numerator, denominator) = (
+        U256::from(factor),
+        U256::from(numerator),
+        U256::from(denominator),
+    );
+    let mut i = U256::from(1);
+    let mut output = U256::ZERO;
+    let mut accum = factor * denominator;
+    while !accum.is_zero() {
+        output += accum;
+        accum = accum * numerator / (denominator * i);
+        i += U256::from(1);
+    }
+    output / denominator
+}
+
+/// 由 excess blob gas 计算 blob base fee
+pub fn blob_base_fee(excess_blob_gas: u64) -> U256 {
+    fake_exponential(1, excess_blob_gas, 3338477)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::InMemoryStateDB;
+    use crate::vm::Interpreter;
+
+    #[test]
+    fn test_intrinsic_gas() {
+        let mut msg = Message {
+            to: Some(Address::ZERO),
+            data: Bytes::from_static(&[0, 1, 2]),
+            access_list: vec![(Address::ZERO, vec![U256::ZERO, U256::from(1)])],
+            ..Default::default()
+        };
+        assert_eq!(
+            msg.intrinsic_gas(Fork::Cancun),
+            21000 + 4 + 32 + 2400 + 3800
+        );
+        msg.to = None;
+        msg.access_list.clear();
+        assert_eq!(msg.intrinsic_gas(Fork::London), 53000 + 36);
+        assert_eq!(msg.intrinsic_gas(Fork::Shanghai), 53000 + 36 + 2);
+    }
+
+    #[test]
+    fn test_decode_transaction() {
+        // EIP-155 中的例子
+        let raw = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+        let tx = SignedTransaction::decode(&raw).unwrap();
+        assert_eq!(tx.tx_type, LEGACY_TX_TYPE);
+        assert_eq!(tx.chain_id, Some(1));
+        assert_eq!(tx.nonce, 9);
+        assert_eq!(tx.value, U256::from(10).pow(U256::from(18)));
+        assert_eq!(
+            tx.signature_hash(),
+            alloy_primitives::b256!(
+                "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+            )
+        );
+        assert_eq!(
+            tx.recover_sender().unwrap(),
+            alloy_primitives::address!("9d8A62f656a8d1615C1294fd71e9CFb3E4855A4F")
+        );
+        assert_eq!(tx.encode(), raw);
+
+        let tx = SignedTransaction {
+            tx_type: EIP4844_TX_TYPE,
+            chain_id: Some(1),
+            max_priority_fee_per_gas: Some(U256::from(1)),
+            to: Some(Address::ZERO),
+            access_list: vec![(Address::ZERO, vec![U256::from(1)])],
+            max_fee_per_blob_gas: Some(U256::from(2)),
+            blob_hashes: vec![U256::from(3)],
+            v: 1,
+            r: U256::from(4),
+            s: U256::from(5),
+            ..Default::default()
+        };
+        assert_eq!(SignedTransaction::decode(&tx.encode()).unwrap(), tx);
+
+        let mut high_s = tx;
+        high_s.s = SECP256K1N_HALF + U256::from(1);
+        assert!(high_s.recover_sender().is_err());
+        assert!(SignedTransaction::decode(&[0x05, 0xc0]).is_err());
+        assert!(SignedTransaction::decode(&raw[..raw.len() - 1]).is_err());
+    }
+
+    /// 用私钥 1 签名，返回 (y parity, r, s)
+    fn sign(hash: B256) -> (u64, U256, U256) {
+        let key = k256::ecdsa::SigningKey::from_slice(&U256::from(1).to_be_bytes::<32>()).unwrap();
+        let (sig, recid) = key.sign_prehash_recoverable(hash.as_slice()).unwrap();
+        let bytes = sig.to_bytes();
+        (
+            recid.to_byte() as u64,
+            U256::from_be_slice(&bytes[..32]),
+            U256::from_be_slice(&bytes[32..]),
+        )
+    }
+
+    #[test]
+    fn test_set_code_transaction() {
+        // 私钥 1 对应的地址
+        let signer = alloy_primitives::address!("7E5F4552091A69125d5DfCb7b8C2659029395Bdf");
+        let mut authorization = Authorization {
+            address: Address::repeat_byte(0xaa),
+            nonce: 1,
+            ..Default::default()
+        };
+        let (y_parity, r, s) = sign(authorization.signature_hash());
+        authorization.y_parity = y_parity as u8;
+        authorization.r = r;
+        authorization.s = s;
+        assert_eq!(authorization.recover_authority().unwrap(), signer);
+
+        let mut tx = SignedTransaction {
+            tx_type: EIP7702_TX_TYPE,
+            chain_id: Some(1),
+            gas_price: U256::from(10),
+            max_priority_fee_per_gas: Some(U256::from(1)),
+            gas_limit: 100_000,
+            to: Some(Address::repeat_byte(0xbb)),
+            value: U256::from(7),
+            data: Bytes::from_static(&[1, 2]),
+            authorization_list: vec![authorization],
+            ..Default::default()
+        };
+        (tx.v, tx.r, tx.s) = sign(tx.signature_hash());
+        let decoded = SignedTransaction::decode(&tx.encode()).unwrap();
+        assert_eq!(decoded, tx);
+
+        // 不支持执行，不能转换成消息，直接构造的消息也不能执行
+        let mut blk_ctx = BlockContext::new();
+        blk_ctx.chain_id = U256::from(1);
+        assert!(matches!(
+            tx.to_message(&blk_ctx),
+            Err(EVMError::SetCodeUnsupported)
+        ));
+        let msg = tx.message(tx.recover_sender().unwrap());
+        assert_eq!(msg.caller, signer);
+        assert_eq!(msg.intrinsic_gas(Fork::Cancun), 21000 + 2 * 16 + 25000);
+        assert_eq!(
+            msg.call_args(),
+            Some((signer, signer, tx.to.unwrap(), tx.data.clone(), tx.value))
+        );
+
+        let mut vm = Interpreter::new(Box::new(InMemoryStateDB::new()), &blk_ctx);
+        assert!(matches!(
+            vm.transact(&msg),
+            Err(EVMError::SetCodeUnsupported)
+        ));
+
+        // 没有授权的 set code 交易无效
+        tx.authorization_list.clear();
+        assert!(SignedTransaction::decode(&tx.encode()).is_err());
+
+        // 其他类型的交易 chain id 必须和区块一致
+        tx.tx_type = EIP1559_TX_TYPE;
+        (tx.v, tx.r, tx.s) = sign(tx.signature_hash());
+        assert_eq!(tx.to_message(&blk_ctx).unwrap().caller, signer);
+        blk_ctx.chain_id = U256::from(2);
+        assert!(matches!(
+            tx.to_message(&blk_ctx),
+            Err(EVMError::InvalidTransaction(_))
+        ));
+    }
+
+    #[test]
+    fn test_blob_base_fee() {
+        assert_eq!(blob_base_fee(0), U256::from(1));
+        assert_eq!(blob_base_fee(10_000_000), U256::from(19));
+    }
+}
@@ -0,0 +1,653 @@
+//! Merkle Patricia Trie 的根哈希计算
+
+use std::cell::OnceCell;
+
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use alloy_rlp::{Encodable, Header};
+
+use crate::error::EVMError;
+
+use crate::genesis::GenesisAlloc;
+use crate::state::KECCAK_EMPTY;
+
+/// 空树的根哈希，keccak256(rlp(""))
+pub const EMPTY_ROOT: B256 =
+    alloy_primitives::b256!("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421");
+
+/// 由所有键值对计算根哈希，值是已经编码好的数据
+pub fn trie_root<K, V>(items: impl IntoIterator<Item = (K, V)>) -> B256
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    let mut trie = Trie::new();
+    for (key, value) in items {
+        trie.insert(key.as_ref(), value.as_ref().to_vec());
+    }
+    trie.root()
+}
+
+/// 键先经过 keccak256 的 secure trie
+pub fn secure_trie_root<K, V>(items: impl IntoIterator<Item = (K, V)>) -> B256
+where
+    K: AsRef<[u8]>,
+    V: AsRef<[u8]>,
+{
+    trie_root(
+        items
+            .into_iter()
+            .map(|(key, value)| (keccak256(key.as_ref()), value)),
+    )
+}
+
+/// 存储树中槽位的键
+pub fn storage_key(slot: U256) -> B256 {
+    keccak256(slot.to_be_bytes::<32>())
+}
+
+/// 账户存储的根哈希，值为 0 的槽不在树中
+pub fn storage_root<'a>(storage: impl IntoIterator<Item = (&'a U256, &'a U256)>) -> B256 {
+    trie_root(
+        storage
+            .into_iter()
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(slot, value)| (storage_key(*slot), alloy_rlp::encode(value))),
+    )
+}
+
+/// 账户在状态树中的编码 [nonce, balance, storage_root, code_hash]
+pub fn encode_account(nonce: u64, balance: U256, storage_root: B256, code_hash: B256) -> Vec<u8> {
+    let mut payload = Vec::new();
+    nonce.encode(&mut payload);
+    balance.encode(&mut payload);
+    storage_root.encode(&mut payload);
+    code_hash.encode(&mut payload);
+    encode_list(&payload)
+}
+
+/// 由 alloc 重新计算整个状态根，`InMemoryStateDB` 维护的是增量更新的状态根
+pub fn state_root(alloc: &GenesisAlloc) -> B256 {
+    secure_trie_root(alloc.iter().map(|(address, account)| {
+        let code_hash = match account.code.is_empty() {
+            true => KECCAK_EMPTY,
+            false => keccak256(&account.code),
+        };
+        let storage_root = storage_root(&account.storage);
+        (
+            address,
+            encode_account(account.nonce, account.balance, storage_root, code_hash),
+        )
+    }))
+}
+
+/// 日志列表的哈希 keccak256(rlp([[address, topics, data], ...]))，用于比较执行结果
+pub fn logs_hash(logs: &[(Address, Vec<U256>, Vec<u8>)]) -> B256 {
+    keccak256(encode_logs(logs))
+}
+
+fn encode_logs(logs: &[(Address, Vec<U256>, Vec<u8>)]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for (address, topics, data) in logs {
+        let mut log = Vec::new();
+        address.encode(&mut log);
+        let topics: Vec<B256> = topics.iter().map(|topic| B256::from(*topic)).collect();
+        topics.encode(&mut log);
+        data.as_slice().encode(&mut log);
+        payload.extend(encode_list(&log));
+    }
+    encode_list(&payload)
+}
+
+/// 给已经编码好的列表内容加上列表头
+pub(crate) fn encode_list(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 9);
+    Header {
+        list: true,
+        payload_length: payload.len(),
+    }
+    .encode(&mut out);
+    out.extend_from_slice(payload);
+    out
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// hex-prefix 编码，第一个半字节标记叶子节点和奇数长度
+fn hex_prefix(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 2 } else { 0 };
+    let mut out = Vec::with_capacity(nibbles.len() / 2 + 1);
+    let rest = if nibbles.len() % 2 == 1 {
+        out.push(((flag + 1) << 4) | nibbles[0]);
+        &nibbles[1..]
+    } else {
+        out.push(flag << 4);
+        nibbles
+    };
+    out.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    out
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Merkle Patricia Trie
+///
+/// 每个节点缓存自己的 RLP 编码，修改时只清除从根到被修改节点路径上的缓存，
+/// 所以 `root` 只重新计算被修改过的路径
+#[derive(Debug, Clone, Default)]
+pub struct Trie {
+    root: Option<Box<Node>>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    kind: NodeKind,
+    /// 节点的 RLP 编码，节点被修改时会重新创建，缓存随之清空
+    encoded: OnceCell<Vec<u8>>,
+}
+
+/// 路径都是半字节
+#[derive(Debug, Clone)]
+enum NodeKind {
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+    },
+    Branch {
+        children: [Option<Box<Node>>; 16],
+        value: Option<Vec<u8>>,
+    },
+}
+
+impl Trie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        let path = to_nibbles(key);
+        let mut path = path.as_slice();
+        let mut node = self.root.as_deref()?;
+        loop {
+            match &node.kind {
+                NodeKind::Leaf { path: leaf, value } => {
+                    return (leaf == path).then_some(value.as_slice());
+                }
+                NodeKind::Extension { path: ext, child } => {
+                    path = path.strip_prefix(ext.as_slice())?;
+                    node = child;
+                }
+                NodeKind::Branch { children, value } => match path.split_first() {
+                    None => return value.as_deref(),
+                    Some((&nibble, rest)) => {
+                        node = children[nibble as usize].as_deref()?;
+                        path = rest;
+                    }
+                },
+            }
+        }
+    }
+
+    /// 插入或者更新，空值等同于删除
+    pub fn insert(&mut self, key: &[u8], value: Vec<u8>) {
+        if value.is_empty() {
+            return self.remove(key);
+        }
+        self.root = Some(insert(self.root.take(), &to_nibbles(key), value));
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        if self.get(key).is_some() {
+            let root = self.root.take().unwrap();
+            self.root = remove(*root, &to_nibbles(key));
+        }
+    }
+
+    /// 根哈希，只重新编码上次计算之后被修改过的节点
+    pub fn root(&self) -> B256 {
+        match &self.root {
+            Some(root) => keccak256(root.encode()),
+            None => EMPTY_ROOT,
+        }
+    }
+
+    /// 从根到 `key` 的路径上的节点编码，内嵌在父节点中的节点不单独列出
+    ///
+    /// `key` 不存在时证明到路径中断的节点为止，可以用来证明不存在
+    pub fn proof(&self, key: &[u8]) -> Vec<Bytes> {
+        let path = to_nibbles(key);
+        let mut path = path.as_slice();
+        let mut proof = Vec::new();
+        let Some(mut node) = self.root.as_deref() else {
+            return proof;
+        };
+        loop {
+            let encoded = node.encode();
+            if proof.is_empty() || encoded.len() >= 32 {
+                proof.push(Bytes::copy_from_slice(encoded));
+            }
+            let next = match &node.kind {
+                NodeKind::Leaf { .. } => None,
+                NodeKind::Extension { path: ext, child } => path
+                    .strip_prefix(ext.as_slice())
+                    .map(|rest| (rest, child.as_ref())),
+                NodeKind::Branch { children, .. } => {
+                    path.split_first().and_then(|(&nibble, rest)| {
+                        Some((rest, children[nibble as usize].as_deref()?))
+                    })
+                }
+            };
+            match next {
+                Some((rest, child)) => {
+                    path = rest;
+                    node = child;
+                }
+                None => return proof,
+            }
+        }
+    }
+}
+
+impl Node {
+    fn new(kind: NodeKind) -> Box<Node> {
+        Box::new(Node {
+            kind,
+            encoded: OnceCell::new(),
+        })
+    }
+
+    fn leaf(path: &[u8], value: Vec<u8>) -> Box<Node> {
+        Node::new(NodeKind::Leaf {
+            path: path.to_vec(),
+            value,
+        })
+    }
+
+    /// 路径为空时直接返回子节点
+    fn extension(path: &[u8], child: Box<Node>) -> Box<Node> {
+        match path.is_empty() {
+            true => child,
+            false => Node::new(NodeKind::Extension {
+                path: path.to_vec(),
+                child,
+            }),
+        }
+    }
+
+    fn branch() -> Box<Node> {
+        Node::new(NodeKind::Branch {
+            children: Default::default(),
+            value: None,
+        })
+    }
+
+    /// 节点的 RLP 编码，缓存到节点被修改为止
+    fn encode(&self) -> &[u8] {
+        self.encoded.get_or_init(|| {
+            let mut payload = Vec::new();
+            match &self.kind {
+                NodeKind::Leaf { path, value } => {
+                    hex_prefix(path, true).as_slice().encode(&mut payload);
+                    value.as_slice().encode(&mut payload);
+                }
+                NodeKind::Extension { path, child } => {
+                    hex_prefix(path, false).as_slice().encode(&mut payload);
+                    child.encode_ref(&mut payload);
+                }
+                NodeKind::Branch { children, value } => {
+                    for child in children {
+                        match child {
+                            Some(child) => child.encode_ref(&mut payload),
+                            None => payload.push(alloy_rlp::EMPTY_STRING_CODE),
+                        }
+                    }
+                    value.as_deref().unwrap_or_default().encode(&mut payload);
+                }
+            }
+            encode_list(&payload)
+        })
+    }
+
+    /// 父节点中对子节点的引用，编码不足 32 字节时直接内嵌
+    fn encode_ref(&self, out: &mut Vec<u8>) {
+        let encoded = self.encode();
+        if encoded.len() < 32 {
+            out.extend_from_slice(encoded);
+        } else {
+            keccak256(encoded).encode(out);
+        }
+    }
+}
+
+/// 插入后返回新的节点，没有被修改的子树保留原来的节点和缓存
+fn insert(node: Option<Box<Node>>, path: &[u8], value: Vec<u8>) -> Box<Node> {
+    let Some(node) = node else {
+        return Node::leaf(path, value);
+    };
+    match node.kind {
+        NodeKind::Leaf {
+            path: leaf_path,
+            value: leaf_value,
+        } => {
+            if leaf_path == path {
+                return Node::leaf(path, value);
+            }
+            let common = common_prefix(&leaf_path, path);
+            let branch = insert(Some(Node::branch()), &leaf_path[common..], leaf_value);
+            let branch = insert(Some(branch), &path[common..], value);
+            Node::extension(&path[..common], branch)
+        }
+        NodeKind::Extension {
+            path: ext_path,
+            child,
+        } => {
+            let common = common_prefix(&ext_path, path);
+            if common == ext_path.len() {
+                let child = insert(Some(child), &path[common..], value);
+                return Node::extension(&ext_path, child);
+            }
+            let mut branch = Node::branch();
+            if let NodeKind::Branch { children, .. } = &mut branch.kind {
+                children[ext_path[common] as usize] =
+                    Some(Node::extension(&ext_path[common + 1..], child));
+            }
+            let branch = insert(Some(branch), &path[common..], value);
+            Node::extension(&path[..common], branch)
+        }
+        NodeKind::Branch {
+            mut children,
+            value: branch_value,
+        } => {
+            let branch_value = match path.split_first() {
+                None => Some(value),
+                Some((&nibble, rest)) => {
+                    let child = &mut children[nibble as usize];
+                    *child = Some(insert(child.take(), rest, value));
+                    branch_value
+                }
+            };
+            Node::new(NodeKind::Branch {
+                children,
+                value: branch_value,
+            })
+        }
+    }
+}
+
+/// 删除一个存在的键，分支节点只剩一项时和子节点合并
+fn remove(node: Node, path: &[u8]) -> Option<Box<Node>> {
+    match node.kind {
+        NodeKind::Leaf { .. } => None,
+        NodeKind::Extension {
+            path: ext_path,
+            child,
+        } => {
+            let child = remove(*child, &path[ext_path.len()..])?;
+            Some(join(&ext_path, child))
+        }
+        NodeKind::Branch {
+            mut children,
+            mut value,
+        } => {
+            match path.split_first() {
+                None => value = None,
+                Some((&nibble, rest)) => {
+                    let child = &mut children[nibble as usize];
+                    *child = remove(*child.take().unwrap(), rest);
+                }
+            }
+            let mut remaining = children.iter().enumerate().filter(|(_, c)| c.is_some());
+            match (remaining.next(), remaining.next(), value) {
+                (None, _, value) => value.map(|value| Node::leaf(&[], value)),
+                (Some((nibble, _)), None, None) => {
+                    let child = children[nibble].take().unwrap();
+                    Some(join(&[nibble as u8], child))
+                }
+                (_, _, value) => Some(Node::new(NodeKind::Branch { children, value })),
+            }
+        }
+    }
+}
+
+/// 在节点的路径前加上 `prefix`
+fn join(prefix: &[u8], node: Box<Node>) -> Box<Node> {
+    match node.kind {
+        NodeKind::Leaf { path, value } => Node::leaf(&[prefix, &path].concat(), value),
+        NodeKind::Extension { path, child } => Node::extension(&[prefix, &path].concat(), child),
+        NodeKind::Branch { .. } => Node::extension(prefix, node),
+    }
+}
+
+/// 用 `Trie::proof` 生成的证明验证 `key` 在根为 `root` 的树中的值
+///
+/// 返回证明的值，`None` 表示证明了 `key` 不存在；证明和根不匹配时返回错误
+pub fn verify_proof(root: B256, key: &[u8], proof: &[Bytes]) -> Result<Option<Vec<u8>>, EVMError> {
+    let invalid = |reason: &str| EVMError::InvalidProof(reason.to_string());
+    if proof.is_empty() {
+        return match root == EMPTY_ROOT {
+            true => Ok(None),
+            false => Err(invalid("empty proof for non-empty trie")),
+        };
+    }
+
+    let path = to_nibbles(key);
+    let mut path = path.as_slice();
+    let mut proof = proof.iter();
+    let mut next = ChildRef::Hash(root);
+    loop {
+        let node: &[u8] = match next {
+            ChildRef::Hash(hash) => {
+                let node = proof.next().ok_or_else(|| invalid("missing proof node"))?;
+                if keccak256(node) != hash {
+                    return Err(invalid("proof node hash mismatch"));
+                }
+                node
+            }
+            ChildRef::Inline(node) => node,
+        };
+        let items = decode_list(node).ok_or_else(|| invalid("malformed proof node"))?;
+        let child = match items.as_slice() {
+            [key_item, value] => {
+                let encoded_path =
+                    decode_string(key_item).ok_or_else(|| invalid("malformed path"))?;
+                let (node_path, leaf) =
+                    decode_hex_prefix(encoded_path).ok_or_else(|| invalid("malformed path"))?;
+                if leaf {
+                    let value = decode_string(value).ok_or_else(|| invalid("malformed value"))?;
+                    return Ok((node_path == path).then(|| value.to_vec()));
+                }
+                let Some(rest) = path.strip_prefix(node_path.as_slice()) else {
+                    return Ok(None);
+                };
+                path = rest;
+                value
+            }
+            [children @ .., value] if children.len() == 16 => match path.split_first() {
+                None => {
+                    let value = decode_string(value).ok_or_else(|| invalid("malformed value"))?;
+                    return Ok((!value.is_empty()).then(|| value.to_vec()));
+                }
+                Some((&nibble, rest)) => {
+                    path = rest;
+                    &children[nibble as usize]
+                }
+            },
+            _ => return Err(invalid("malformed proof node")),
+        };
+        next = match ChildRef::decode(child).ok_or_else(|| invalid("malformed child"))? {
+            Some(child) => child,
+            None => return Ok(None),
+        };
+    }
+}
+
+/// 证明中对子节点的引用
+enum ChildRef<'a> {
+    Hash(B256),
+    /// 内嵌在父节点中的子节点编码
+    Inline(&'a [u8]),
+}
+
+impl<'a> ChildRef<'a> {
+    /// 空字符串表示没有子节点
+    fn decode(item: &'a [u8]) -> Option<Option<Self>> {
+        let mut buf = item;
+        let header = Header::decode(&mut buf).ok()?;
+        match (header.list, header.payload_length) {
+            (true, _) => Some(Some(ChildRef::Inline(item))),
+            (false, 0) => Some(None),
+            (false, 32) => Some(Some(ChildRef::Hash(B256::from_slice(buf)))),
+            _ => None,
+        }
+    }
+}
+
+/// 拆分 RLP 列表，返回每一项包括头部在内的编码
+fn decode_list(data: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut buf = data;
+    let header = Header::decode(&mut buf).ok()?;
+    if !header.list || header.payload_length != buf.len() {
+        return None;
+    }
+    let mut items = Vec::new();
+    while !buf.is_empty() {
+        let mut rest = buf;
+        let header = Header::decode(&mut rest).ok()?;
+        let len = buf.len() - rest.len() + header.payload_length;
+        if len > buf.len() {
+            return None;
+        }
+        items.push(&buf[..len]);
+        buf = &buf[len..];
+    }
+    Some(items)
+}
+
+/// RLP 字符串的内容
+fn decode_string(item: &[u8]) -> Option<&[u8]> {
+    let mut buf = item;
+    let header = Header::decode(&mut buf).ok()?;
+    (!header.list && header.payload_length == buf.len()).then_some(buf)
+}
+
+/// `hex_prefix` 的逆运算，返回半字节路径和是否为叶子节点
+fn decode_hex_prefix(encoded: &[u8]) -> Option<(Vec<u8>, bool)> {
+    let (&first, rest) = encoded.split_first()?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return None;
+    }
+    let mut path = Vec::with_capacity(rest.len() * 2 + 1);
+    if flag & 1 == 1 {
+        path.push(first & 0x0f);
+    }
+    path.extend(to_nibbles(rest));
+    Some((path, flag & 2 == 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::b256;
+
+    #[test]
+    fn test_trie_root() {
+        assert_eq!(trie_root(Vec::<(&[u8], &[u8])>::new()), EMPTY_ROOT);
+        let items = [
+            ("do", "verb"),
+            ("dog", "puppy"),
+            ("doge", "coin"),
+            ("horse", "stallion"),
+        ];
+        assert_eq!(
+            trie_root(items),
+            b256!("5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84")
+        );
+    }
+
+    #[test]
+    fn test_trie_update() {
+        // 每次修改后增量计算的根哈希和重新构造的树一致
+        let mut trie = Trie::new();
+        let mut items = std::collections::BTreeMap::<Vec<u8>, Vec<u8>>::new();
+        for i in 0u32..300 {
+            let key = keccak256(i.to_be_bytes()).0[..(i % 7 + 1) as usize].to_vec();
+            match i % 5 {
+                4 => {
+                    let key = items.keys().nth(i as usize % items.len()).cloned().unwrap();
+                    trie.remove(&key);
+                    items.remove(&key);
+                }
+                _ => {
+                    trie.insert(&key, i.to_be_bytes().to_vec());
+                    items.insert(key, i.to_be_bytes().to_vec());
+                }
+            }
+            if i % 10 == 0 {
+                assert_eq!(trie.root(), trie_root(&items));
+            }
+        }
+        assert_eq!(trie.root(), trie_root(&items));
+        for (key, value) in &items {
+            assert_eq!(trie.get(key), Some(value.as_slice()));
+        }
+
+        for key in items.keys() {
+            trie.remove(key);
+        }
+        assert!(trie.is_empty());
+        assert_eq!(trie.root(), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn test_proof() {
+        let mut trie = Trie::new();
+        for (key, value) in [
+            ("do", "verb"),
+            ("dog", "puppy"),
+            ("doge", "coin"),
+            ("horse", "stallion"),
+        ] {
+            trie.insert(key.as_bytes(), value.as_bytes().to_vec());
+        }
+        let root = trie.root();
+        for key in ["do", "dog", "doge", "horse"] {
+            let proof = trie.proof(key.as_bytes());
+            let value = verify_proof(root, key.as_bytes(), &proof).unwrap();
+            assert_eq!(value.as_deref(), trie.get(key.as_bytes()));
+        }
+        for key in ["d", "dogs", "cat", "horses"] {
+            let proof = trie.proof(key.as_bytes());
+            assert_eq!(verify_proof(root, key.as_bytes(), &proof).unwrap(), None);
+        }
+
+        let proof = trie.proof(b"dog");
+        assert!(verify_proof(EMPTY_ROOT, b"dog", &proof).is_err());
+        assert!(verify_proof(root, b"dog", &proof[..proof.len() - 1]).is_err());
+        let mut tampered = proof.clone();
+        let last = tampered.last_mut().unwrap();
+        *last = [&last[..last.len() - 1], b"!"].concat().into();
+        assert!(verify_proof(root, b"dog", &tampered).is_err());
+        assert_eq!(verify_proof(EMPTY_ROOT, b"dog", &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_state_root() {
+        assert_eq!(state_root(&GenesisAlloc::new()), EMPTY_ROOT);
+        assert_eq!(
+            logs_hash(&[]),
+            b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347")
+        );
+    }
+}
@@ -1,321 +1,1092 @@
-use alloy_primitives::{keccak256, Address, B256, U256};
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
 
-use crate::opcode::{CALLCODE, CREATE, CREATE2, DELEGATECALL, STATICCALL};
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+
+use crate::opcode::{CALLCODE, CREATE, CREATE2, DELEGATECALL, SELFDESTRUCT, STATICCALL};
 use crate::u256::u256_to_address;
 use crate::{
+    breakpoint::{
+        read_range, writes_memory, Breakpoint, Event, Hit, StorageWatch, WatchedState, Watchpoint,
+    },
     context::{BlockContext, Context},
     error::EVMError,
+    execution::Execution,
+    fork::Fork,
+    gas,
+    instructions::access_account,
     opcode::{get_opcode_size, CALL, PUSH1, PUSH32},
-    opcode_table::OPCODE_TABLE,
-    state::StateDB,
-    u256::u256_to_usize,
+    opcode_table::{
+        make_gas_table, make_jump_table, make_stack_table, GasTable, JumpTable, StackTable,
+        OPCODE_TABLE,
+    },
+    precompile,
+    state::{InMemoryStateDB, StateDB},
+    transaction::{ExecutionResult, Message, MAX_BLOBS_PER_BLOCK},
 };
 
+/// 最大调用深度，超过后 CALL/CREATE 直接失败
+pub const CALL_DEPTH_LIMIT: usize = 1024;
+
+/// 系统调用 (EIP-4788) 的调用方
+pub const SYSTEM_ADDRESS: Address =
+    alloy_primitives::address!("fffffffffffffffffffffffffffffffffffffffe");
+/// 系统调用的 gas，不计入区块
+pub const SYSTEM_CALL_GAS: u64 = 30_000_000;
+
+/// 调用帧结束后，结果怎样交回父帧
+#[derive(Clone)]
+pub enum FrameKind {
+    /// 最外层调用，没有父帧
+    Root,
+    /// CALL 类指令，返回数据写回父帧内存，失败时状态回滚到 `snapshot`
+    Call {
+        ret_offset: usize,
+        ret_size: usize,
+        snapshot: usize,
+    },
+    /// CREATE 类指令，返回数据作为新合约的代码
+    Create { address: Address, snapshot: usize },
+}
+
+/// 调用栈中的一帧
+pub struct Frame {
+    pub ctx: Context,
+    pub kind: FrameKind,
+}
+
+/// 执行一条指令后交给外层循环的动作
+pub(crate) enum Action {
+    /// 继续执行当前帧
+    Continue,
+    /// 压入新的子调用帧
+    Call(Box<Frame>),
+    /// 当前帧执行结束
+    Return(Result<(), EVMError>),
+    /// 停在断点上，指令没有执行
+    Pause,
+}
+
 pub struct Interpreter<'a> {
     state: Box<dyn StateDB>,
     blk_ctx: &'a BlockContext,
+    fork: Fork,
+    jump_table: JumpTable,
+    stack_table: StackTable,
+    gas_table: GasTable,
+    pub(crate) frames: Vec<Frame>,
+    /// 指令跟踪的输出
+    trace: Option<Box<dyn Write>>,
+    instruction_count: u64,
+
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    watch_memory: bool,
+    /// 添加存储监视点后 `state` 被包装成 `WatchedState`，两者共享这份记录
+    storage_watch: Option<Rc<RefCell<StorageWatch>>>,
+    hits: Vec<Hit>,
+    /// 命中断点时停在指令执行前，`Execution` 逐步执行时打开
+    pub(crate) pause_on_break: bool,
+    /// 从断点继续执行时，跳过下一条指令的断点检查
+    pub(crate) skip_breakpoint: bool,
 }
 
 impl<'a> Interpreter<'a> {
     pub fn new(state: Box<dyn StateDB>, blk_ctx: &'a BlockContext) -> Self {
-        Self { state, blk_ctx }
+        Self::new_with_fork(state, blk_ctx, Fork::LATEST)
+    }
+
+    pub fn new_with_fork(state: Box<dyn StateDB>, blk_ctx: &'a BlockContext, fork: Fork) -> Self {
+        Self {
+            state,
+            blk_ctx,
+            fork,
+            jump_table: make_jump_table(fork),
+            stack_table: make_stack_table(),
+            gas_table: make_gas_table(),
+            frames: Vec::new(),
+            trace: None,
+            instruction_count: 0,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            watch_memory: false,
+            storage_watch: None,
+            hits: Vec::new(),
+            pause_on_break: false,
+            skip_breakpoint: false,
+        }
+    }
+
+    /// 设置后每条指令执行前写入 `out`，为 None 时关闭
+    pub fn set_trace(&mut self, out: Option<Box<dyn Write>>) {
+        self.trace = out;
+    }
+
+    pub fn state(&self) -> &dyn StateDB {
+        self.state.as_ref()
+    }
+
+    /// 取回状态，用于在同一个状态上继续执行下一笔交易
+    pub fn into_state(self) -> Box<dyn StateDB> {
+        self.state
+    }
+
+    pub fn fork(&self) -> Fork {
+        self.fork
+    }
+
+    /// 已执行的指令数，包括所有嵌套调用
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    /// 添加断点，返回命中时 `Event::Breakpoint` 中的编号
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    /// 添加监视点，返回命中时事件中的编号
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        let id = self.watchpoints.len();
+        match watchpoint {
+            Watchpoint::Storage { address, slot } => {
+                if self.storage_watch.is_none() {
+                    let watch = Rc::new(RefCell::new(StorageWatch::default()));
+                    let inner =
+                        std::mem::replace(&mut self.state, Box::new(InMemoryStateDB::new()));
+                    self.state = Box::new(WatchedState {
+                        inner,
+                        watch: watch.clone(),
+                    });
+                    self.storage_watch = Some(watch);
+                }
+                if let Some(watch) = &self.storage_watch {
+                    watch.borrow_mut().slots.push((id, address, slot));
+                }
+            }
+            Watchpoint::Memory { .. } => self.watch_memory = true,
+        }
+        self.watchpoints.push(watchpoint);
+        id
+    }
+
+    /// 到目前为止所有的命中，按发生顺序排列
+    pub fn hits(&self) -> &[Hit] {
+        &self.hits
     }
 
+    pub fn take_hits(&mut self) -> Vec<Hit> {
+        std::mem::take(&mut self.hits)
+    }
+
+    /// 执行 `ctx` 直到结束，嵌套调用由 `frames` 管理，不占用原生调用栈
     pub fn run_with_ctx(&mut self, ctx: &mut Context) -> Result<(), EVMError> {
+        debug_assert!(self.frames.is_empty());
+        self.pause_on_break = false;
+        self.frames.push(Frame {
+            ctx: std::mem::take(ctx),
+            kind: FrameKind::Root,
+        });
+
+        let (root, result) = self.run_frames();
+        *ctx = root;
+        result
+    }
+
+    /// 执行帧栈上的所有帧，直到最外层帧结束
+    pub(crate) fn run_frames(&mut self) -> (Context, Result<(), EVMError>) {
         loop {
-            let opcode = ctx.code[ctx.pc];
-            match OPCODE_TABLE.get(&opcode) {
-                Some((opcode_name, _, inst_fn)) => {
-                    // print the instruction
-                    match opcode {
-                        PUSH1..=PUSH32 => {
-                            let operand_size = (opcode - PUSH1 + 1) as usize;
-                            let operand =
-                                ctx.code[(ctx.pc + 1)..(ctx.pc + 1 + operand_size)].to_vec();
-                            println!(
-                                "{}{} 0x{}",
-                                " ".repeat(ctx.depth * 4),
-                                opcode_name,
-                                hex::encode(operand)
-                            );
-                        }
-                        _ => {
-                            println!(
-                                "{}{}",
-                                " ".repeat(ctx.depth * 4), // 每个深度级别缩进4个空格
-                                opcode_name
-                            );
-                        }
-                    };
-
-                    let result = match opcode {
-                        CALL => self.call(ctx),
-                        CALLCODE => self.call_code(ctx),
-                        STATICCALL => self.static_call(ctx),
-                        CREATE => self.create(ctx),
-                        CREATE2 => self.create2(ctx),
-                        DELEGATECALL => self.delegate_call(ctx),
-                        _ => {
-                            // execute the instruction
-                            inst_fn(ctx, &mut self.state, &self.blk_ctx)
-                        }
-                    };
-
-                    match result {
-                        Ok(_) => {
-                            ctx.pc += get_opcode_size(opcode);
-                        }
-                        Err(EVMError::Stop) => break,
-                        Err(e) => return Err(e),
-                    }
-                }
-                None => return Err(EVMError::InvalidOpcode(opcode)),
+            let mut frame = self.frames.pop().expect("frame stack is empty");
+            let action = self.run_frame(&mut frame.ctx);
+            if let Some(done) = self.handle_action(frame, action) {
+                return done;
             }
-            if ctx.pc >= ctx.code.len() {
-                break;
+        }
+    }
+
+    /// 在当前帧中连续执行，直到帧结束或者需要进入子调用
+    fn run_frame(&mut self, ctx: &mut Context) -> Action {
+        loop {
+            match self.execute(ctx) {
+                Action::Continue => {}
+                action => return action,
             }
         }
-        Ok(())
     }
 
-    pub fn run(
-        &mut self,
-        origin: Address,
-        from: Address,
-        to: Address,
-        args: Vec<u8>,
-        value: U256,
-    ) -> Result<(), EVMError> {
-        let mut ctx = Context::new();
-        ctx.contract = to;
-        ctx.code = self.state.get_code(to);
-        ctx.call_data = args;
-        ctx.value = value;
-        ctx.caller = from;
-        ctx.origin = origin;
+    /// 执行当前帧的下一条指令
+    pub(crate) fn execute(&mut self, ctx: &mut Context) -> Action {
+        if ctx.pc >= ctx.code.len() {
+            return Action::Return(Ok(()));
+        }
 
-        self.run_with_ctx(&mut ctx)?;
-        ctx.stack.print_stack();
-        println!();
-        ctx.memory.print_memory();
-        Ok(())
-    }
+        let pc = ctx.pc;
+        let opcode = ctx.code[pc];
+        if !self.breakpoints.is_empty() && !std::mem::take(&mut self.skip_breakpoint) {
+            if let Some(id) = self
+                .breakpoints
+                .iter()
+                .position(|bp| bp.matches(ctx, opcode))
+            {
+                self.hits.push(Hit::new(ctx, pc, Event::Breakpoint(id)));
+                if self.pause_on_break {
+                    return Action::Pause;
+                }
+            }
+        }
 
-    fn call(&mut self, ctx: &mut Context) -> Result<(), EVMError> {
-        // TODO 往后处理gas
-        let [gas, to, value, args_offset, args_size, ret_offset, ret_size] = ctx.stack.pop_n::<7>();
+        let Some(inst_fn) = self.jump_table[opcode as usize] else {
+            return Action::Return(Err(EVMError::InvalidOpcode(opcode)));
+        };
+        let (inputs, outputs) = self.stack_table[opcode as usize];
+        if let Err(e) = ctx.stack.check(inputs, outputs) {
+            return Action::Return(Err(e));
+        }
+        if let Err(e) = ctx.use_gas(self.gas_table[opcode as usize]) {
+            return Action::Return(Err(e));
+        }
 
-        let call_data = ctx
-            .memory
-            .read(u256_to_usize(args_offset), u256_to_usize(args_size));
+        if let Some(out) = &mut self.trace {
+            trace_instruction(out, ctx, opcode);
+        }
+        self.instruction_count += 1;
 
-        let mut new_ctx = Context::new();
-        new_ctx.contract = u256_to_address(to);
-        new_ctx.code = self.state.get_code(new_ctx.contract);
-        new_ctx.call_data = call_data;
-        new_ctx.value = value;
-        new_ctx.caller = ctx.origin;
-        new_ctx.depth = ctx.depth + 1;
+        let memory_before =
+            (self.watch_memory && writes_memory(opcode)).then(|| self.watched_memory(ctx));
+        let result = match opcode {
+            CALL | CALLCODE | DELEGATECALL | STATICCALL => self.call(ctx, opcode),
+            CREATE | CREATE2 => self.create(ctx, opcode),
+            SELFDESTRUCT => self.selfdestruct(ctx),
+            _ => {
+                // execute the instruction
+                inst_fn(ctx, &mut self.state, self.blk_ctx).map(|_| None)
+            }
+        };
+        if let Some(before) = memory_before {
+            self.check_memory(ctx, pc, before);
+        }
+        if self.storage_watch.is_some() {
+            self.check_storage(ctx, pc);
+        }
 
-        if !value.is_zero() {
-            match self.state.transfer(ctx.caller, new_ctx.contract, value) {
-                Ok(_) => {}
-                Err(EVMError::InsufficientBalance) => {
-                    ctx.stack.push(U256::ZERO);
-                    return Ok(());
+        match result {
+            Ok(child) => {
+                // 跳转指令已经设置了 pc
+                if ctx.pc == pc {
+                    ctx.pc += get_opcode_size(opcode);
                 }
-                Err(e) => {
-                    return Err(e);
+                match child {
+                    Some(frame) => Action::Call(Box::new(frame)),
+                    None => Action::Continue,
                 }
             }
+            Err(EVMError::Stop) => Action::Return(Ok(())),
+            Err(e) => Action::Return(Err(e)),
         }
+    }
 
-        // prepare state for transaction
-        self.state.prepare();
-        match self.run_with_ctx(&mut new_ctx) {
-            Ok(_) => {
-                ctx.stack.push(U256::from(1));
+    /// 被监视的内存范围的当前内容
+    fn watched_memory(&self, ctx: &Context) -> Vec<(usize, Vec<u8>)> {
+        let memory = ctx.memory.data();
+        self.watchpoints
+            .iter()
+            .enumerate()
+            .filter_map(|(id, watchpoint)| match *watchpoint {
+                Watchpoint::Memory { offset, size } => {
+                    Some((id, read_range(&memory, offset, size)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
 
-                // commit state for transaction
-                self.state.commit();
+    /// 和指令执行前的内容比较，记录发生变化的内存监视点
+    fn check_memory(&mut self, ctx: &Context, pc: usize, before: Vec<(usize, Vec<u8>)>) {
+        let memory = ctx.memory.data();
+        for (watchpoint, old) in before {
+            let Watchpoint::Memory { offset, size } = self.watchpoints[watchpoint] else {
+                unreachable!()
+            };
+            let new = read_range(&memory, offset, size);
+            if new != old {
+                let event = Event::MemoryChange {
+                    watchpoint,
+                    old,
+                    new,
+                };
+                self.hits.push(Hit::new(ctx, pc, event));
             }
-            Err(e) => {
-                ctx.stack.push(U256::ZERO);
+        }
+    }
+
+    /// 记录指令执行期间被监视的存储访问
+    fn check_storage(&mut self, ctx: &Context, pc: usize) {
+        if let Some(watch) = &self.storage_watch {
+            let events: Vec<Event> = watch.borrow_mut().events.drain(..).collect();
+            for event in &events {
+                // SSTORE 计算 gas 时读取的当前值不算作读取
+                if let Event::StorageRead { watchpoint, .. } = event {
+                    let written = events.iter().any(|e| {
+                        matches!(e, Event::StorageWrite { watchpoint: w, .. } if w == watchpoint)
+                    });
+                    if written {
+                        continue;
+                    }
+                }
+                self.hits.push(Hit::new(ctx, pc, event.clone()));
             }
         }
+    }
 
-        ctx.memory.write_with_size(
-            u256_to_usize(ret_offset),
-            u256_to_usize(ret_size),
-            new_ctx.return_data.as_slice(),
-        );
-        ctx.return_data = new_ctx.return_data;
+    /// 根据帧的执行结果调整帧栈，最外层帧结束时返回它的 Context 和结果
+    pub(crate) fn handle_action(
+        &mut self,
+        frame: Frame,
+        action: Action,
+    ) -> Option<(Context, Result<(), EVMError>)> {
+        let result = match action {
+            Action::Continue | Action::Pause => {
+                self.frames.push(frame);
+                return None;
+            }
+            Action::Call(child) => {
+                self.frames.push(frame);
+                self.frames.push(*child);
+                return None;
+            }
+            Action::Return(result) => result,
+        };
 
-        Ok(())
+        let Some(mut parent) = self.frames.pop() else {
+            return Some((frame.ctx, result));
+        };
+        self.return_to_parent(&mut parent.ctx, frame, result);
+        self.frames.push(parent);
+        None
     }
 
-    fn delegate_call(&mut self, ctx: &mut Context) -> Result<(), EVMError> {
-        // TODO 往后处理gas
-        let [gas, to, args_offset, args_size, ret_offset, ret_size] = ctx.stack.pop_n::<6>();
+    /// 把子帧的结果交回父帧
+    ///
+    /// 成功时把剩余 gas 还给父帧；REVERT 时撤销子帧的状态修改，剩余 gas 和返回数据照常交回；
+    /// 其他错误撤销状态修改并消耗子帧的所有 gas
+    fn return_to_parent(
+        &mut self,
+        parent: &mut Context,
+        child: Frame,
+        result: Result<(), EVMError>,
+    ) {
+        match child.kind {
+            FrameKind::Root => unreachable!("root frame has no parent"),
+            FrameKind::Call {
+                ret_offset,
+                ret_size,
+                snapshot,
+            } => {
+                let mut return_data = child.ctx.output.clone();
+                match result {
+                    Ok(_) => {
+                        parent.gas += child.ctx.gas;
+                        parent.stack.push(U256::from(1));
+                    }
+                    Err(EVMError::Revert) => {
+                        self.state.revert_to_snapshot(snapshot);
+                        parent.gas += child.ctx.gas;
+                        parent.stack.push(U256::ZERO);
+                    }
+                    Err(_) => {
+                        self.state.revert_to_snapshot(snapshot);
+                        parent.stack.push(U256::ZERO);
+                        return_data = Bytes::new();
+                    }
+                }
 
-        let call_data = ctx
-            .memory
-            .read(u256_to_usize(args_offset), u256_to_usize(args_size));
+                // 先释放子帧的内存，父帧才能写入返回数据
+                drop(child);
 
-        let mut new_ctx = Context::new();
+                let memory_before = self.watch_memory.then(|| self.watched_memory(parent));
+                let size = ret_size.min(return_data.len());
+                parent.memory.write(ret_offset, &return_data[..size]);
+                parent.return_data = return_data;
+                if let Some(before) = memory_before {
+                    // CALL 类指令都只有一个字节，pc 已经指向下一条指令
+                    self.check_memory(parent, parent.pc - 1, before);
+                }
+            }
+            FrameKind::Create { address, snapshot } => {
+                let mut gas = child.ctx.gas;
+                let return_data = child.ctx.output.clone();
+                drop(child);
 
-        new_ctx.contract = ctx.contract;
-        new_ctx.code = self.state.get_code(u256_to_address(to));
-        new_ctx.call_data = call_data;
-        new_ctx.caller = ctx.caller;
-        new_ctx.depth = ctx.depth + 1;
+                match result.and_then(|_| self.deploy_code(address, return_data.clone(), &mut gas))
+                {
+                    Ok(_) => {
+                        parent.gas += gas;
+                        parent.stack.push(address.into_word().into());
+                        parent.return_data = Bytes::new();
+                    }
+                    Err(EVMError::Revert) => {
+                        self.state.revert_to_snapshot(snapshot);
+                        parent.gas += gas;
+                        parent.stack.push(U256::ZERO);
+                        parent.return_data = return_data;
+                    }
+                    Err(_) => {
+                        self.state.revert_to_snapshot(snapshot);
+                        parent.stack.push(U256::ZERO);
+                        parent.return_data = Bytes::new();
+                    }
+                }
+            }
+        }
+    }
+
+    /// 初始化代码成功返回后，扣除存储代码的 gas 并保存为合约代码
+    fn deploy_code(
+        &mut self,
+        address: Address,
+        code: Bytes,
+        gas: &mut u64,
+    ) -> Result<(), EVMError> {
+        if code.len() > gas::MAX_CODE_SIZE {
+            return Err(EVMError::CodeSizeLimit);
+        }
+        // EIP-3541，0xEF 开头的代码留给 EOF
+        if self.fork >= Fork::London && code.first() == Some(&0xef) {
+            return Err(EVMError::InvalidCode);
+        }
+        let cost = gas::CODE_DEPOSIT_BYTE * code.len() as u64;
+        if *gas < cost {
+            return Err(EVMError::OutOfGas);
+        }
+        *gas -= cost;
+        self.state.set_code(address, code);
+        Ok(())
+    }
+
+    /// 执行一笔交易：校验、购买 gas、执行、退款并支付矿工费用，最后清理本交易的状态
+    ///
+    /// 交易无效时返回 `Err(EVMError::InvalidTransaction)`，读取外部状态出错时返回该错误，
+    /// 两种情况状态都不变；执行失败的交易仍然有效，结果放在 `ExecutionResult::result` 中
+    pub fn transact(&mut self, msg: &Message) -> Result<ExecutionResult, EVMError> {
+        let result = self.execute_message(msg)?;
+        self.state.finalize();
+        Ok(result)
+    }
+
+    /// 和 `transact` 一样执行交易，但是撤销所有修改，用于 eth_call 和估算 gas
+    pub fn simulate(&mut self, msg: &Message) -> Result<ExecutionResult, EVMError> {
+        let result = self.execute_message(msg)?;
+        // `prepare` 清空了 journal，本交易的所有修改都在 0 之后
+        self.state.revert_to_snapshot(0);
+        Ok(result)
+    }
+
+    /// `transact` 除了最后提交之外的部分
+    fn execute_message(&mut self, msg: &Message) -> Result<ExecutionResult, EVMError> {
+        let intrinsic_gas = self.validate(msg);
+        // 读取外部状态出错时读到的值不可信，交易无法执行
+        if let Some(e) = self.state.take_error() {
+            return Err(e);
+        }
+        let intrinsic_gas = intrinsic_gas?;
+        let blk_ctx = self.blk_ctx;
+        let gas_price = msg.effective_gas_price(blk_ctx.base_fee);
+        let blob_fee = U256::from(msg.blob_gas()) * blk_ctx.block_hash_fee;
 
-        // prepare state for transaction
         self.state.prepare();
-        match self.run_with_ctx(&mut new_ctx) {
-            Ok(_) => {
-                ctx.stack.push(U256::from(1));
+        self.state
+            .sub_balance(msg.caller, U256::from(msg.gas_limit) * gas_price + blob_fee)?;
+        self.state.set_nonce(msg.caller, msg.nonce + 1);
 
-                // commit state for transaction
-                self.state.commit();
+        let to = msg.to.unwrap_or_else(|| msg.caller.create(msg.nonce));
+        self.state.access_address(msg.caller);
+        self.state.access_address(to);
+        for address in precompile::addresses(self.fork) {
+            self.state.access_address(address);
+        }
+        // EIP-3651
+        if self.fork >= Fork::Shanghai {
+            self.state
+                .access_address(u256_to_address(blk_ctx.block_coinbase));
+        }
+        for (address, slots) in &msg.access_list {
+            self.state.access_address(*address);
+            for slot in slots {
+                self.state.access_slot(*address, *slot);
             }
-            Err(e) => {
-                ctx.stack.push(U256::ZERO);
+        }
+
+        let gas = msg.gas_limit - intrinsic_gas;
+        let snapshot = self.state.snapshot();
+        let (result, mut gas_left, output) = match msg.to {
+            Some(to) => self.transact_call(msg, to, gas),
+            None => self.transact_create(msg, to, gas),
+        };
+        match &result {
+            Ok(_) => {}
+            Err(EVMError::Revert) => self.state.revert_to_snapshot(snapshot),
+            Err(_) => {
+                self.state.revert_to_snapshot(snapshot);
+                gas_left = 0;
             }
         }
 
-        ctx.memory.write_with_size(
-            u256_to_usize(ret_offset),
-            u256_to_usize(ret_size),
-            new_ctx.return_data.as_slice(),
+        let refund = self
+            .state
+            .get_refund()
+            .min((msg.gas_limit - gas_left) / gas::MAX_REFUND_QUOTIENT);
+        gas_left += refund;
+        let gas_used = msg.gas_limit - gas_left;
+        self.state
+            .add_balance(msg.caller, U256::from(gas_left) * gas_price);
+        let tip = match self.fork >= Fork::London {
+            true => gas_price - blk_ctx.base_fee,
+            false => gas_price,
+        };
+        self.state.add_balance(
+            u256_to_address(blk_ctx.block_coinbase),
+            U256::from(gas_used) * tip,
         );
-        ctx.return_data = new_ctx.return_data;
 
-        Ok(())
+        if let Some(e) = self.state.take_error() {
+            self.state.revert_to_snapshot(0);
+            return Err(e);
+        }
+        let logs = self.state.logs().to_vec();
+        Ok(ExecutionResult {
+            contract_address: (msg.to.is_none() && result.is_ok()).then_some(to),
+            result,
+            gas_used,
+            output,
+            logs,
+        })
     }
 
-    fn call_code(&mut self, ctx: &mut Context) -> Result<(), EVMError> {
-        // TODO 往后处理gas
-        let [gas, to, args_offset, args_size, ret_offset, ret_size] = ctx.stack.pop_n::<6>();
-
-        let call_data = ctx
-            .memory
-            .read(u256_to_usize(args_offset), u256_to_usize(args_size));
+    /// 区块开始时的系统调用：由 `SYSTEM_ADDRESS` 调用 `to`，不校验、不收费，
+    /// 调用失败时回滚
+    pub fn system_call(&mut self, to: Address, data: Bytes) -> Result<Bytes, EVMError> {
+        self.state.prepare();
+        self.state.access_address(to);
+        let snapshot = self.state.snapshot();
+        let mut ctx = self.call_context(SYSTEM_ADDRESS, SYSTEM_ADDRESS, to, data, U256::ZERO);
+        ctx.gas = SYSTEM_CALL_GAS;
+        let result = self.run_with_ctx(&mut ctx);
+        if result.is_err() {
+            self.state.revert_to_snapshot(snapshot);
+        }
+        if let Some(e) = self.state.take_error() {
+            self.state.revert_to_snapshot(snapshot);
+            return Err(e);
+        }
+        self.state.finalize();
+        result.map(|_| ctx.output)
+    }
 
-        let mut new_ctx = Context::new();
+    /// 检查交易是否可以被打包，返回 intrinsic gas
+    fn validate(&self, msg: &Message) -> Result<u64, EVMError> {
+        let invalid = |reason: &str| Err(EVMError::InvalidTransaction(reason.to_string()));
+        let blk_ctx = self.blk_ctx;
 
-        new_ctx.contract = ctx.contract;
-        new_ctx.caller = u256_to_address(to);
-        new_ctx.code = self.state.get_code(new_ctx.caller);
-        new_ctx.call_data = call_data;
-        new_ctx.depth = ctx.depth + 1;
+        if let Some(priority_fee) = msg.max_priority_fee_per_gas {
+            if self.fork < Fork::London {
+                return invalid("EIP-1559 transaction before London");
+            }
+            if priority_fee > msg.gas_price {
+                return invalid("max priority fee per gas higher than max fee per gas");
+            }
+        }
+        if self.fork >= Fork::London && msg.gas_price < blk_ctx.base_fee {
+            return invalid("max fee per gas less than block base fee");
+        }
 
-        // prepare state for transaction
-        self.state.prepare();
-        match self.run_with_ctx(&mut new_ctx) {
-            Ok(_) => {
-                ctx.stack.push(U256::from(1));
+        // 还没有实现 EIP-7702 的代码委托
+        if !msg.authorization_list.is_empty() {
+            return Err(EVMError::SetCodeUnsupported);
+        }
 
-                // commit state for transaction
-                self.state.commit();
+        if let Some(max_fee_per_blob_gas) = msg.max_fee_per_blob_gas {
+            if self.fork < Fork::Cancun {
+                return invalid("blob transaction before Cancun");
             }
-            Err(e) => {
-                ctx.stack.push(U256::ZERO);
+            if msg.to.is_none() {
+                return invalid("blob transaction cannot create contract");
+            }
+            if msg.blob_hashes.is_empty() || msg.blob_hashes.len() > MAX_BLOBS_PER_BLOCK {
+                return invalid("invalid number of blobs");
+            }
+            // versioned hash 的第一个字节是 KZG 版本号
+            if msg.blob_hashes.iter().any(|hash| hash.byte(31) != 0x01) {
+                return invalid("invalid blob versioned hash");
+            }
+            if max_fee_per_blob_gas < blk_ctx.block_hash_fee {
+                return invalid("max fee per blob gas less than blob base fee");
             }
         }
 
-        ctx.memory.write_with_size(
-            u256_to_usize(ret_offset),
-            u256_to_usize(ret_size),
-            new_ctx.return_data.as_slice(),
-        );
-        ctx.return_data = new_ctx.return_data;
+        if U256::from(msg.gas_limit) > blk_ctx.block_gas_limit {
+            return invalid("gas limit exceeds block gas limit");
+        }
+        let intrinsic_gas = msg.intrinsic_gas(self.fork);
+        if intrinsic_gas > msg.gas_limit {
+            return invalid("intrinsic gas too low");
+        }
+        if msg.to.is_none()
+            && self.fork >= Fork::Shanghai
+            && msg.data.len() > gas::MAX_INITCODE_SIZE
+        {
+            return invalid("max initcode size exceeded");
+        }
 
-        Ok(())
+        let nonce = self.state.get_nonce(msg.caller);
+        if nonce != msg.nonce {
+            return invalid(&format!(
+                "nonce mismatch: expected {nonce}, got {}",
+                msg.nonce
+            ));
+        }
+        if nonce == u64::MAX {
+            return invalid("nonce overflow");
+        }
+        // EIP-3607
+        if self.state.get_code_size(msg.caller) != 0 {
+            return invalid("sender is not an EOA");
+        }
+
+        match msg.max_cost() {
+            Some(cost) if cost <= self.state.get_balance(msg.caller) => Ok(intrinsic_gas),
+            _ => invalid("insufficient funds for gas * price + value"),
+        }
     }
 
-    fn static_call(&mut self, ctx: &mut Context) -> Result<(), EVMError> {
-        let [gas, to, args_offset, args_size, ret_offset, ret_size] = ctx.stack.pop_n::<6>();
+    /// 交易的最外层调用，返回执行结果、剩余 gas 和返回数据
+    fn transact_call(
+        &mut self,
+        msg: &Message,
+        to: Address,
+        gas: u64,
+    ) -> (Result<(), EVMError>, u64, Bytes) {
+        if let Err(e) = self.state.transfer(msg.caller, to, msg.value) {
+            return (Err(e), 0, Bytes::new());
+        }
+        if precompile::is_precompile(to, self.fork) {
+            return match precompile::run(to, &msg.data, gas) {
+                Ok((used, output)) => (Ok(()), gas - used, output),
+                Err(e) => (Err(e), 0, Bytes::new()),
+            };
+        }
 
-        let call_data = ctx
-            .memory
-            .read(u256_to_usize(args_offset), u256_to_usize(args_size));
+        let mut ctx = self.call_context(msg.caller, msg.caller, to, msg.data.clone(), msg.value);
+        ctx.gas = gas;
+        let result = self.run_with_ctx(&mut ctx);
+        (result, ctx.gas, ctx.output)
+    }
 
-        let mut new_ctx = Context::new();
-        new_ctx.contract = u256_to_address(to);
-        new_ctx.code = self.state.get_code(new_ctx.contract);
-        new_ctx.call_data = call_data;
-        new_ctx.caller = ctx.origin;
-        new_ctx.depth = ctx.depth + 1;
+    /// 交易创建合约，初始化代码就是交易数据
+    fn transact_create(
+        &mut self,
+        msg: &Message,
+        address: Address,
+        gas: u64,
+    ) -> (Result<(), EVMError>, u64, Bytes) {
+        if let Err(e) = self.create_account(msg.caller, address, msg.value) {
+            return (Err(e), 0, Bytes::new());
+        }
 
-        // prepare state for transaction
-        self.state.prepare();
-        match self.run_with_ctx(&mut new_ctx) {
-            Ok(_) => {
-                ctx.stack.push(U256::from(1));
+        let mut ctx = Context::new();
+        ctx.contract = address;
+        ctx.caller = msg.caller;
+        ctx.origin = msg.caller;
+        ctx.value = msg.value;
+        ctx.code = msg.data.clone();
+        ctx.gas = gas;
+        let result = self.run_with_ctx(&mut ctx);
+        let mut gas_left = ctx.gas;
+        match result.and_then(|_| self.deploy_code(address, ctx.output.clone(), &mut gas_left)) {
+            Ok(_) => (Ok(()), gas_left, Bytes::new()),
+            Err(e) => (Err(e), gas_left, ctx.output),
+        }
+    }
+
+    pub fn run(
+        &mut self,
+        origin: Address,
+        from: Address,
+        to: Address,
+        args: Bytes,
+        value: U256,
+    ) -> Result<(), EVMError> {
+        let mut ctx = self.call_context(origin, from, to, args, value);
+
+        self.run_with_ctx(&mut ctx)
+    }
+
+    /// 和 `run` 一样准备调用，但返回可以逐条执行的 `Execution`
+    pub fn start_call(
+        &mut self,
+        origin: Address,
+        from: Address,
+        to: Address,
+        args: Bytes,
+        value: U256,
+    ) -> Execution<'_, 'a> {
+        let ctx = self.call_context(origin, from, to, args, value);
+        self.start(ctx)
+    }
+
+    /// 以 `ctx` 为最外层帧开始执行，之后由 `Execution` 控制
+    pub fn start(&mut self, ctx: Context) -> Execution<'_, 'a> {
+        debug_assert!(self.frames.is_empty());
+        self.pause_on_break = true;
+        self.frames.push(Frame {
+            ctx,
+            kind: FrameKind::Root,
+        });
+        Execution::new(self)
+    }
+
+    fn call_context(
+        &self,
+        origin: Address,
+        from: Address,
+        to: Address,
+        args: Bytes,
+        value: U256,
+    ) -> Context {
+        let mut ctx = Context::new();
+        ctx.contract = to;
+        ctx.code = self.state.get_code(to);
+        ctx.call_data = args;
+        ctx.value = value;
+        ctx.caller = from;
+        ctx.origin = origin;
+        ctx
+    }
+
+    /// 子调用帧，`ret_offset`/`ret_size` 是返回数据在父帧内存中的位置
+    fn call_frame(ctx: Context, ret_offset: usize, ret_size: usize, snapshot: usize) -> Frame {
+        Frame {
+            ctx,
+            kind: FrameKind::Call {
+                ret_offset,
+                ret_size,
+                snapshot,
+            },
+        }
+    }
+
+    /// CALL/CALLCODE/DELEGATECALL/STATICCALL，调用预编译合约时直接在当前帧完成
+    fn call(&mut self, ctx: &mut Context, opcode: u8) -> Result<Option<Frame>, EVMError> {
+        let gas = ctx.stack.pop();
+        let to = u256_to_address(ctx.stack.pop());
+        let value = match opcode {
+            CALL | CALLCODE => ctx.stack.pop(),
+            _ => U256::ZERO,
+        };
+        let [args_offset, args_size, ret_offset, ret_size] = ctx.stack.pop_n::<4>();
+
+        if opcode == CALL && ctx.is_static && !value.is_zero() {
+            return Err(EVMError::WriteProtection);
+        }
+        let (args_offset, args_size) = ctx.expand_memory(args_offset, args_size)?;
+        let (ret_offset, ret_size) = ctx.expand_memory(ret_offset, ret_size)?;
+        access_account(ctx, &mut self.state, to)?;
+        if !value.is_zero() {
+            ctx.use_gas(gas::CALL_VALUE)?;
+            if opcode == CALL && self.state.is_empty(to) {
+                ctx.use_gas(gas::NEW_ACCOUNT)?;
             }
-            Err(e) => {
-                ctx.stack.push(U256::ZERO);
+        }
+
+        // 子调用最多得到剩余 gas 的 63/64，带 value 时额外得到 2300
+        let mut child_gas = gas
+            .saturating_to::<u64>()
+            .min(gas::all_but_one_64th(ctx.gas));
+        ctx.use_gas(child_gas)?;
+        if !value.is_zero() {
+            child_gas += gas::CALL_STIPEND;
+        }
+
+        ctx.return_data = Bytes::new();
+        if ctx.depth >= CALL_DEPTH_LIMIT || self.state.get_balance(ctx.contract) < value {
+            ctx.gas += child_gas;
+            ctx.stack.push(U256::ZERO);
+            return Ok(None);
+        }
+
+        let call_data: Bytes = ctx.memory.read(args_offset, args_size).into();
+        let snapshot = self.state.snapshot();
+        // 不存在的账户只有收到 value 时才会被创建
+        if opcode == CALL && (!value.is_zero() || self.state.exists(to)) {
+            self.state.transfer(ctx.contract, to, value)?;
+        }
+
+        if precompile::is_precompile(to, self.fork) {
+            match precompile::run(to, &call_data, child_gas) {
+                Ok((used, output)) => {
+                    ctx.gas += child_gas - used;
+                    let size = ret_size.min(output.len());
+                    ctx.memory.write(ret_offset, &output[..size]);
+                    ctx.return_data = output;
+                    ctx.stack.push(U256::from(1));
+                }
+                Err(_) => {
+                    self.state.revert_to_snapshot(snapshot);
+                    ctx.stack.push(U256::ZERO);
+                }
             }
+            return Ok(None);
         }
 
-        ctx.memory.write_with_size(
-            u256_to_usize(ret_offset),
-            u256_to_usize(ret_size),
-            new_ctx.return_data.as_slice(),
-        );
-        ctx.return_data = new_ctx.return_data;
+        let mut new_ctx = Context::new_frame(ctx);
+        new_ctx.gas = child_gas;
+        new_ctx.code = self.state.get_code(to);
+        new_ctx.call_data = call_data;
+        match opcode {
+            CALL | STATICCALL => {
+                new_ctx.contract = to;
+                new_ctx.caller = ctx.contract;
+                new_ctx.value = value;
+            }
+            CALLCODE => {
+                new_ctx.contract = ctx.contract;
+                new_ctx.caller = ctx.contract;
+                new_ctx.value = value;
+            }
+            _ => {
+                new_ctx.contract = ctx.contract;
+                new_ctx.caller = ctx.caller;
+                new_ctx.value = ctx.value;
+            }
+        }
+        new_ctx.is_static |= opcode == STATICCALL;
 
-        Ok(())
+        Ok(Some(Self::call_frame(
+            new_ctx, ret_offset, ret_size, snapshot,
+        )))
     }
 
-    fn create(&mut self, ctx: &mut Context) -> Result<(), EVMError> {
+    /// CREATE/CREATE2
+    fn create(&mut self, ctx: &mut Context, opcode: u8) -> Result<Option<Frame>, EVMError> {
+        if ctx.is_static {
+            return Err(EVMError::WriteProtection);
+        }
         let [value, offset, size] = ctx.stack.pop_n::<3>();
-        let code = ctx.memory.read(u256_to_usize(offset), u256_to_usize(size));
+        let salt = (opcode == CREATE2).then(|| ctx.stack.pop());
+
+        let (offset, size) = ctx.expand_memory(offset, size)?;
+        let words = gas::words(size as u64);
+        // EIP-3860 限制初始化代码的大小并按字收费
+        if self.fork >= Fork::Shanghai {
+            if size > gas::MAX_INITCODE_SIZE {
+                return Err(EVMError::CodeSizeLimit);
+            }
+            ctx.use_gas(gas::INITCODE_WORD * words)?;
+        }
+        if salt.is_some() {
+            ctx.use_gas(gas::KECCAK256_WORD * words)?;
+        }
+        let code: Bytes = ctx.memory.read(offset, size).into();
 
-        let contract_address = ctx.caller.create(self.state.get_nonce(ctx.caller));
+        let child_gas = gas::all_but_one_64th(ctx.gas);
+        ctx.use_gas(child_gas)?;
 
-        if !value.is_zero() {
-            self.state.transfer(ctx.contract, contract_address, value)?;
+        ctx.return_data = Bytes::new();
+        let nonce = self.state.get_nonce(ctx.contract);
+        if ctx.depth >= CALL_DEPTH_LIMIT
+            || self.state.get_balance(ctx.contract) < value
+            || nonce == u64::MAX
+        {
+            ctx.gas += child_gas;
+            ctx.stack.push(U256::ZERO);
+            return Ok(None);
         }
+        self.state.set_nonce(ctx.contract, nonce + 1);
 
-        let contract_code = self.init_contract(ctx, contract_address, code)?;
-        self.state.set_code(contract_address, contract_code);
-        ctx.stack.push(contract_address.into_word().into());
-        Ok(())
+        let address = match salt {
+            Some(salt) => ctx.contract.create2(B256::from(salt), keccak256(&code)),
+            None => ctx.contract.create(nonce),
+        };
+        self.state.access_address(address);
+        let snapshot = match self.create_account(ctx.contract, address, value) {
+            Ok(snapshot) => snapshot,
+            Err(_) => {
+                ctx.stack.push(U256::ZERO);
+                return Ok(None);
+            }
+        };
+
+        let mut new_ctx = Context::new_frame(ctx);
+        new_ctx.contract = address;
+        new_ctx.caller = ctx.contract;
+        new_ctx.value = value;
+        new_ctx.code = code;
+        new_ctx.gas = child_gas;
+
+        Ok(Some(Frame {
+            ctx: new_ctx,
+            kind: FrameKind::Create { address, snapshot },
+        }))
     }
 
-    fn create2(&mut self, ctx: &mut Context) -> Result<(), EVMError> {
-        let [value, offset, size, salt] = ctx.stack.pop_n::<4>();
+    /// 创建合约账户并转入 value，返回创建前的 snapshot
+    ///
+    /// 地址上已经有代码或者 nonce 时冲突，调用方消耗掉所有给子帧的 gas
+    fn create_account(
+        &mut self,
+        caller: Address,
+        address: Address,
+        value: U256,
+    ) -> Result<usize, EVMError> {
+        if self.state.get_nonce(address) != 0 || self.state.get_code_size(address) != 0 {
+            return Err(EVMError::CreateCollision);
+        }
+        let snapshot = self.state.snapshot();
+        // 地址上原有的余额保留
+        let balance = self.state.get_balance(address);
+        self.state.create_object(address);
+        self.state.add_balance(address, balance);
+        self.state.set_nonce(address, 1);
+        self.state.transfer(caller, address, value)?;
+        Ok(snapshot)
+    }
 
-        let code = ctx.memory.read(u256_to_usize(offset), u256_to_usize(size));
-        let code_hash = keccak256(&code);
-        let contract_address = ctx.caller.create2(B256::from(salt), B256::from(code_hash));
+    /// SELFDESTRUCT，Cancun 之后只有同一交易中创建的合约才会被删除 (EIP-6780)
+    fn selfdestruct(&mut self, ctx: &mut Context) -> Result<Option<Frame>, EVMError> {
+        if ctx.is_static {
+            return Err(EVMError::WriteProtection);
+        }
+        let beneficiary = u256_to_address(ctx.stack.pop());
+        let balance = self.state.get_balance(ctx.contract);
+        if self.state.access_address(beneficiary) {
+            ctx.use_gas(gas::COLD_ACCOUNT_ACCESS)?;
+        }
+        if !balance.is_zero() && self.state.is_empty(beneficiary) {
+            ctx.use_gas(gas::NEW_ACCOUNT)?;
+        }
 
-        if !value.is_zero() {
-            self.state.transfer(ctx.contract, contract_address, value)?;
+        let destroy = self.fork < Fork::Cancun || self.state.is_created(ctx.contract);
+        if beneficiary != ctx.contract {
+            self.state.transfer(ctx.contract, beneficiary, balance)?;
+        } else if destroy {
+            // 转给自己的余额随账户一起销毁
+            self.state.sub_balance(ctx.contract, balance)?;
+        }
+        if destroy {
+            self.state.selfdestruct(ctx.contract);
         }
+        Err(EVMError::Stop)
+    }
+}
 
-        let contract_code = self.init_contract(ctx, contract_address, code)?;
-        self.state.set_code(contract_address, contract_code);
-        ctx.stack.push(contract_address.into_word().into());
-        Ok(())
+fn trace_instruction(out: &mut dyn Write, ctx: &Context, opcode: u8) {
+    let name = OPCODE_TABLE[&opcode].name;
+    // 每个深度级别缩进4个空格
+    let indent = " ".repeat(ctx.depth * 4);
+    // 写入跟踪失败不影响执行
+    let _ = match opcode {
+        PUSH1..=PUSH32 => {
+            let operand = &ctx.code[(ctx.pc + 1)..(ctx.pc + get_opcode_size(opcode))];
+            writeln!(out, "{}{} 0x{}", indent, name, hex::encode(operand))
+        }
+        _ => writeln!(out, "{}{}", indent, name),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::deploy;
+
+    #[test]
+    fn test_call_depth_limit() {
+        // 合约不断调用自己，直到达到调用深度上限
+        let (state, caller, contract) = deploy(
+            r#"
+            PUSH1 0
+            PUSH1 0
+            PUSH1 0
+            PUSH1 0
+            PUSH1 0
+            ADDRESS
+            GAS
+            CALL
+            STOP
+        "#,
+        );
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        vm.run(caller, caller, contract, Bytes::new(), U256::ZERO)
+            .unwrap();
+        assert_eq!(vm.instruction_count(), (CALL_DEPTH_LIMIT as u64 + 1) * 9);
     }
 
-    fn init_contract(
-        &mut self,
-        ctx: &mut Context,
-        contract_address: Address,
-        code: Vec<u8>,
-    ) -> Result<Vec<u8>, EVMError> {
-        let mut new_ctx = Context::new();
-        new_ctx.contract = contract_address;
-        new_ctx.code = code;
-        new_ctx.caller = ctx.caller;
-        new_ctx.depth = ctx.depth + 1;
+    #[test]
+    fn test_call_return_data() {
+        // 初始化代码返回的运行时代码把 0x2a 写入内存并返回
+        let (state, caller, contract) = deploy(
+            r#"
+            PUSH19 0x69602a60005260206000f3600052600a6016f3
+            PUSH1 0
+            MSTORE
+            PUSH1 0x13
+            PUSH1 0x0d
+            PUSH1 0
+            CREATE
+            PUSH1 0x20
+            PUSH1 0x40
+            PUSH1 0
+            PUSH1 0
+            PUSH1 0
+            DUP6
+            GAS
+            CALL
+            STOP
+        "#,
+        );
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let mut ctx = Context::new();
+        ctx.contract = contract;
+        ctx.code = vm.state.get_code(contract);
+        ctx.caller = caller;
+        vm.run_with_ctx(&mut ctx).unwrap();
+
+        assert_eq!(ctx.stack.peek(), U256::from(1));
+        assert_eq!(ctx.memory.read32(0x40), U256::from(0x2a));
+        assert_eq!(ctx.return_data.len(), 32);
+    }
+
+    #[test]
+    fn test_stack_underflow() {
+        let (state, caller, contract) = deploy("PUSH1 1\nADD");
+        let blk_ctx = BlockContext::new();
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let result = vm.run(caller, caller, contract, Bytes::new(), U256::ZERO);
+        assert!(matches!(result, Err(EVMError::StackUnderflow)));
+
+        // 空栈上执行 ADD
+        let (state, caller, contract) = deploy("ADD");
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let result = vm.run(caller, caller, contract, Bytes::new(), U256::ZERO);
+        assert!(matches!(result, Err(EVMError::StackUnderflow)));
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let blk_ctx = BlockContext::new();
 
-        self.run_with_ctx(&mut new_ctx)?;
+        // 1024 次 PUSH 正好填满栈
+        let (state, caller, contract) = deploy(&"PUSH1 1\n".repeat(1024));
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        vm.run(caller, caller, contract, Bytes::new(), U256::ZERO)
+            .unwrap();
 
-        Ok(new_ctx.return_data)
+        // 第 1025 次 PUSH 溢出
+        let (state, caller, contract) = deploy(&"PUSH1 1\n".repeat(1025));
+        let mut vm = Interpreter::new(Box::new(state), &blk_ctx);
+        let result = vm.run(caller, caller, contract, Bytes::new(), U256::ZERO);
+        assert!(matches!(result, Err(EVMError::StackOverflow)));
     }
 }